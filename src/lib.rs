@@ -1,6 +1,18 @@
 #![feature(pattern)]
+// Without `std` only what needs nothing but `core` is built: the traits,
+// the patterns for borrowed haystacks and the lazy consumers. The tests
+// always link `std`, to compare against it.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-pub use std::str::pattern as v1;
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(feature = "alloc")]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "regex")]
+extern crate regex;
+
+pub use core::str::pattern as v1;
 pub mod v2;
 pub mod v3;
 pub mod v4;
@@ -23,6 +23,8 @@ pub unsafe trait ReverseSearcher<'a, H: ?Sized>: Searcher<'a, H> {
     fn next_reject_back(&mut self) -> Option<(usize, usize)>;
 }
 
+// Promises that searching backwards yields exactly the same ranges as
+// searching forwards, just starting from the other end.
 pub trait DoubleEndedSearcher<'a, H: ?Sized>: ReverseSearcher<'a, H> {}
 
 
@@ -31,12 +33,22 @@ pub mod string {
 
     pub struct Ascii(u8);
 
+    #[derive(Clone)]
     pub struct AsciiSearcher<'a> {
         haystack: &'a str,
         pos: usize,
         ascii: u8,
     }
 
+    impl<'a> ::core::fmt::Debug for AsciiSearcher<'a> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_struct("AsciiSearcher")
+                .field("pos", &self.pos)
+                .field("ascii", &(self.ascii as char))
+                .finish()
+        }
+    }
+
     unsafe impl<'a> Searcher<'a, str> for AsciiSearcher<'a> {
         fn haystack(&self) -> &'a str { self.haystack }
 
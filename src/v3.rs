@@ -29,6 +29,8 @@ pub unsafe trait ReverseSearcher<'a, H: ?Sized + SearchCursor>: Searcher<'a, H>
     fn next_reject_back(&mut self) -> Option<H::SearchCursor>;
 }
 
+// Promises that searching backwards yields exactly the same ranges as
+// searching forwards, just starting from the other end.
 pub trait DoubleEndedSearcher<'a, H: ?Sized + SearchCursor>: ReverseSearcher<'a, H> {}
 
 
@@ -40,6 +42,7 @@ pub mod string {
 
     pub struct Ascii(u8);
 
+    #[derive(Clone)]
     pub struct AsciiSearcher<'a> {
         haystack: &'a str,
         start: *const u8,
@@ -47,6 +50,17 @@ pub mod string {
         ascii: u8,
     }
 
+    // Prints the remaining range as offsets instead of raw addresses.
+    impl<'a> ::core::fmt::Debug for AsciiSearcher<'a> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let front = self.haystack.as_ptr() as usize;
+            f.debug_struct("AsciiSearcher")
+                .field("remaining", &(self.start as usize - front..self.end as usize - front))
+                .field("ascii", &(self.ascii as char))
+                .finish()
+        }
+    }
+
     unsafe impl<'a> Searcher<'a, str> for AsciiSearcher<'a> {
         fn haystack(&self) -> &'a str { self.haystack }
 
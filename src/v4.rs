@@ -38,6 +38,8 @@ pub unsafe trait ReverseSearcher<H: SearchCursor>: Searcher<H> {
     fn next_reject_back(&mut self) -> Option<H::Cursor>;
 }
 
+// Promises that searching backwards yields exactly the same ranges as
+// searching forwards, just starting from the other end.
 pub trait DoubleEndedSearcher<H: SearchCursor>: ReverseSearcher<H> {}
 
 
@@ -55,21 +57,33 @@ pub mod string {
         unsafe fn cursor_to_self(_: Self::Start,
                                  cursor: Self::Cursor) -> &'a str
         {
-            let slice = ::std::slice::from_raw_parts(cursor.0,
+            let slice = ::core::slice::from_raw_parts(cursor.0,
                 cursor.1 as usize - cursor.0 as usize);
 
-            ::std::str::from_utf8_unchecked(slice)
+            ::core::str::from_utf8_unchecked(slice)
         }
     }
 
     pub struct Ascii(pub u8);
 
+    #[derive(Clone)]
     pub struct AsciiSearcher<'a> {
         front: *const u8,
         start: *const u8,
         end: *const u8,
         ascii: u8,
-        _marker: ::std::marker::PhantomData<&'a str>
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    // Prints the remaining range as offsets instead of raw addresses.
+    impl<'a> ::core::fmt::Debug for AsciiSearcher<'a> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let front = self.front as usize;
+            f.debug_struct("AsciiSearcher")
+                .field("remaining", &(self.start as usize - front..self.end as usize - front))
+                .field("ascii", &(self.ascii as char))
+                .finish()
+        }
     }
 
     unsafe impl<'a> Searcher<&'a str> for AsciiSearcher<'a> {
@@ -106,6 +120,39 @@ pub mod string {
         }
     }
 
+    // `front` stays valid for either direction, so nothing extra is needed.
+    unsafe impl<'a> ReverseSearcher<&'a str> for AsciiSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if *self.end == self.ascii {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if *self.end != self.ascii {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a str> for AsciiSearcher<'a> {}
+
     impl<'a> Pattern<&'a str> for Ascii {
         type Searcher = AsciiSearcher<'a>;
 
@@ -117,7 +164,7 @@ pub mod string {
                     haystack.as_ptr().offset(haystack.len() as isize)
                 },
                 ascii: self.0,
-                _marker: ::std::marker::PhantomData,
+                _marker: ::core::marker::PhantomData,
             }
         }
 
@@ -154,7 +201,7 @@ pub mod slice {
         unsafe fn cursor_to_self(_: Self::Start,
                                  cursor: Self::Cursor) -> &'a mut [u8]
         {
-            ::std::slice::from_raw_parts_mut(cursor.0,
+            ::core::slice::from_raw_parts_mut(cursor.0,
                 cursor.1 as usize - cursor.0 as usize)
         }
     }
@@ -163,15 +210,30 @@ pub mod slice {
 
     pub struct AsciiSearcher<'a> {
         front_offset: usize,
+        // Offset of `end`, the counterpart of `front_offset`
+        back_offset: usize,
+        // The `Start` for the last yielded cursor, which either
+        // end could have yielded
+        last_start: usize,
         start: *mut u8,
         end: *mut u8,
         ascii: u8,
-        _marker: ::std::marker::PhantomData<&'a mut [u8]>
+        _marker: ::core::marker::PhantomData<&'a mut [u8]>
+    }
+
+    // Not `Clone`, since two searchers would hand out aliasing slices.
+    impl<'a> ::core::fmt::Debug for AsciiSearcher<'a> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_struct("AsciiSearcher")
+                .field("remaining", &(self.front_offset..self.back_offset))
+                .field("ascii", &(self.ascii as char))
+                .finish()
+        }
     }
 
     unsafe impl<'a> Searcher<&'a mut [u8]> for AsciiSearcher<'a> {
         fn haystack_start(&self) -> usize {
-            self.front_offset
+            self.last_start
         }
 
         fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
@@ -182,6 +244,7 @@ pub mod slice {
                     self.front_offset += 1;
 
                     if *p == self.ascii {
+                        self.last_start = self.front_offset;
                         return Some((p, self.start));
                     }
                 }
@@ -197,6 +260,7 @@ pub mod slice {
                     self.front_offset += 1;
 
                     if *p != self.ascii {
+                        self.last_start = self.front_offset;
                         return Some((p, self.start));
                     }
                 }
@@ -205,18 +269,59 @@ pub mod slice {
         }
     }
 
+    unsafe impl<'a> ReverseSearcher<&'a mut [u8]> for AsciiSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+                    self.back_offset -= 1;
+
+                    if *self.end == self.ascii {
+                        self.last_start = self.back_offset + 1;
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+                    self.back_offset -= 1;
+
+                    if *self.end != self.ascii {
+                        self.last_start = self.back_offset + 1;
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a mut [u8]> for AsciiSearcher<'a> {}
+
     impl<'a> Pattern<&'a mut [u8]> for Ascii {
         type Searcher = AsciiSearcher<'a>;
 
         fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            // Both bounds have to come from the same pointer, as a second
+            // `as_mut_ptr` call would invalidate the first one.
+            let start = haystack.as_mut_ptr();
             AsciiSearcher {
                 front_offset: 0,
-                start: haystack.as_mut_ptr(),
+                back_offset: haystack.len(),
+                last_start: 0,
+                start,
                 end: unsafe {
-                    haystack.as_mut_ptr().offset(haystack.len() as isize)
+                    start.offset(haystack.len() as isize)
                 },
                 ascii: self.0,
-                _marker: ::std::marker::PhantomData,
+                _marker: ::core::marker::PhantomData,
             }
         }
 
@@ -242,8 +347,10 @@ pub mod os_string {
 
 }
 
+#[cfg(feature = "alloc")]
 pub mod api_consumer {
     use super::*;
+    use alloc::vec::Vec;
 
     pub fn match_indices<H, P>(haystack: H, pattern: P) -> Vec<(usize, H)>
         where H: SearchCursor,
@@ -265,6 +372,27 @@ pub mod api_consumer {
         ret
     }
 
+    pub fn rmatch_indices<H, P>(haystack: H, pattern: P) -> Vec<(usize, H)>
+        where H: SearchCursor,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let mut ret = vec![];
+
+        while let Some(match_) = searcher.next_match_back() {
+            let start = searcher.haystack_start();
+            unsafe {
+                let pos = H::offset_from_start(start, match_);
+                let slice = H::cursor_to_self(start, match_);
+
+                ret.push((pos, slice));
+            }
+        }
+
+        ret
+    }
+
     #[test]
     fn test_match_indices() {
         assert_eq!(match_indices("banana", string::Ascii(b'a')),
@@ -286,4 +414,36 @@ pub mod api_consumer {
         assert_eq!(slice, b"binini");
     }
 
+    // The offsets only come out right because the searcher tracks
+    // `back_offset` as well and remembers which end yielded last.
+    #[test]
+    fn test_rmatch_indices() {
+        assert_eq!(rmatch_indices("banana", string::Ascii(b'a')),
+                   vec![(5, "a"), (3, "a"), (1, "a")]);
+
+        let slice = &mut {*b"banana"}[..];
+
+        {
+            let match_indices = rmatch_indices(&mut*slice, slice::Ascii(b'a'));
+
+            assert_eq!(match_indices.iter().map(|x| x.0).collect::<Vec<_>>(),
+                       vec![5, 3, 1]);
+
+            for m in match_indices {
+                m.1[0] = b'o';
+            }
+        }
+
+        assert_eq!(slice, b"bonono");
+
+        // Interleaving both ends
+        let mut searcher = slice::Ascii(b'o').into_searcher(&mut*slice);
+        unsafe {
+            let front = searcher.next_match().unwrap();
+            assert_eq!(<&mut [u8]>::offset_from_start(searcher.haystack_start(), front), 1);
+            let back = searcher.next_match_back().unwrap();
+            assert_eq!(<&mut [u8]>::offset_from_start(searcher.haystack_start(), back), 5);
+        }
+    }
+
 }
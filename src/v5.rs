@@ -2,12 +2,85 @@
 pub trait Pattern<H: SearchPtrs>: Sized {
     type Searcher: Searcher<H>;
     fn into_searcher(self, haystack: H) -> Self::Searcher;
-    fn is_prefix_of(self, haystack: H) -> bool;
+
+    // The first match has to begin at the front of the haystack.
+    // Patterns that can answer this without a searcher should override it.
+    fn is_prefix_of(self, haystack: H) -> bool {
+        let mut searcher = self.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        match searcher.next_match() {
+            Some((begin, _)) => unsafe {
+                H::cursor_cmp(haystack, begin, H::cursor_at_front(haystack))
+                    == ::core::cmp::Ordering::Equal
+            },
+            None => false,
+        }
+    }
+
+    // The last match has to end at the back of the haystack.
     fn is_suffix_of(self, haystack: H) -> bool
-        where Self::Searcher: ReverseSearcher<H>;
+        where Self::Searcher: ReverseSearcher<H>
+    {
+        let mut searcher = self.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        match searcher.next_match_back() {
+            Some((_, end)) => unsafe { H::offset_from_end(haystack, end) == 0 },
+            None => false,
+        }
+    }
+
+    // The offset of the first match. Patterns that have to build tables
+    // for their searcher can override it to skip that for short haystacks.
+    fn first_match_offset(self, haystack: H) -> Option<usize> {
+        let mut searcher = self.into_searcher(haystack);
+        searcher.next_match().map(|(begin, _)| unsafe {
+            H::offset_from_start(searcher.haystack(), begin)
+        })
+    }
 
     fn is_contained_in(self, haystack: H) -> bool {
-        self.into_searcher(haystack).next_match().is_some()
+        self.first_match_offset(haystack).is_some()
+    }
+
+    // A searcher starting at `cursor` instead of the front, to resume
+    // a search paused at the `Searcher::position` of an earlier searcher.
+    // The cursor has to point into the haystack, eg. by coming from
+    // a searcher over an earlier borrow of it. The default only accepts
+    // the front.
+    unsafe fn into_searcher_at(self, haystack: H, cursor: H::Cursor) -> Self::Searcher {
+        let searcher = self.into_searcher(haystack);
+        let hs = searcher.haystack();
+        assert!(H::offset_from_start(hs, cursor) == 0,
+                "pattern can only start searching at the front");
+        searcher
+    }
+
+    // The number of matches. Can be overridden by patterns that count
+    // faster than their searcher finds.
+    fn count_in(self, haystack: H) -> usize {
+        let counted = self.into_searcher(haystack).try_fold_matches(0, |count, _| {
+            ::core::ops::ControlFlow::Continue(count + 1)
+        });
+        match counted {
+            ::core::ops::ControlFlow::Continue(count) |
+            ::core::ops::ControlFlow::Break(count) => count,
+        }
+    }
+
+    // The searcher behind a pointer, to keep searchers of different
+    // patterns in one collection.
+    #[cfg(feature = "alloc")]
+    fn into_boxed_searcher<'h>(self, haystack: H) -> BoxedSearcher<'h, H>
+        where Self::Searcher: 'h
+    {
+        ::alloc::boxed::Box::new(self.into_searcher(haystack))
+    }
+
+    #[cfg(feature = "alloc")]
+    fn into_boxed_reverse_searcher<'h>(self, haystack: H) -> BoxedReverseSearcher<'h, H>
+        where Self::Searcher: ReverseSearcher<H> + 'h
+    {
+        ::alloc::boxed::Box::new(self.into_searcher(haystack))
     }
 }
 
@@ -27,333 +100,13850 @@ pub trait SearchPtrs {
     // Usually a Memory address in form of a raw pointer or usize
     type Cursor: Copy;
 
+    // The number of elements in front of the cursor. Valid for every cursor
+    // between the front and the back of the haystack, so also for the end
+    // of a match and for the back itself.
+    //
+    // Offsets, distances and lengths all count elements of the haystack:
+    // bytes for the string-like haystacks, including `Chain` and
+    // `Segments`, and `T`s for `&[T]`, so eg. chars for `&[char]` and
+    // code units for `&[u16]`. Zero-sized `T`s are counted as well.
     unsafe fn offset_from_start(hs: Self::Haystack, begin: Self::Cursor) -> usize;
     unsafe fn range_to_self(hs: Self::Haystack,
                             start: Self::Cursor,
                             end: Self::Cursor) -> Self;
     unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor;
     unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor;
-}
 
-pub unsafe trait Searcher<H: SearchPtrs> {
-    fn haystack(&self) -> H::Haystack;
+    // Orders two cursors by their position in the haystack.
+    unsafe fn cursor_cmp(hs: Self::Haystack,
+                         a: Self::Cursor,
+                         b: Self::Cursor) -> ::core::cmp::Ordering {
+        Self::offset_from_start(hs, a).cmp(&Self::offset_from_start(hs, b))
+    }
 
-    fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)>;
-    fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)>;
-}
+    // The number of elements from `a` to `b`, where `a` must not lie
+    // behind `b`.
+    unsafe fn cursor_distance(hs: Self::Haystack,
+                              a: Self::Cursor,
+                              b: Self::Cursor) -> usize {
+        Self::offset_from_start(hs, b) - Self::offset_from_start(hs, a)
+    }
 
-pub unsafe trait ReverseSearcher<H: SearchPtrs>: Searcher<H> {
-    fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)>;
-    fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)>;
-}
+    // The number of elements in the haystack.
+    unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+        Self::offset_from_start(hs, Self::cursor_at_back(hs))
+    }
 
-pub trait DoubleEndedSearcher<H: SearchPtrs>: ReverseSearcher<H> {}
+    // The number of elements behind the cursor, the counterpart of
+    // `offset_from_start` for the back.
+    unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+        Self::cursor_distance(hs, cursor, Self::cursor_at_back(hs))
+    }
 
+    // The cursor with `n` elements in front of it, the inverse of
+    // `offset_from_start`. `n` must not exceed the length of the haystack.
+    // Unlike `cursor_advance` this needn't check that the cursor falls on
+    // an element boundary, the caller has to make sure it does.
+    unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor;
 
-pub mod string {
-    use super::*;
-    impl<'a> SearchPtrs for &'a str {
-        type Haystack = (*const u8, *const u8);
-        type Cursor = *const u8;
+    // Moves `cursor` forward by `n` elements, which must not take it past
+    // the back of the haystack. Panics if the new cursor would split
+    // an element the way slicing a `str` inside a char does.
+    unsafe fn cursor_advance(hs: Self::Haystack,
+                             cursor: Self::Cursor,
+                             n: usize) -> Self::Cursor;
 
-        unsafe fn offset_from_start(haystack: Self::Haystack,
-                                    begin: Self::Cursor) -> usize {
-            begin as usize - haystack.0 as usize
-        }
+    // Moves `cursor` forward to where the next search could start, which
+    // must not be the back. That is one element further, or for the
+    // string-like haystacks the boundary behind the next char.
+    unsafe fn cursor_next(hs: Self::Haystack, cursor: Self::Cursor) -> Self::Cursor {
+        Self::cursor_advance(hs, cursor, 1)
+    }
+}
 
-        unsafe fn range_to_self(_: Self::Haystack,
-                                start: Self::Cursor,
-                                end: Self::Cursor) -> Self {
-            let slice = ::std::slice::from_raw_parts(start,
-                end as usize - start as usize);
+// One step of a searcher through the haystack, like `std`'s `SearchStep`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SearchStep<C> {
+    Match(C, C),
+    Reject(C, C),
+    Done,
+}
 
-            ::std::str::from_utf8_unchecked(slice)
-        }
-        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
-            hs.0
-        }
-        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
-            hs.1
+impl<C> SearchStep<C> {
+    // Converts the cursors of a match or reject.
+    pub fn map<D, F: FnMut(C) -> D>(self, mut f: F) -> SearchStep<D> {
+        match self {
+            SearchStep::Match(begin, end) => SearchStep::Match(f(begin), f(end)),
+            SearchStep::Reject(begin, end) => SearchStep::Reject(f(begin), f(end)),
+            SearchStep::Done => SearchStep::Done,
         }
     }
+}
 
-    pub struct Ascii(pub u8);
+pub unsafe trait Searcher<H: SearchPtrs> {
+    fn haystack(&self) -> H::Haystack;
 
-    pub struct AsciiSearcher<'a> {
-        haystack: (*const u8, *const u8),
-        start: *const u8,
-        end: *const u8,
-        ascii: u8,
-        _marker: ::std::marker::PhantomData<&'a str>
-    }
+    // The steps returned by `next` partition the haystack: each one begins
+    // where the previous one ended, the first at the front and the last at
+    // the back, after which only `Done` is returned. Adjacent rejects may
+    // or may not be merged.
+    //
+    // `next_match` and `next_reject` skip the steps of the other kind, as
+    // in `std`, and are only overridden to find them faster. The calls can
+    // be mixed freely.
+    fn next(&mut self) -> SearchStep<H::Cursor>;
 
-    unsafe impl<'a> Searcher<&'a str> for AsciiSearcher<'a> {
-        fn haystack(&self) -> (*const u8, *const u8) {
-            self.haystack
+    fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+        loop {
+            match self.next() {
+                SearchStep::Match(begin, end) => return Some((begin, end)),
+                SearchStep::Reject(..) => {}
+                SearchStep::Done => return None,
+            }
         }
+    }
 
-        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
-            while self.start != self.end {
-                unsafe {
-                    let p = self.start;
-                    self.start = self.start.offset(1);
-
-                    if *p == self.ascii {
-                        return Some((p, self.start));
-                    }
-                }
+    fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+        loop {
+            match self.next() {
+                SearchStep::Reject(begin, end) => return Some((begin, end)),
+                SearchStep::Match(..) => {}
+                SearchStep::Done => return None,
             }
-            None
         }
+    }
 
-        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
-            while self.start != self.end {
-                unsafe {
-                    let p = self.start;
-                    self.start = self.start.offset(1);
+    // Where the searcher continues when searching forward. A searcher
+    // from `Pattern::into_searcher_at` with this cursor yields the same
+    // matches as this one from here on, so a search can be paused and
+    // resumed later. Only searchers of patterns that implement
+    // `into_searcher_at` override this, the default panics.
+    fn position(&self) -> H::Cursor {
+        panic!("searcher can't be resumed")
+    }
 
-                    if *p != self.ascii {
-                        return Some((p, self.start));
+    // Skips ahead to `cursor`, so that searching forward continues from
+    // there. The cursor has to lie in the part not searched yet, behind
+    // the end of the last step. A match that begins in front of the
+    // cursor and reaches past it is skipped as well.
+    //
+    // The default walks the steps up to the cursor. It uses `next`
+    // rather than `next_match`, as a match behind the cursor would
+    // otherwise be lost. Searchers that can jump there directly
+    // override this.
+    fn skip_to(&mut self, cursor: H::Cursor) {
+        let hs = self.haystack();
+        loop {
+            match self.next() {
+                SearchStep::Match(_, end) | SearchStep::Reject(_, end) => {
+                    if unsafe { H::cursor_cmp(hs, end, cursor) } != ::core::cmp::Ordering::Less {
+                        return;
                     }
                 }
+                SearchStep::Done => return,
             }
-            None
         }
     }
 
-    impl<'a> Pattern<&'a str> for Ascii {
-        type Searcher = AsciiSearcher<'a>;
+    // Bounds on the number of matches `next_match` has left to yield,
+    // in the sense of `Iterator::size_hint`. Consumers preallocate from
+    // them, so an upper bound must never be too low.
+    fn match_bounds(&self) -> (usize, Option<usize>) {
+        (0, None)
+    }
 
-        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
-            let begin = haystack.as_ptr();
-            let end = unsafe {
-                haystack.as_ptr().offset(haystack.len() as isize)
+    // Feeds the following matches to `f` until it breaks or the matches
+    // run out. After a break the searcher continues behind the match
+    // passed last. Searchers with a tight loop can override this to call
+    // `f` from inside it instead of returning every match.
+    //
+    // Being generic, it is left out of `dyn Searcher`, whose matches are
+    // folded by this default.
+    fn try_fold_matches<B, F>(&mut self, init: B, mut f: F) -> ::core::ops::ControlFlow<B, B>
+        where F: FnMut(B, (H::Cursor, H::Cursor)) -> ::core::ops::ControlFlow<B, B>,
+              Self: Sized,
+    {
+        let mut acc = init;
+        while let Some(m) = self.next_match() {
+            acc = match f(acc, m) {
+                ::core::ops::ControlFlow::Continue(acc) => acc,
+                brk => return brk,
             };
-            AsciiSearcher {
-                haystack: (begin, end),
-                start: begin,
-                end: end,
-                ascii: self.0,
-                _marker: ::std::marker::PhantomData,
-            }
         }
+        ::core::ops::ControlFlow::Continue(acc)
+    }
+}
 
-        fn is_prefix_of(self, haystack: &'a str) -> bool {
-            haystack.as_bytes()
-                .get(0)
-                .map(|&b| b == self.0)
-                .unwrap_or(false)
-        }
+pub unsafe trait ReverseSearcher<H: SearchPtrs>: Searcher<H> {
+    fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)>;
+    fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)>;
+}
 
-        fn is_suffix_of(self, haystack: &'a str) -> bool
-            where Self::Searcher: ReverseSearcher<&'a str> {
-            haystack.as_bytes()
-                .get(haystack.len() - 1)
-                .map(|&b| b == self.0)
-                .unwrap_or(false)
-        }
-    }
+// Promises that searching backwards yields exactly the same ranges as
+// searching forwards, just starting from the other end, like `std`'s
+// `DoubleEndedSearcher`. Consumers can then take matches from both ends
+// of one searcher and the two directions meet without skipping or
+// repeating anything.
+//
+// Patterns matching single elements qualify, substring patterns don't:
+// searching "aa" in "aaa" finds `0..2` from the front but `1..3` from
+// the back.
+pub trait DoubleEndedSearcher<H: SearchPtrs>: ReverseSearcher<H> {}
 
+// Promises that a searcher stays exhausted, like `FusedIterator`: once
+// `next` returned `Done` or `next_match` or `next_reject` returned
+// `None`, all three keep returning `Done` and `None`. The same holds for
+// the methods of `ReverseSearcher`, if the searcher has them.
+//
+// The contract of `next` asks for this already, but the other methods
+// are easily overridden without it, so consumers may only rely on it
+// for searchers with this trait. `api_consumer::Fuse` adds it to any
+// searcher.
+pub trait FusedSearcher<H: SearchPtrs>: Searcher<H> {}
+
+// Searchers of patterns with several alternatives, which can tell
+// the index of the alternative that produced a match.
+//
+// `last_match_id` is only meaningful right after `next_match` returned
+// `Some`, any other call may change or invalidate it. Before the first
+// match it may panic, as there is no id to give.
+pub trait TaggedSearcher<H: SearchPtrs>: Searcher<H> {
+    fn last_match_id(&self) -> usize;
 }
 
-pub mod slice {
-    use super::*;
-    impl<'a> SearchPtrs for &'a mut [u8] {
-        // Store address bounds as usize since aliasing interaction is unclear
-        type Haystack = (*mut u8, *mut u8);
-        type Cursor = *mut u8;
+// Searchers as trait objects, for when the pattern is only known at
+// runtime. Apart from `try_fold_matches` every method is object safe,
+// as none is generic and the cursors all come from `H`.
+#[cfg(feature = "alloc")]
+pub type BoxedSearcher<'h, H> = ::alloc::boxed::Box<dyn Searcher<H> + 'h>;
+#[cfg(feature = "alloc")]
+pub type BoxedReverseSearcher<'h, H> = ::alloc::boxed::Box<dyn ReverseSearcher<H> + 'h>;
 
-        unsafe fn offset_from_start(haystack: Self::Haystack,
-                                    begin: Self::Cursor) -> usize {
-            begin as usize - haystack.0 as usize
-        }
+#[cfg(feature = "alloc")]
+unsafe impl<H, S> Searcher<H> for ::alloc::boxed::Box<S>
+    where H: SearchPtrs,
+          S: Searcher<H> + ?Sized,
+{
+    fn haystack(&self) -> H::Haystack {
+        (**self).haystack()
+    }
 
-        unsafe fn range_to_self(_: Self::Haystack,
-                                start: Self::Cursor,
-                                end: Self::Cursor) -> Self {
-            ::std::slice::from_raw_parts_mut(start,
-                end as usize - start as usize)
-        }
-        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
-            hs.0 as *mut u8
-        }
-        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
-            hs.1 as *mut u8
-        }
+    fn next(&mut self) -> SearchStep<H::Cursor> {
+        (**self).next()
     }
 
-    pub struct Ascii(pub u8);
+    fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+        (**self).next_match()
+    }
 
-    pub struct AsciiSearcher<'a> {
-        haystack: (*mut u8, *mut u8),
-        start: *mut u8,
-        end: *mut u8,
-        ascii: u8,
-        _marker: ::std::marker::PhantomData<&'a mut [u8]>
+    fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+        (**self).next_reject()
     }
 
-    unsafe impl<'a> Searcher<&'a mut [u8]> for AsciiSearcher<'a> {
-        fn haystack(&self) -> (*mut u8, *mut u8) {
-            self.haystack
-        }
+    fn position(&self) -> H::Cursor {
+        (**self).position()
+    }
 
-        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
-            while self.start != self.end {
-                unsafe {
-                    let p = self.start;
-                    self.start = self.start.offset(1);
+    fn skip_to(&mut self, cursor: H::Cursor) {
+        (**self).skip_to(cursor)
+    }
 
-                    if *p == self.ascii {
-                        return Some((p, self.start));
-                    }
-                }
-            }
-            None
-        }
+    fn match_bounds(&self) -> (usize, Option<usize>) {
+        (**self).match_bounds()
+    }
+}
 
-        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
-            while self.start != self.end {
-                unsafe {
-                    let p = self.start;
-                    self.start = self.start.offset(1);
+#[cfg(feature = "alloc")]
+unsafe impl<H, S> ReverseSearcher<H> for ::alloc::boxed::Box<S>
+    where H: SearchPtrs,
+          S: ReverseSearcher<H> + ?Sized,
+{
+    fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+        (**self).next_match_back()
+    }
 
-                    if *p != self.ascii {
-                        return Some((p, self.start));
-                    }
-                }
-            }
-            None
-        }
+    fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+        (**self).next_reject_back()
     }
+}
 
-    impl<'a> Pattern<&'a mut [u8]> for Ascii {
-        type Searcher = AsciiSearcher<'a>;
+#[cfg(feature = "alloc")]
+impl<H, S> DoubleEndedSearcher<H> for ::alloc::boxed::Box<S>
+    where H: SearchPtrs,
+          S: DoubleEndedSearcher<H> + ?Sized,
+{}
 
-        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
-            let begin = haystack.as_mut_ptr();
-            let end = unsafe {
-                haystack.as_mut_ptr().offset(haystack.len() as isize)
-            };
+#[cfg(feature = "alloc")]
+impl<H, S> FusedSearcher<H> for ::alloc::boxed::Box<S>
+    where H: SearchPtrs,
+          S: FusedSearcher<H> + ?Sized,
+{}
 
-            AsciiSearcher {
-                haystack: (begin, end),
-                start: begin,
-                end: end,
-                ascii: self.0,
-                _marker: ::std::marker::PhantomData,
+#[cfg(feature = "alloc")]
+impl<H, S> TaggedSearcher<H> for ::alloc::boxed::Box<S>
+    where H: SearchPtrs,
+          S: TaggedSearcher<H> + ?Sized,
+{
+    fn last_match_id(&self) -> usize {
+        (**self).last_match_id()
+    }
+}
+
+// Searcher state for an empty needle, shared by the substring patterns.
+//
+// Like in std, an empty needle matches with zero width at every element
+// boundary, including the front and the back of the haystack, and the
+// rejects are the single elements in between. Every call moves the
+// position forward, so the same empty match is never yielded twice.
+#[derive(Clone)]
+struct EmptyNeedle {
+    is_match_fw: bool,
+}
+
+impl EmptyNeedle {
+    fn new() -> Self {
+        EmptyNeedle { is_match_fw: true }
+    }
+
+    // `step` returns the cursor behind the element starting at its argument.
+    fn next_match<C, F>(&mut self, position: &mut C, end: C, mut step: F)
+        -> Option<(C, C)>
+        where C: Copy + PartialEq,
+              F: FnMut(C) -> C,
+    {
+        loop {
+            let p = *position;
+            if self.is_match_fw {
+                self.is_match_fw = false;
+                return Some((p, p));
             }
+            if p == end {
+                return None;
+            }
+            *position = step(p);
+            self.is_match_fw = true;
         }
+    }
 
-        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
-            haystack
-                .get(0)
-                .map(|&b| b == self.0)
-                .unwrap_or(false)
+    fn next<C, F>(&mut self, position: &mut C, end: C, mut step: F) -> SearchStep<C>
+        where C: Copy + PartialEq,
+              F: FnMut(C) -> C,
+    {
+        let p = *position;
+        if self.is_match_fw {
+            self.is_match_fw = false;
+            return SearchStep::Match(p, p);
         }
-
-        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
-            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
-            haystack
-                .get(haystack.len() - 1)
-                .map(|&b| b == self.0)
-                .unwrap_or(false)
+        if p == end {
+            return SearchStep::Done;
         }
+        *position = step(p);
+        self.is_match_fw = true;
+        SearchStep::Reject(p, *position)
     }
-}
 
-pub mod os_string {
-    //use super::*;
+    fn next_reject<C, F>(&mut self, position: &mut C, end: C, mut step: F)
+        -> Option<(C, C)>
+        where C: Copy + PartialEq,
+              F: FnMut(C) -> C,
+    {
+        // A pending match is skipped
+        self.is_match_fw = false;
 
+        let p = *position;
+        if p == end {
+            return None;
+        }
+        *position = step(p);
+        self.is_match_fw = true;
+        Some((p, *position))
+    }
 }
 
-pub mod api_consumer {
-    use super::*;
+// The offsets of the range between two cursors, which the `Debug` impls
+// of the searchers print instead of raw addresses.
+unsafe fn debug_range<H: SearchPtrs>(hs: H::Haystack,
+                                     start: H::Cursor,
+                                     end: H::Cursor) -> ::core::ops::Range<usize> {
+    H::offset_from_start(hs, start)..H::offset_from_start(hs, end)
+}
 
-    pub fn match_indices<H, P>(haystack: H, pattern: P) -> Vec<(usize, H)>
-        where H: SearchPtrs,
-              P: Pattern<H>,
-    {
-        let mut searcher = pattern.into_searcher(haystack);
-        let mut ret = vec![];
+// The cursor of `hs` at the offset of `cursor`, for the overrides of
+// `Pattern::into_searcher_at`. The cursor is derived from the haystack
+// again, as it may come from a searcher over an earlier borrow of it.
+unsafe fn resume_cursor<H: SearchPtrs>(hs: H::Haystack, cursor: H::Cursor) -> H::Cursor {
+    let offset = H::offset_from_start(hs, cursor);
+    assert!(offset <= H::haystack_len(hs), "cursor lies outside the haystack");
+    H::cursor_advance(hs, H::cursor_at_front(hs), offset)
+}
 
-        while let Some((begin, end)) = searcher.next_match() {
-            let haystack = searcher.haystack();
-            unsafe {
-                let offset = H::offset_from_start(haystack, begin);
-                let slice = H::range_to_self(haystack, begin, end);
+// Prints a byte needle like the `String::from_utf8_lossy` of it would,
+// without needing an allocator.
+struct DebugLossy<'a>(&'a [u8]);
 
-                ret.push((offset, slice));
+impl<'a> ::core::fmt::Debug for DebugLossy<'a> {
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        use core::fmt::Write;
+        f.write_char('"')?;
+        for chunk in self.0.utf8_chunks() {
+            write!(f, "{}", chunk.valid().escape_debug())?;
+            if !chunk.invalid().is_empty() {
+                f.write_char(::core::char::REPLACEMENT_CHARACTER)?;
             }
         }
+        f.write_char('"')
+    }
+}
 
-        ret
+// Implements `Debug` for a searcher over `$hs` that keeps its cursors in
+// the fields `haystack`, `start` and `end`. The not yet searched range
+// is printed as offsets, followed by the listed fields.
+macro_rules! impl_searcher_debug {
+    ($hs:ty => [$($gen:tt)*] $name:ident<$($param:tt),*>
+     { $($field:ident $(as $cast:ty)*),* }) => {
+        impl<$($gen)*> ::core::fmt::Debug for $name<$($param),*> {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                let remaining = unsafe {
+                    debug_range::<$hs>(self.haystack, self.start, self.end)
+                };
+                f.debug_struct(stringify!($name))
+                    .field("remaining", &remaining)
+                    $(.field(stringify!($field), &(self.$field $(as $cast)*)))*
+                    .finish()
+            }
+        }
     }
+}
 
-    #[test]
-    fn test_match_indices() {
-        assert_eq!(match_indices("banana", string::Ascii(b'a')),
-                   vec![(1, "a"), (3, "a"), (5, "a")]);
+// Implements `Pattern` for references to clonable patterns by searching
+// with a clone, so the pattern stays usable afterwards.
+//
+// A blanket `impl Pattern<H> for &P` would overlap with the impl for
+// `FnMut(char) -> bool` closures, since `&F` is itself such a closure,
+// so the impls are generated per pattern type instead.
+macro_rules! impl_pattern_for_ref {
+    ($([$($gen:tt),*] $ty:ty),* $(,)*) => {$(
+        impl<'p, $($gen,)* H> Pattern<H> for &'p $ty
+            where H: SearchPtrs,
+                  $ty: Pattern<H> + Clone,
+        {
+            type Searcher = <$ty as Pattern<H>>::Searcher;
 
-        let mut slice = &mut {*b"banana"}[..];
+            fn into_searcher(self, haystack: H) -> Self::Searcher {
+                self.clone().into_searcher(haystack)
+            }
 
-        {
-            let match_indices = match_indices(&mut*slice, slice::Ascii(b'a'));
+            fn is_prefix_of(self, haystack: H) -> bool {
+                self.clone().is_prefix_of(haystack)
+            }
 
-            assert_eq!(match_indices.iter().map(|x| x.0).collect::<Vec<_>>(),
-                       vec![1, 3, 5]);
+            fn is_suffix_of(self, haystack: H) -> bool
+                where Self::Searcher: ReverseSearcher<H> {
+                self.clone().is_suffix_of(haystack)
+            }
 
-            for m in match_indices {
-                m.1[0] = b'i';
+            fn first_match_offset(self, haystack: H) -> Option<usize> {
+                self.clone().first_match_offset(haystack)
+            }
+
+            fn is_contained_in(self, haystack: H) -> bool {
+                self.clone().is_contained_in(haystack)
+            }
+
+            fn count_in(self, haystack: H) -> usize {
+                self.clone().count_in(haystack)
             }
         }
+    )*}
+}
 
-        assert_eq!(slice, b"binini");
+// Word-at-a-time byte scanning, used to speed up searchers
+// for single bytes.
+mod memchr {
+    use core::mem::size_of;
+
+    const LO: usize = usize::MAX / 255;
+    const HI: usize = LO * 128;
+
+    fn contains_zero_byte(x: usize) -> bool {
+        x.wrapping_sub(LO) & !x & HI != 0
     }
 
-    pub fn split<H, P>(haystack: H, pattern: P) -> Vec<H>
-        where H: SearchPtrs,
-              P: Pattern<H>,
+    // Returns the index of the first occurrence of `needle`.
+    pub fn memchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+        let repeated = LO * needle as usize;
+        let ptr = haystack.as_ptr();
+        let mut i = 0;
+
+        // Skip over whole words that can not contain the needle,
+        // and leave the rest to the byte loop below.
+        while i + size_of::<usize>() <= haystack.len() {
+            let word = unsafe {
+                (ptr.add(i) as *const usize).read_unaligned()
+            };
+            if contains_zero_byte(word ^ repeated) {
+                break;
+            }
+            i += size_of::<usize>();
+        }
+
+        haystack[i..].iter().position(|&b| b == needle).map(|j| i + j)
+    }
+
+    // Returns the index of the first occurrence of either needle.
+    pub fn memchr2(n1: u8, n2: u8, haystack: &[u8]) -> Option<usize> {
+        let (r1, r2) = (LO * n1 as usize, LO * n2 as usize);
+        let ptr = haystack.as_ptr();
+        let mut i = 0;
+
+        while i + size_of::<usize>() <= haystack.len() {
+            let word = unsafe {
+                (ptr.add(i) as *const usize).read_unaligned()
+            };
+            if contains_zero_byte(word ^ r1) || contains_zero_byte(word ^ r2) {
+                break;
+            }
+            i += size_of::<usize>();
+        }
+
+        haystack[i..].iter()
+            .position(|&b| b == n1 || b == n2)
+            .map(|j| i + j)
+    }
+
+    // Returns the index of the first occurrence of any of the needles.
+    pub fn memchr3(n1: u8, n2: u8, n3: u8, haystack: &[u8]) -> Option<usize> {
+        let (r1, r2, r3) = (LO * n1 as usize, LO * n2 as usize, LO * n3 as usize);
+        let ptr = haystack.as_ptr();
+        let mut i = 0;
+
+        while i + size_of::<usize>() <= haystack.len() {
+            let word = unsafe {
+                (ptr.add(i) as *const usize).read_unaligned()
+            };
+            if contains_zero_byte(word ^ r1)
+                || contains_zero_byte(word ^ r2)
+                || contains_zero_byte(word ^ r3) {
+                break;
+            }
+            i += size_of::<usize>();
+        }
+
+        haystack[i..].iter()
+            .position(|&b| b == n1 || b == n2 || b == n3)
+            .map(|j| i + j)
+    }
+
+    // Returns the index of the last occurrence of `needle`.
+    pub fn memrchr(needle: u8, haystack: &[u8]) -> Option<usize> {
+        let repeated = LO * needle as usize;
+        let ptr = haystack.as_ptr();
+        let mut end = haystack.len();
+
+        // Like `memchr`, but skipping whole words from the back
+        while end >= size_of::<usize>() {
+            let word = unsafe {
+                (ptr.add(end - size_of::<usize>()) as *const usize).read_unaligned()
+            };
+            if contains_zero_byte(word ^ repeated) {
+                break;
+            }
+            end -= size_of::<usize>();
+        }
+
+        haystack[..end].iter().rposition(|&b| b == needle)
+    }
+
+    #[test]
+    fn test_memchr() {
+        let haystack: Vec<u8> = (0..100u32).map(|i| (i * 7 % 13) as u8).collect();
+        for start in 0..20 {
+            for len in 0..(haystack.len() - start) {
+                let h = &haystack[start..start + len];
+                for needle in 0..14 {
+                    assert_eq!(memchr(needle, h),
+                               h.iter().position(|&b| b == needle));
+                    assert_eq!(memrchr(needle, h),
+                               h.iter().rposition(|&b| b == needle));
+                }
+            }
+        }
+        assert_eq!(memchr(0x80, &[0x7f, 0x81, 0xff, 0x80]), Some(3));
+        assert_eq!(memchr(0, &[1; 17]), None);
+        assert_eq!(memrchr(0x80, &[0x80, 0x81, 0xff, 0x7f]), Some(0));
+        assert_eq!(memrchr(0, &[1; 17]), None);
+    }
+}
+
+// The two-way substring search algorithm as used by std's `StrSearcher`.
+// It runs in linear time without allocating, by splitting the needle at
+// a critical factorization computed up front.
+mod two_way {
+    use core::cmp;
+
+    #[derive(Clone)]
+    pub struct TwoWay {
+        // Index of the critical factorization
+        crit_pos: usize,
+        period: usize,
+        // Bitset of the low 6 bits of all needle bytes,
+        // to quickly skip over impossible positions
+        byteset: u64,
+        // How much of the needle front is already known to match
+        // at the current position. Only used for short periods.
+        memory: usize,
+        long_period: bool,
+    }
+
+    impl TwoWay {
+        pub fn new(needle: &[u8]) -> TwoWay {
+            let (crit_pos_false, period_false) = maximal_suffix(needle, false);
+            let (crit_pos_true, period_true) = maximal_suffix(needle, true);
+
+            let (crit_pos, period) = if crit_pos_false > crit_pos_true {
+                (crit_pos_false, period_false)
+            } else {
+                (crit_pos_true, period_true)
+            };
+
+            if needle[..crit_pos] == needle[period..period + crit_pos] {
+                TwoWay {
+                    crit_pos,
+                    period,
+                    byteset: byteset(&needle[..period]),
+                    memory: 0,
+                    long_period: false,
+                }
+            } else {
+                // The exact period is not needed, a lower bound on it
+                // is enough to guarantee linear time.
+                TwoWay {
+                    crit_pos,
+                    period: cmp::max(crit_pos, needle.len() - crit_pos) + 1,
+                    byteset: byteset(needle),
+                    memory: 0,
+                    long_period: true,
+                }
+            }
+        }
+
+        fn byteset_contains(&self, b: u8) -> bool {
+            (self.byteset >> (b & 0x3f)) & 1 != 0
+        }
+
+        // Returns the index of the first occurrence of the needle the state
+        // was built from. The memory is only valid for the haystack position
+        // the last call stopped at, so it is reset whenever a call returns.
+        pub fn find(&mut self, needle: &[u8], haystack: &[u8]) -> Option<usize> {
+            let needle_last = needle.len() - 1;
+            let mut position = 0;
+
+            'search: loop {
+                let tail_byte = match haystack.get(position + needle_last) {
+                    Some(&b) => b,
+                    None => {
+                        self.memory = 0;
+                        return None;
+                    }
+                };
+
+                if !self.byteset_contains(tail_byte) {
+                    position += needle.len();
+                    self.memory = 0;
+                    continue 'search;
+                }
+
+                // Match the right part of the needle
+                let start = if self.long_period {
+                    self.crit_pos
+                } else {
+                    cmp::max(self.crit_pos, self.memory)
+                };
+                for i in start..needle.len() {
+                    if needle[i] != haystack[position + i] {
+                        position += i - self.crit_pos + 1;
+                        self.memory = 0;
+                        continue 'search;
+                    }
+                }
+
+                // Match the left part of the needle
+                let start = if self.long_period { 0 } else { self.memory };
+                for i in (start..self.crit_pos).rev() {
+                    if needle[i] != haystack[position + i] {
+                        position += self.period;
+                        if !self.long_period {
+                            self.memory = needle.len() - self.period;
+                        }
+                        continue 'search;
+                    }
+                }
+
+                self.memory = 0;
+                return Some(position);
+            }
+        }
+    }
+
+    fn byteset(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0, |a, &b| (1 << (b & 0x3f)) | a)
+    }
+
+    // Computes the maximal suffix of `arr` for the given byte order,
+    // returning its start and period.
+    fn maximal_suffix(arr: &[u8], order_greater: bool) -> (usize, usize) {
+        let mut left = 0;
+        let mut right = 1;
+        let mut offset = 0;
+        let mut period = 1;
+
+        while let Some(&a) = arr.get(right + offset) {
+            let b = arr[left + offset];
+            if (a < b && !order_greater) || (a > b && order_greater) {
+                // The suffix is smaller, the period is the whole prefix
+                right += offset + 1;
+                offset = 0;
+                period = right - left;
+            } else if a == b {
+                // Advance through a repetition of the current period
+                if offset + 1 == period {
+                    right += offset + 1;
+                    offset = 0;
+                } else {
+                    offset += 1;
+                }
+            } else {
+                // The suffix is larger, start over from here
+                left = right;
+                right += 1;
+                offset = 0;
+                period = 1;
+            }
+        }
+        (left, period)
+    }
+
+    #[test]
+    fn test_find() {
+        let haystack = b"abaabaaabaaaabaaaaabcabcabd";
+        for start in 0..haystack.len() {
+            for len in 1..10 {
+                let needle = &haystack[start..haystack.len().min(start + len)];
+                let mut two_way = TwoWay::new(needle);
+                for from in 0..haystack.len() {
+                    let h = &haystack[from..];
+                    assert_eq!(two_way.find(needle, h),
+                               h.windows(needle.len()).position(|w| w == needle));
+                }
+            }
+        }
+    }
+}
+
+// Boyer-Moore-Horspool substring search, used by the byte substring
+// searcher for long needles where comparing at every position gets expensive.
+// The table is boxed, so without `alloc` long needles are searched naively.
+#[cfg(feature = "alloc")]
+mod horspool {
+    use alloc::boxed::Box;
+
+    // Needles shorter than this are searched naively, as building
+    // the table costs more than the skips save.
+    pub const MIN_NEEDLE_LEN: usize = 8;
+
+    // For every byte, how far the needle can be shifted if that byte
+    // is found below the last needle position.
+    pub struct SkipTable([usize; 256]);
+
+    impl SkipTable {
+        pub fn new(needle: &[u8]) -> Box<SkipTable> {
+            let last = needle.len() - 1;
+            let mut table = Box::new(SkipTable([needle.len(); 256]));
+            for (i, &b) in needle[..last].iter().enumerate() {
+                table.0[b as usize] = last - i;
+            }
+            table
+        }
+
+        // Returns the index of the first occurrence of the needle
+        // the table was built from.
+        pub fn find(&self, needle: &[u8], haystack: &[u8]) -> Option<usize> {
+            let last = needle.len() - 1;
+            let mut i = 0;
+
+            while i + needle.len() <= haystack.len() {
+                let b = haystack[i + last];
+                if b == needle[last] && haystack[i..i + last] == needle[..last] {
+                    return Some(i);
+                }
+                i += self.0[b as usize];
+            }
+            None
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let haystack = b"abaabaaabaaaabaaaaab";
+        for start in 0..haystack.len() {
+            for len in 1..10 {
+                let needle = &haystack[start..haystack.len().min(start + len)];
+                let table = SkipTable::new(needle);
+                for from in 0..haystack.len() {
+                    let h = &haystack[from..];
+                    assert_eq!(table.find(needle, h),
+                               h.windows(needle.len()).position(|w| w == needle));
+                }
+            }
+        }
+    }
+}
+
+
+pub mod string {
+    use super::*;
+    use core::ops::ControlFlow;
+    #[cfg(feature = "alloc")]
+    use alloc::string::String;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    // `cursor_at_offset` doesn't check for char boundaries, the caller
+    // is responsible for not creating a cursor inside a char.
+    impl<'a> SearchPtrs for &'a str {
+        type Haystack = (*const u8, *const u8);
+        type Cursor = *const u8;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            begin as usize - haystack.0 as usize
+        }
+
+        unsafe fn range_to_self(hs: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            let slice = ::core::slice::from_raw_parts(start,
+                Self::cursor_distance(hs, start, end));
+
+            ::core::str::from_utf8_unchecked(slice)
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            hs.0
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            hs.1
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> ::core::cmp::Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            b as usize - a as usize
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            hs.1 as usize - hs.0 as usize
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            hs.1 as usize - cursor as usize
+        }
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            hs.0.add(n)
+        }
+        unsafe fn cursor_advance(hs: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            let p = cursor.add(n);
+            assert!(is_char_boundary(p, hs.1), "cursor is not at a char boundary");
+            p
+        }
+        unsafe fn cursor_next(hs: Self::Haystack, cursor: Self::Cursor) -> Self::Cursor {
+            next_char(cursor, hs.1).1
+        }
+    }
+
+    // A single ASCII byte. Unlike `slice::Ascii` the byte can't be set
+    // directly, since matching a byte >= 0x80 could split a multi-byte char
+    // and `range_to_self` would then create an invalid `&str`.
+    #[derive(Clone, Copy)]
+    pub struct Ascii(u8);
+
+    impl Ascii {
+        // Returns `None` if the byte is not ASCII.
+        pub const fn new(byte: u8) -> Option<Ascii> {
+            if byte.is_ascii() {
+                Some(Ascii(byte))
+            } else {
+                None
+            }
+        }
+
+        /// # Safety
+        ///
+        /// The byte must be ASCII, that is below 0x80.
+        pub const unsafe fn new_unchecked(byte: u8) -> Ascii {
+            Ascii(byte)
+        }
+
+        pub const fn to_u8(self) -> u8 {
+            self.0
+        }
+    }
+
+    // Matches like `Ascii`, but ignoring ASCII case differences. The
+    // byte is private for the same reason.
+    #[derive(Clone, Copy)]
+    pub struct AsciiNoCase(u8);
+
+    impl AsciiNoCase {
+        // Returns `None` if the byte is not ASCII.
+        pub const fn new(byte: u8) -> Option<AsciiNoCase> {
+            if byte.is_ascii() {
+                Some(AsciiNoCase(byte))
+            } else {
+                None
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct AsciiSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        ascii: u8,
+        ignore_case: bool,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a] AsciiSearcher<'a> { ascii as char, ignore_case } }
+
+    impl<'a> AsciiSearcher<'a> {
+        fn is_match(&self, b: u8) -> bool {
+            if self.ignore_case {
+                b.eq_ignore_ascii_case(&self.ascii)
+            } else {
+                b == self.ascii
+            }
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a str> for AsciiSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn position(&self) -> *const u8 {
+            self.start
+        }
+
+        // There's no state besides the position, so the searcher jumps to
+        // the cursor directly. It's rederived from `start` to keep the
+        // provenance of the haystack.
+        fn skip_to(&mut self, cursor: *const u8) {
+            assert!(self.start <= cursor && cursor <= self.end,
+                    "cursor lies outside the remaining range");
+            let offset = cursor as usize - self.start as usize;
+            self.start = unsafe { self.start.add(offset) };
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                if self.is_match(*p) {
+                    self.start = p.offset(1);
+                    SearchStep::Match(p, self.start)
+                } else {
+                    // Rejects have to span whole chars
+                    self.start = next_char(p, self.end).1;
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            if !self.ignore_case {
+                unsafe {
+                    let rest = ::core::slice::from_raw_parts(self.start,
+                        self.end as usize - self.start as usize);
+
+                    return match memchr::memchr(self.ascii, rest) {
+                        Some(i) => {
+                            let p = self.start.add(i);
+                            self.start = p.offset(1);
+                            Some((p, self.start))
+                        }
+                        None => {
+                            self.start = self.end;
+                            None
+                        }
+                    };
+                }
+            }
+
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if self.is_match(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn try_fold_matches<B, F>(&mut self, init: B, mut f: F) -> ControlFlow<B, B>
+            where F: FnMut(B, (*const u8, *const u8)) -> ControlFlow<B, B>
+        {
+            let mut acc = init;
+            while self.start != self.end {
+                unsafe {
+                    let p = if self.ignore_case {
+                        let p = self.start;
+                        self.start = p.offset(1);
+                        if !self.is_match(*p) {
+                            continue;
+                        }
+                        p
+                    } else {
+                        let rest = ::core::slice::from_raw_parts(self.start,
+                            self.end as usize - self.start as usize);
+                        match memchr::memchr(self.ascii, rest) {
+                            Some(i) => {
+                                let p = self.start.add(i);
+                                self.start = p.offset(1);
+                                p
+                            }
+                            None => {
+                                self.start = self.end;
+                                break;
+                            }
+                        }
+                    };
+
+                    acc = match f(acc, (p, self.start)) {
+                        ControlFlow::Continue(acc) => acc,
+                        brk => return brk,
+                    };
+                }
+            }
+            ControlFlow::Continue(acc)
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    // Rejects have to span whole chars
+                    let (_, next) = next_char(p, self.end);
+                    self.start = next;
+
+                    if !self.is_match(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a str> for AsciiSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a str> for AsciiSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            if !self.ignore_case {
+                unsafe {
+                    let rest = ::core::slice::from_raw_parts(self.start,
+                        self.end as usize - self.start as usize);
+
+                    return match memchr::memrchr(self.ascii, rest) {
+                        Some(i) => {
+                            let p = self.start.add(i);
+                            self.end = p;
+                            Some((p, p.offset(1)))
+                        }
+                        None => {
+                            self.end = self.start;
+                            None
+                        }
+                    };
+                }
+            }
+
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if self.is_match(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    // Rejects have to span whole chars
+                    let (_, prev) = prev_char(self.start, p);
+                    self.end = prev;
+
+                    if !self.is_match(*prev) {
+                        return Some((prev, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    // Each match is a single byte, so the two ends can't both yield it.
+    impl<'a> DoubleEndedSearcher<&'a str> for AsciiSearcher<'a> {}
+
+    impl<'a> Pattern<&'a str> for Ascii {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().offset(haystack.len() as isize)
+            };
+            AsciiSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end: end,
+                ascii: self.0,
+                ignore_case: false,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn first_match_offset(self, haystack: &'a str) -> Option<usize> {
+            memchr::memchr(self.0, haystack.as_bytes())
+        }
+
+        unsafe fn into_searcher_at(self, haystack: &'a str, cursor: *const u8) -> Self::Searcher {
+            let mut searcher = self.into_searcher(haystack);
+            searcher.start = resume_cursor::<&str>(searcher.haystack, cursor);
+            searcher
+        }
+
+        fn count_in(self, haystack: &'a str) -> usize {
+            haystack.bytes().filter(|&b| b == self.0).count()
+        }
+    }
+
+    // Shorthand for `Ascii`. As a byte >= 0x80 could split a multi-byte
+    // char such bytes never match, instead of being rejected like by
+    // `Ascii::new`.
+    impl<'a> Pattern<&'a str> for u8 {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            // 0xFF never occurs in UTF-8
+            let ascii = if self.is_ascii() { self } else { 0xFF };
+            AsciiSearcher {
+                ascii,
+                ..Ascii(0).into_searcher(haystack)
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            Ascii::new(self)
+                .map(|ascii| ascii.is_prefix_of(haystack))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes().last() == Some(&self) && self.is_ascii()
+        }
+    }
+
+    impl<'a> Pattern<&'a str> for AsciiNoCase {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            AsciiSearcher {
+                ignore_case: true,
+                ..Ascii(self.0).into_searcher(haystack)
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.as_bytes()
+                .first()
+                .map(|b| b.eq_ignore_ascii_case(&self.0))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes()
+                .last()
+                .map(|b| b.eq_ignore_ascii_case(&self.0))
+                .unwrap_or(false)
+        }
+    }
+
+    // A set of ASCII bytes, matching any single one of them.
+    // Bytes >= 0x80 can not be inserted, since matching them could
+    // yield a cursor in the middle of a multi-byte char.
+    #[derive(Clone, Copy)]
+    pub struct AsciiSet(slice::AsciiSet);
+
+    impl AsciiSet {
+        pub fn new(bytes: &[u8]) -> AsciiSet {
+            bytes.iter().cloned().collect()
+        }
+
+        pub fn insert(&mut self, b: u8) {
+            assert!(b.is_ascii(), "AsciiSet can only contain ASCII bytes");
+            self.0.insert(b);
+        }
+
+        pub fn contains(&self, b: u8) -> bool {
+            self.0.contains(b)
+        }
+    }
+
+    impl ::core::iter::FromIterator<u8> for AsciiSet {
+        fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> AsciiSet {
+            let mut set = AsciiSet(slice::AsciiSet::new(&[]));
+            for b in iter {
+                set.insert(b);
+            }
+            set
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct AsciiSetSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        set: AsciiSet,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a] AsciiSetSearcher<'a> {} }
+
+    unsafe impl<'a> Searcher<&'a str> for AsciiSetSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                if self.set.contains(*p) {
+                    self.start = p.offset(1);
+                    SearchStep::Match(p, self.start)
+                } else {
+                    // Rejects have to span whole chars
+                    self.start = next_char(p, self.end).1;
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if self.set.contains(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    // Rejects have to span whole chars
+                    let (_, next) = next_char(p, self.end);
+                    self.start = next;
+
+                    if !self.set.contains(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a str> for AsciiSetSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a str> for AsciiSetSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if self.set.contains(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    // Rejects have to span whole chars
+                    let (_, prev) = prev_char(self.start, p);
+                    self.end = prev;
+
+                    if !self.set.contains(*prev) {
+                        return Some((prev, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a str> for AsciiSetSearcher<'a> {}
+
+    impl<'a> Pattern<&'a str> for AsciiSet {
+        type Searcher = AsciiSetSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            AsciiSetSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                set: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.as_bytes()
+                .first()
+                .map(|&b| self.contains(b))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes()
+                .last()
+                .map(|&b| self.contains(b))
+                .unwrap_or(false)
+        }
+    }
+
+    // Matches either of two bytes, see `AsciiFewSearcher`. Like for
+    // `Ascii` the bytes can't be set directly, as they have to be ASCII.
+    #[derive(Clone, Copy)]
+    pub struct Ascii2(u8, u8);
+
+    impl Ascii2 {
+        // Returns `None` if either byte is not ASCII.
+        pub const fn new(a: u8, b: u8) -> Option<Ascii2> {
+            if a.is_ascii() && b.is_ascii() {
+                Some(Ascii2(a, b))
+            } else {
+                None
+            }
+        }
+    }
+
+    // Matches any of three bytes, see `AsciiFewSearcher`.
+    #[derive(Clone, Copy)]
+    pub struct Ascii3(u8, u8, u8);
+
+    impl Ascii3 {
+        // Returns `None` if any of the bytes is not ASCII.
+        pub const fn new(a: u8, b: u8, c: u8) -> Option<Ascii3> {
+            if a.is_ascii() && b.is_ascii() && c.is_ascii() {
+                Some(Ascii3(a, b, c))
+            } else {
+                None
+            }
+        }
+    }
+
+    // Searcher for two or three bytes, which scans a word at a time
+    // instead of looking up every byte in a table like `AsciiSet` does.
+    #[derive(Clone)]
+    pub struct AsciiFewSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        // For two needles the last one is a repeat of the second
+        needles: [u8; 3],
+        three: bool,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a] AsciiFewSearcher<'a> { needles, three } }
+
+    impl<'a> AsciiFewSearcher<'a> {
+        fn new(haystack: &'a str, needles: [u8; 3], three: bool) -> Self {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            AsciiFewSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                needles,
+                three,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_match(&self, b: u8) -> bool {
+            self.needles.contains(&b)
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a str> for AsciiFewSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                if self.is_match(*p) {
+                    self.start = p.offset(1);
+                    SearchStep::Match(p, self.start)
+                } else {
+                    // Rejects have to span whole chars
+                    self.start = next_char(p, self.end).1;
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            let [n1, n2, n3] = self.needles;
+            unsafe {
+                let rest = ::core::slice::from_raw_parts(self.start,
+                    self.end as usize - self.start as usize);
+
+                let found = if self.three {
+                    memchr::memchr3(n1, n2, n3, rest)
+                } else {
+                    memchr::memchr2(n1, n2, rest)
+                };
+
+                match found {
+                    Some(i) => {
+                        let p = self.start.add(i);
+                        self.start = p.offset(1);
+                        Some((p, self.start))
+                    }
+                    None => {
+                        self.start = self.end;
+                        None
+                    }
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    // Rejects have to span whole chars
+                    let (_, next) = next_char(p, self.end);
+                    self.start = next;
+
+                    if !self.is_match(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a str> for AsciiFewSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a str> for AsciiFewSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if self.is_match(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    // Rejects have to span whole chars
+                    let (_, prev) = prev_char(self.start, p);
+                    self.end = prev;
+
+                    if !self.is_match(*prev) {
+                        return Some((prev, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a str> for AsciiFewSearcher<'a> {}
+
+    impl<'a> Pattern<&'a str> for Ascii2 {
+        type Searcher = AsciiFewSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            AsciiFewSearcher::new(haystack, [self.0, self.1, self.1], false)
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.as_bytes()
+                .first()
+                .map(|&b| b == self.0 || b == self.1)
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes()
+                .last()
+                .map(|&b| b == self.0 || b == self.1)
+                .unwrap_or(false)
+        }
+    }
+
+    impl<'a> Pattern<&'a str> for Ascii3 {
+        type Searcher = AsciiFewSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            AsciiFewSearcher::new(haystack, [self.0, self.1, self.2], true)
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.as_bytes()
+                .first()
+                .map(|&b| b == self.0 || b == self.1 || b == self.2)
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes()
+                .last()
+                .map(|&b| b == self.0 || b == self.1 || b == self.2)
+                .unwrap_or(false)
+        }
+    }
+
+    // Whether `p` is at a char boundary, ie. not at a UTF-8 continuation byte.
+    unsafe fn is_char_boundary(p: *const u8, end: *const u8) -> bool {
+        p == end || (*p as i8) >= -0x40
+    }
+
+    // Decodes the char starting at `p`, returning it together with
+    // the cursor right after its encoding.
+    // `p` has to lie on a char boundary before `end`.
+    unsafe fn next_char(p: *const u8, end: *const u8) -> (char, *const u8) {
+        let rest = ::core::slice::from_raw_parts(p, end as usize - p as usize);
+        let c = ::core::str::from_utf8_unchecked(rest).chars().next().unwrap();
+
+        (c, p.add(c.len_utf8()))
+    }
+
+    // Decodes the char ending at `p`, returning it together with
+    // the cursor right before its encoding.
+    // `p` has to lie on a char boundary after `start`.
+    unsafe fn prev_char(start: *const u8, p: *const u8) -> (char, *const u8) {
+        let rest = ::core::slice::from_raw_parts(start, p as usize - start as usize);
+        let c = ::core::str::from_utf8_unchecked(rest).chars().next_back().unwrap();
+
+        (c, p.sub(c.len_utf8()))
+    }
+
+    // Substring searcher, generic over how the needle is stored so that
+    // owned needles like `String` can be moved into it.
+    //
+    // The needle does not have to be valid UTF-8, occurrences that don't
+    // begin and end at char boundaries of the haystack are skipped.
+    #[derive(Clone)]
+    pub struct StrSearcher<'a, N> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        needle: N,
+        ignore_case: bool,
+        // Continue searching one char behind the begin of a match
+        // instead of behind its end
+        overlapping: bool,
+        // Set for all non-empty needles, unless ignoring case
+        two_way: Option<two_way::TwoWay>,
+        // Only set for the empty needle
+        empty_needle: Option<EmptyNeedle>,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl<'a, N: AsRef<[u8]>> ::core::fmt::Debug for StrSearcher<'a, N> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let remaining = unsafe {
+                debug_range::<&str>(self.haystack, self.start, self.end)
+            };
+            f.debug_struct("StrSearcher")
+                .field("remaining", &remaining)
+                .field("needle", &DebugLossy(self.needle.as_ref()))
+                .field("ignore_case", &self.ignore_case)
+                .field("overlapping", &self.overlapping)
+                .finish()
+        }
+    }
+
+    impl<'a, N> StrSearcher<'a, N> where N: AsRef<[u8]> {
+        fn new(haystack: &'a str, needle: N) -> Self {
+            Self::with_ignore_case(haystack, needle, false)
+        }
+
+        // The two-way table only works for exact matches, so it is not
+        // built when ignoring case.
+        fn with_ignore_case(haystack: &'a str, needle: N, ignore_case: bool) -> Self {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            let empty_needle = if needle.as_ref().is_empty() {
+                Some(EmptyNeedle::new())
+            } else {
+                None
+            };
+            let two_way = if needle.as_ref().is_empty() || ignore_case {
+                None
+            } else {
+                Some(two_way::TwoWay::new(needle.as_ref()))
+            };
+            StrSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                needle,
+                ignore_case,
+                overlapping: false,
+                two_way,
+                empty_needle,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        // Finds the begin of the next occurence of the (non-empty) needle
+        // in `[start, end)` without advancing the searcher.
+        fn find(&mut self) -> Option<*const u8> {
+            let needle = self.needle.as_ref();
+            let mut from = self.start;
+            loop {
+                unsafe {
+                    let rest = ::core::slice::from_raw_parts(from,
+                        self.end as usize - from as usize);
+
+                    let i = match self.two_way {
+                        Some(ref mut two_way) => two_way.find(needle, rest),
+                        // Only unset when ignoring case, as the empty
+                        // needle doesn't get here
+                        None => {
+                            rest.windows(needle.len())
+                                .position(|w| w.eq_ignore_ascii_case(needle))
+                        }
+                    }?;
+
+                    let p = from.add(i);
+                    let e = p.add(needle.len());
+                    if is_char_boundary(p, self.end) && is_char_boundary(e, self.end) {
+                        return Some(p);
+                    }
+                    from = p.offset(1);
+                }
+            }
+        }
+    }
+
+    unsafe impl<'a, N> Searcher<&'a str> for StrSearcher<'a, N>
+        where N: AsRef<[u8]>,
+    {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        // The two-way state only depends on the needle, so a resumed
+        // searcher continues with the same state.
+        //
+        // The empty needle may have matched at `start` already, the
+        // position is then behind the following char. Only once the
+        // searcher is done there is no such char, and a searcher resumed
+        // at the back yields the empty match there again.
+        fn position(&self) -> *const u8 {
+            match self.empty_needle {
+                Some(ref empty) if !empty.is_match_fw && self.start != self.end => unsafe {
+                    next_char(self.start, self.end).1
+                },
+                _ => self.start,
+            }
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            assert!(!self.overlapping,
+                    "overlapping matches have no rejects in between");
+
+            if let Some(ref mut empty) = self.empty_needle {
+                let end = self.end;
+                return empty.next(&mut self.start, end, |p| unsafe {
+                    next_char(p, end).1
+                });
+            }
+
+            let p = self.start;
+            match self.find() {
+                Some(m) if m == p => unsafe {
+                    self.start = p.add(self.needle.as_ref().len());
+                    SearchStep::Match(p, self.start)
+                },
+                Some(m) => {
+                    self.start = m;
+                    SearchStep::Reject(p, m)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let end = self.end;
+                return empty.next_match(&mut self.start, end, |p| unsafe {
+                    next_char(p, end).1
+                });
+            }
+
+            match self.find() {
+                Some(p) => unsafe {
+                    let match_end = p.add(self.needle.as_ref().len());
+                    self.start = if self.overlapping {
+                        next_char(p, self.end).1
+                    } else {
+                        match_end
+                    };
+                    Some((p, match_end))
+                },
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            assert!(!self.overlapping,
+                    "overlapping matches have no rejects in between");
+
+            if let Some(ref mut empty) = self.empty_needle {
+                let end = self.end;
+                return empty.next_reject(&mut self.start, end, |p| unsafe {
+                    next_char(p, end).1
+                });
+            }
+
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some(m) if m == p => unsafe {
+                        self.start = p.add(self.needle.as_ref().len());
+                    },
+                    Some(m) => {
+                        self.start = m;
+                        return Some((p, m));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some((p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, N> FusedSearcher<&'a str> for StrSearcher<'a, N>
+        where N: AsRef<[u8]>,
+    {}
+
+    // Haystacks shorter than this are searched naively by
+    // `first_match_offset`, as building the two-way tables would take
+    // longer than the search itself.
+    const SHORT_HAYSTACK: usize = 64;
+
+    impl<'a, 'b> Pattern<&'a str> for &'b str {
+        type Searcher = StrSearcher<'a, &'b str>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            StrSearcher::new(haystack, self)
+        }
+
+        unsafe fn into_searcher_at(self, haystack: &'a str, cursor: *const u8) -> Self::Searcher {
+            let mut searcher = StrSearcher::new(haystack, self);
+            searcher.start = resume_cursor::<&str>(searcher.haystack, cursor);
+            searcher
+        }
+
+        // As the needle is UTF-8 too, every occurrence lies on char
+        // boundaries and none has to be skipped.
+        fn first_match_offset(self, haystack: &'a str) -> Option<usize> {
+            if haystack.len() >= SHORT_HAYSTACK {
+                let mut searcher = self.into_searcher(haystack);
+                return searcher.next_match().map(|(begin, _)| unsafe {
+                    <&str>::offset_from_start(searcher.haystack(), begin)
+                });
+            }
+            if self.is_empty() {
+                return Some(0);
+            }
+            haystack.as_bytes()
+                .windows(self.len())
+                .position(|w| w == self.as_bytes())
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.as_bytes().starts_with(self.as_bytes())
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes().ends_with(self.as_bytes())
+        }
+    }
+
+    // Searches for the bytes directly, without requiring them to be UTF-8.
+    // Occurrences that begin or end inside a char never match.
+    impl<'a, 'b> Pattern<&'a str> for &'b [u8] {
+        type Searcher = StrSearcher<'a, &'b [u8]>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            StrSearcher::new(haystack, self)
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.as_bytes().starts_with(self)
+                && haystack.is_char_boundary(self.len())
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes().ends_with(self)
+                && haystack.is_char_boundary(haystack.len() - self.len())
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'a, 'b> Pattern<&'a str> for &'b String {
+        type Searcher = StrSearcher<'a, &'b str>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            self.as_str().into_searcher(haystack)
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            self.as_str().is_prefix_of(haystack)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes().ends_with(self.as_bytes())
+        }
+    }
+
+    // The searcher takes ownership of the needle, so it doesn't borrow
+    // from wherever the `String` was built.
+    #[cfg(feature = "alloc")]
+    impl<'a> Pattern<&'a str> for String {
+        type Searcher = StrSearcher<'a, String>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            StrSearcher::new(haystack, self)
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            self.as_str().is_prefix_of(haystack)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes().ends_with(self.as_bytes())
+        }
+    }
+
+    impl<'a, 'b> Pattern<&'a str> for generic::Overlapping<&'b str> {
+        type Searcher = StrSearcher<'a, &'b str>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            StrSearcher {
+                overlapping: true,
+                ..StrSearcher::new(haystack, self.0)
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            self.0.is_prefix_of(haystack)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes().ends_with(self.0.as_bytes())
+        }
+    }
+
+    // Substring pattern like `&str`, but ignoring ASCII case differences.
+    // Non-ASCII bytes have to match exactly. The matched ranges keep the
+    // casing of the haystack.
+    #[derive(Clone, Copy)]
+    pub struct NoCaseAscii<'b>(pub &'b str);
+
+    impl<'a, 'b> Pattern<&'a str> for NoCaseAscii<'b> {
+        type Searcher = StrSearcher<'a, &'b str>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            StrSearcher::with_ignore_case(haystack, self.0, true)
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            let needle = self.0.as_bytes();
+            haystack.as_bytes()
+                .get(..needle.len())
+                .map(|b| b.eq_ignore_ascii_case(needle))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            let needle = self.0.as_bytes();
+            haystack.len().checked_sub(needle.len())
+                .map(|i| haystack.as_bytes()[i..].eq_ignore_ascii_case(needle))
+                .unwrap_or(false)
+        }
+    }
+
+    // A glob pattern where `?` matches any single char and `*` matches any
+    // run of chars, all other chars match themselves.
+    //
+    // Matches are leftmost-shortest: of all matches starting at the
+    // leftmost possible position the shortest one is yielded, so `*` is
+    // effectively lazy, eg. `c*e` matches `"cde"` in `"cdeee"`, and a
+    // trailing `*` matches nothing. A pattern that can match the empty
+    // string yields a zero-width match at every char boundary.
+    #[cfg(feature = "alloc")]
+    #[derive(Clone, Copy)]
+    pub struct Glob<'b>(pub &'b str);
+
+    #[cfg(feature = "alloc")]
+    #[derive(Clone)]
+    pub struct GlobSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        position: *const u8,
+        end: *const u8,
+        glob: Vec<char>,
+        // Used to not yield an empty match right behind the previous match
+        last_match_end: Option<*const u8>,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'a> ::core::fmt::Debug for GlobSearcher<'a> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let remaining = unsafe {
+                debug_range::<&str>(self.haystack, self.position, self.end)
+            };
+            f.debug_struct("GlobSearcher")
+                .field("remaining", &remaining)
+                .field("glob", &self.glob.iter().collect::<String>())
+                .finish()
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'a> GlobSearcher<'a> {
+        // Returns the end of the shortest match starting at `p`,
+        // or with `to_end` of a match reaching the end of the haystack.
+        //
+        // Each segment between two `*` is placed as early as possible,
+        // which gives the shortest match, so on a mismatch only the last
+        // `*` has to be extended.
+        fn match_at(&self, p: *const u8, to_end: bool) -> Option<*const u8> {
+            let mut gi = 0;
+            let mut t = p;
+            // Index of the last `*` and where its run currently ends
+            let mut star = None;
+
+            loop {
+                if gi == self.glob.len() {
+                    if !to_end || t == self.end {
+                        return Some(t);
+                    }
+                } else {
+                    match self.glob[gi] {
+                        '*' => {
+                            star = Some((gi, t));
+                            gi += 1;
+                            continue;
+                        }
+                        g => if t != self.end {
+                            let (c, next) = unsafe { next_char(t, self.end) };
+                            if g == '?' || g == c {
+                                t = next;
+                                gi += 1;
+                                continue;
+                            }
+                        }
+                    }
+                }
+
+                match star {
+                    Some((star_gi, star_end)) if star_end != self.end => {
+                        let next = unsafe { next_char(star_end, self.end).1 };
+                        star = Some((star_gi, next));
+                        gi = star_gi + 1;
+                        t = next;
+                    }
+                    _ => return None,
+                }
+            }
+        }
+
+        // Finds the leftmost match at or after the position.
+        fn find(&self) -> Option<(*const u8, *const u8)> {
+            let mut p = self.position;
+            loop {
+                if let Some(e) = self.match_at(p, false) {
+                    if e != p || self.last_match_end != Some(p) {
+                        return Some((p, e));
+                    }
+                }
+                if p == self.end {
+                    return None;
+                }
+                p = unsafe { next_char(p, self.end).1 };
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    unsafe impl<'a> Searcher<&'a str> for GlobSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            let p = self.position;
+            match self.find() {
+                Some((begin, end)) if begin == p => {
+                    self.position = end;
+                    self.last_match_end = Some(end);
+                    SearchStep::Match(begin, end)
+                }
+                Some((begin, _)) => {
+                    self.position = begin;
+                    SearchStep::Reject(p, begin)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.position = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            match self.find() {
+                Some((begin, end)) => {
+                    self.position = end;
+                    self.last_match_end = Some(end);
+                    Some((begin, end))
+                }
+                None => {
+                    self.position = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.position != self.end {
+                let p = self.position;
+                match self.find() {
+                    Some((begin, end)) if begin == p => {
+                        self.position = end;
+                        self.last_match_end = Some(end);
+                    }
+                    Some((begin, _)) => {
+                        self.position = begin;
+                        return Some((p, begin));
+                    }
+                    None => {
+                        self.position = self.end;
+                        return Some((p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'a> FusedSearcher<&'a str> for GlobSearcher<'a> {}
+
+    #[cfg(feature = "alloc")]
+    impl<'a, 'b> Pattern<&'a str> for Glob<'b> {
+        type Searcher = GlobSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            GlobSearcher {
+                haystack: (begin, end),
+                position: begin,
+                end,
+                glob: self.0.chars().collect(),
+                last_match_end: None,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            let searcher = self.into_searcher(haystack);
+            searcher.match_at(searcher.position, false).is_some()
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            let searcher = self.into_searcher(haystack);
+            haystack.char_indices()
+                .map(|(i, _)| i)
+                .chain(Some(haystack.len()))
+                .any(|i| unsafe {
+                    searcher.match_at(searcher.position.add(i), true).is_some()
+                })
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct CharSliceSearcher<'a, 'b> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        chars: &'b [char],
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a, 'b] CharSliceSearcher<'a, 'b> { chars } }
+
+    unsafe impl<'a, 'b> Searcher<&'a str> for CharSliceSearcher<'a, 'b> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            let (c, next) = unsafe { next_char(p, self.end) };
+            self.start = next;
+            if self.chars.contains(&c) {
+                SearchStep::Match(p, next)
+            } else {
+                SearchStep::Reject(p, next)
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    let (c, next) = next_char(p, self.end);
+                    self.start = next;
+
+                    if self.chars.contains(&c) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    let (c, next) = next_char(p, self.end);
+                    self.start = next;
+
+                    if !self.chars.contains(&c) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every char takes at least one of the remaining bytes
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a, 'b> FusedSearcher<&'a str> for CharSliceSearcher<'a, 'b> {}
+
+    unsafe impl<'a, 'b> ReverseSearcher<&'a str> for CharSliceSearcher<'a, 'b> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    let (c, prev) = prev_char(self.start, p);
+                    self.end = prev;
+
+                    if self.chars.contains(&c) {
+                        return Some((prev, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    let (c, prev) = prev_char(self.start, p);
+                    self.end = prev;
+
+                    if !self.chars.contains(&c) {
+                        return Some((prev, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, 'b> DoubleEndedSearcher<&'a str> for CharSliceSearcher<'a, 'b> {}
+
+    impl<'a, 'b> Pattern<&'a str> for &'b [char] {
+        type Searcher = CharSliceSearcher<'a, 'b>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            CharSliceSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                chars: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.chars()
+                .next()
+                .map(|c| self.contains(&c))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.chars()
+                .next_back()
+                .map(|c| self.contains(&c))
+                .unwrap_or(false)
+        }
+    }
+
+    // Something that decides whether a single char matches,
+    // used to share `CharPredicateSearcher` between char patterns.
+    pub trait CharEq {
+        fn matches(&mut self, c: char) -> bool;
+    }
+
+    impl<F> CharEq for F where F: FnMut(char) -> bool {
+        fn matches(&mut self, c: char) -> bool {
+            self(c)
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct CharPredicateSearcher<'a, F> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        predicate: F,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a, F] CharPredicateSearcher<'a, F> {} }
+
+    unsafe impl<'a, F> Searcher<&'a str> for CharPredicateSearcher<'a, F>
+        where F: CharEq
+    {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            let (c, next) = unsafe { next_char(p, self.end) };
+            self.start = next;
+            if self.predicate.matches(c) {
+                SearchStep::Match(p, next)
+            } else {
+                SearchStep::Reject(p, next)
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    let (c, next) = next_char(p, self.end);
+                    self.start = next;
+
+                    if self.predicate.matches(c) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    let (c, next) = next_char(p, self.end);
+                    self.start = next;
+
+                    if !self.predicate.matches(c) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every char takes at least one of the remaining bytes
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a, F> FusedSearcher<&'a str> for CharPredicateSearcher<'a, F>
+        where F: CharEq
+    {}
+
+    unsafe impl<'a, F> ReverseSearcher<&'a str> for CharPredicateSearcher<'a, F>
+        where F: CharEq
+    {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    let (c, prev) = prev_char(self.start, p);
+                    self.end = prev;
+
+                    if self.predicate.matches(c) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    let (c, prev) = prev_char(self.start, p);
+                    self.end = prev;
+
+                    if !self.predicate.matches(c) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, F> DoubleEndedSearcher<&'a str> for CharPredicateSearcher<'a, F>
+        where F: CharEq {}
+
+    // Also covers predicates only known at runtime, as
+    // `Box<dyn FnMut(char) -> bool>` and `&mut dyn FnMut(char) -> bool` are
+    // closures themselves. The searcher keeps them as they are, so every
+    // char costs one dynamic call and nothing is boxed again.
+    impl<'a, F> Pattern<&'a str> for F where F: FnMut(char) -> bool {
+        type Searcher = CharPredicateSearcher<'a, F>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            CharPredicateSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                predicate: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.chars()
+                .next()
+                .map(self)
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.chars()
+                .next_back()
+                .map(self)
+                .unwrap_or(false)
+        }
+    }
+
+    // Zero-sized patterns for common char classes, each matching a single
+    // char. They all use `CharPredicateSearcher` with a function pointer,
+    // so there is only one searcher type to instantiate.
+    pub mod classes {
+        use super::*;
+
+        macro_rules! char_class {
+            ($($(#[$attr:meta])* $name:ident => $predicate:expr;)*) => {$(
+                $(#[$attr])*
+                #[derive(Clone, Copy)]
+                pub struct $name;
+
+                impl<'a> Pattern<&'a str> for $name {
+                    type Searcher = CharPredicateSearcher<'a, fn(char) -> bool>;
+
+                    fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+                        ($predicate as fn(char) -> bool).into_searcher(haystack)
+                    }
+
+                    fn is_prefix_of(self, haystack: &'a str) -> bool {
+                        ($predicate as fn(char) -> bool).is_prefix_of(haystack)
+                    }
+
+                    fn is_suffix_of(self, haystack: &'a str) -> bool
+                        where Self::Searcher: ReverseSearcher<&'a str> {
+                        ($predicate as fn(char) -> bool).is_suffix_of(haystack)
+                    }
+                }
+            )*}
+        }
+
+        char_class! {
+            // `char::is_alphabetic`
+            Alpha => char::is_alphabetic;
+            // ASCII digits only, like `char::is_ascii_digit`
+            Digit => |c: char| c.is_ascii_digit();
+            // `char::is_alphanumeric`
+            AlphaNum => char::is_alphanumeric;
+            // `char::is_ascii_hexdigit`
+            HexDigit => |c: char| c.is_ascii_hexdigit();
+        }
+
+        impl_pattern_for_ref! {
+            [] Alpha,
+            [] Digit,
+            [] AlphaNum,
+            [] HexDigit,
+        }
+    }
+
+    // Mutable string slices use the same searchers as `&str`, running on
+    // a shared view of the haystack and handing out mutable cursors.
+    //
+    // The pieces created from a searchers ranges are only disjoint if the
+    // ranges don't overlap. This holds for the ranges used by `split`,
+    // and for the matches of all patterns implemented here.
+    // Like for `&str`, `cursor_at_offset` leaves char boundaries
+    // to the caller.
+    impl SearchPtrs for &mut str {
+        type Haystack = (*mut u8, *mut u8);
+        type Cursor = *mut u8;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            begin as usize - haystack.0 as usize
+        }
+
+        unsafe fn range_to_self(hs: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            let slice = ::core::slice::from_raw_parts_mut(start,
+                Self::cursor_distance(hs, start, end));
+            ::core::str::from_utf8_unchecked_mut(slice)
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            hs.0
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            hs.1
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> ::core::cmp::Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            b as usize - a as usize
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            hs.1 as usize - hs.0 as usize
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            hs.1 as usize - cursor as usize
+        }
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            hs.0.add(n)
+        }
+        unsafe fn cursor_advance(hs: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            let p = cursor.add(n);
+            assert!(is_char_boundary(p, hs.1), "cursor is not at a char boundary");
+            p
+        }
+        unsafe fn cursor_next(hs: Self::Haystack, cursor: Self::Cursor) -> Self::Cursor {
+            let (c, _) = next_char(cursor, hs.1);
+            cursor.add(c.len_utf8())
+        }
+    }
+
+    // Adapts a `&str` searcher to a `&mut str` haystack.
+    pub struct MutStrSearcher<S> {
+        haystack: (*mut u8, *mut u8),
+        // The begin of the shared view the inner searcher runs on
+        view: *const u8,
+        inner: S,
+    }
+
+    // Not `Clone`, as two searchers would hand out aliasing pieces.
+    // The inner searcher runs on a view with the same offsets.
+    impl<S: ::core::fmt::Debug> ::core::fmt::Debug for MutStrSearcher<S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_tuple("MutStrSearcher").field(&self.inner).finish()
+        }
+    }
+
+    impl<'a, S> MutStrSearcher<S> where S: Searcher<&'a str> {
+        fn new<F>(haystack: &'a mut str, make_searcher: F) -> Self
+            where F: FnOnce(&'a str) -> S
+        {
+            let begin = haystack.as_mut_ptr();
+            let end = unsafe {
+                begin.add(haystack.len())
+            };
+            // The view is derived from `begin` as well, so that it stays
+            // valid next to the mutable pointers.
+            let view = unsafe {
+                ::core::str::from_utf8_unchecked(
+                    ::core::slice::from_raw_parts(begin, haystack.len()))
+            };
+            MutStrSearcher {
+                haystack: (begin, end),
+                view: view.as_ptr(),
+                inner: make_searcher(view),
+            }
+        }
+    }
+
+    impl<S> MutStrSearcher<S> {
+        // The cursors of the inner searcher come from the shared view and
+        // only grant read access. They are turned into offsets and
+        // re-derived from `begin`, so that all pieces stem from that
+        // single mutable pointer and may be written to.
+        fn rebase(&self, range: (*const u8, *const u8)) -> (*mut u8, *mut u8) {
+            unsafe {
+                (self.haystack.0.add(range.0.offset_from(self.view) as usize),
+                 self.haystack.0.add(range.1.offset_from(self.view) as usize))
+            }
+        }
+    }
+
+    unsafe impl<'a, S> Searcher<&'a mut str> for MutStrSearcher<S>
+        where S: Searcher<&'a str>
+    {
+        fn haystack(&self) -> (*mut u8, *mut u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*mut u8> {
+            match self.inner.next() {
+                SearchStep::Match(begin, end) => {
+                    let (begin, end) = self.rebase((begin, end));
+                    SearchStep::Match(begin, end)
+                }
+                SearchStep::Reject(begin, end) => {
+                    let (begin, end) = self.rebase((begin, end));
+                    SearchStep::Reject(begin, end)
+                }
+                SearchStep::Done => SearchStep::Done,
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.inner.next_match().map(|range| self.rebase(range))
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.inner.next_reject().map(|range| self.rebase(range))
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            self.inner.match_bounds()
+        }
+    }
+
+    impl<'a, S> FusedSearcher<&'a mut str> for MutStrSearcher<S>
+        where S: FusedSearcher<&'a str>
+    {}
+
+    unsafe impl<'a, S> ReverseSearcher<&'a mut str> for MutStrSearcher<S>
+        where S: ReverseSearcher<&'a str>
+    {
+        fn next_match_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.inner.next_match_back().map(|range| self.rebase(range))
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.inner.next_reject_back().map(|range| self.rebase(range))
+        }
+    }
+
+    impl<'a, S> DoubleEndedSearcher<&'a mut str> for MutStrSearcher<S>
+        where S: DoubleEndedSearcher<&'a str> {}
+
+    impl<'a> Pattern<&'a mut str> for Ascii {
+        type Searcher = MutStrSearcher<AsciiSearcher<'a>>;
+
+        fn into_searcher(self, haystack: &'a mut str) -> Self::Searcher {
+            MutStrSearcher::new(haystack, |view| self.into_searcher(view))
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut str) -> bool {
+            self.is_prefix_of(&*haystack)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut str> {
+            haystack.as_bytes().last() == Some(&self.0)
+        }
+    }
+
+    impl<'a> Pattern<&'a mut str> for char {
+        type Searcher = MutStrSearcher<StrSearcher<'a, slice::EncodedChar>>;
+
+        fn into_searcher(self, haystack: &'a mut str) -> Self::Searcher {
+            MutStrSearcher::new(haystack, |view| {
+                StrSearcher::new(view, slice::EncodedChar::new(self))
+            })
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut str) -> bool {
+            haystack.starts_with(self)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut str> {
+            haystack.ends_with(self)
+        }
+    }
+
+    // Matches any char inside the inclusive range.
+    #[derive(Clone)]
+    pub struct CharRange(pub ::core::ops::RangeInclusive<char>);
+
+    impl CharEq for CharRange {
+        fn matches(&mut self, c: char) -> bool {
+            self.0.contains(&c)
+        }
+    }
+
+    impl<'a> Pattern<&'a str> for CharRange {
+        type Searcher = CharPredicateSearcher<'a, CharRange>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            CharPredicateSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                predicate: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.chars()
+                .next()
+                .map(|c| self.0.contains(&c))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.chars()
+                .next_back()
+                .map(|c| self.0.contains(&c))
+                .unwrap_or(false)
+        }
+    }
+
+    // Matches maximal runs of unicode whitespace,
+    // so that adjacent whitespace chars never produce empty fields.
+    // Rejects are likewise maximal runs of non-whitespace.
+    #[derive(Clone, Copy)]
+    pub struct Whitespace;
+
+    #[derive(Clone)]
+    pub struct WhitespaceSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a] WhitespaceSearcher<'a> {} }
+
+    impl<'a> WhitespaceSearcher<'a> {
+        // Advances `start` over chars whose whitespace-ness equals `ws`
+        fn skip_fw(&mut self, ws: bool) {
+            while self.start != self.end {
+                let (c, next) = unsafe { next_char(self.start, self.end) };
+                if c.is_whitespace() != ws {
+                    break;
+                }
+                self.start = next;
+            }
+        }
+
+        // Moves `end` back over chars whose whitespace-ness equals `ws`
+        fn skip_bw(&mut self, ws: bool) {
+            while self.start != self.end {
+                let (c, prev) = unsafe { prev_char(self.start, self.end) };
+                if c.is_whitespace() != ws {
+                    break;
+                }
+                self.end = prev;
+            }
+        }
+
+        fn next_run(&mut self, ws: bool) -> Option<(*const u8, *const u8)> {
+            self.skip_fw(!ws);
+            if self.start == self.end {
+                return None;
+            }
+            let p = self.start;
+            self.skip_fw(ws);
+            Some((p, self.start))
+        }
+
+        fn next_run_back(&mut self, ws: bool) -> Option<(*const u8, *const u8)> {
+            self.skip_bw(!ws);
+            if self.start == self.end {
+                return None;
+            }
+            let p = self.end;
+            self.skip_bw(ws);
+            Some((self.end, p))
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a str> for WhitespaceSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let ws = unsafe { next_char(self.start, self.end).0 }.is_whitespace();
+            let p = self.start;
+            self.skip_fw(ws);
+            if ws {
+                SearchStep::Match(p, self.start)
+            } else {
+                SearchStep::Reject(p, self.start)
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run(true)
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run(false)
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a str> for WhitespaceSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a str> for WhitespaceSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run_back(true)
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run_back(false)
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a str> for WhitespaceSearcher<'a> {}
+
+    impl<'a> Pattern<&'a str> for Whitespace {
+        type Searcher = WhitespaceSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            WhitespaceSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.chars()
+                .next()
+                .map(char::is_whitespace)
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.chars()
+                .next_back()
+                .map(char::is_whitespace)
+                .unwrap_or(false)
+        }
+    }
+
+    // Matches maximal runs of ASCII digits, so that each number in the
+    // haystack is yielded as a whole. Rejects are maximal runs of other chars.
+    #[derive(Clone, Copy)]
+    pub struct DigitRun;
+
+    #[derive(Clone)]
+    pub struct DigitRunSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a] DigitRunSearcher<'a> {} }
+
+    // As digits are ASCII, runs can be scanned byte by byte without
+    // ever ending inside a multi-byte char.
+    impl<'a> DigitRunSearcher<'a> {
+        // Advances `start` over bytes whose digit-ness equals `digit`
+        fn skip_fw(&mut self, digit: bool) {
+            while self.start != self.end {
+                if unsafe { *self.start }.is_ascii_digit() != digit {
+                    break;
+                }
+                self.start = unsafe { self.start.offset(1) };
+            }
+        }
+
+        // Moves `end` back over bytes whose digit-ness equals `digit`
+        fn skip_bw(&mut self, digit: bool) {
+            while self.start != self.end {
+                if unsafe { *self.end.offset(-1) }.is_ascii_digit() != digit {
+                    break;
+                }
+                self.end = unsafe { self.end.offset(-1) };
+            }
+        }
+
+        fn next_run(&mut self, digit: bool) -> Option<(*const u8, *const u8)> {
+            self.skip_fw(!digit);
+            if self.start == self.end {
+                return None;
+            }
+            let p = self.start;
+            self.skip_fw(digit);
+            Some((p, self.start))
+        }
+
+        fn next_run_back(&mut self, digit: bool) -> Option<(*const u8, *const u8)> {
+            self.skip_bw(!digit);
+            if self.start == self.end {
+                return None;
+            }
+            let p = self.end;
+            self.skip_bw(digit);
+            Some((self.end, p))
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a str> for DigitRunSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let digit = unsafe { *self.start }.is_ascii_digit();
+            let p = self.start;
+            self.skip_fw(digit);
+            if digit {
+                SearchStep::Match(p, self.start)
+            } else {
+                SearchStep::Reject(p, self.start)
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run(true)
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run(false)
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a str> for DigitRunSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a str> for DigitRunSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run_back(true)
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.next_run_back(false)
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a str> for DigitRunSearcher<'a> {}
+
+    impl<'a> Pattern<&'a str> for DigitRun {
+        type Searcher = DigitRunSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            DigitRunSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.as_bytes()
+                .first()
+                .map(u8::is_ascii_digit)
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.as_bytes()
+                .last()
+                .map(u8::is_ascii_digit)
+                .unwrap_or(false)
+        }
+    }
+
+    // Matches line terminators, ie. `"\r\n"` as a single match and
+    // a bare `"\n"`. A `'\r'` not followed by `'\n'` is not a terminator.
+    #[derive(Clone, Copy)]
+    pub struct LineTerminator;
+
+    #[derive(Clone)]
+    pub struct LineTerminatorSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    impl_searcher_debug! { &'a str => ['a] LineTerminatorSearcher<'a> {} }
+
+    impl<'a> LineTerminatorSearcher<'a> {
+        // Extends a match of the `'\n'` at `p` to a preceding `'\r'`
+        fn terminator_at(&self, p: *const u8) -> (*const u8, *const u8) {
+            unsafe {
+                if p != self.start && *p.offset(-1) == b'\r' {
+                    (p.offset(-1), p.offset(1))
+                } else {
+                    (p, p.offset(1))
+                }
+            }
+        }
+
+        fn find(&self) -> Option<(*const u8, *const u8)> {
+            unsafe {
+                let rest = ::core::slice::from_raw_parts(self.start,
+                    self.end as usize - self.start as usize);
+
+                memchr::memchr(b'\n', rest)
+                    .map(|i| self.terminator_at(self.start.add(i)))
+            }
+        }
+
+        fn find_back(&self) -> Option<(*const u8, *const u8)> {
+            unsafe {
+                let rest = ::core::slice::from_raw_parts(self.start,
+                    self.end as usize - self.start as usize);
+
+                rest.iter()
+                    .rposition(|&b| b == b'\n')
+                    .map(|i| self.terminator_at(self.start.add(i)))
+            }
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a str> for LineTerminatorSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            let p = self.start;
+            match self.find() {
+                Some((begin, end)) if begin == p => {
+                    self.start = end;
+                    SearchStep::Match(begin, end)
+                }
+                Some((begin, _)) => {
+                    self.start = begin;
+                    SearchStep::Reject(p, begin)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            match self.find() {
+                Some((begin, end)) => {
+                    self.start = end;
+                    Some((begin, end))
+                }
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some((begin, end)) if begin == p => {
+                        self.start = end;
+                    }
+                    Some((begin, _)) => {
+                        self.start = begin;
+                        return Some((p, begin));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some((p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a str> for LineTerminatorSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a str> for LineTerminatorSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            match self.find_back() {
+                Some((begin, end)) => {
+                    self.end = begin;
+                    Some((begin, end))
+                }
+                None => {
+                    self.end = self.start;
+                    None
+                }
+            }
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                let p = self.end;
+                match self.find_back() {
+                    Some((begin, end)) if end == p => {
+                        self.end = begin;
+                    }
+                    Some((_, end)) => {
+                        self.end = end;
+                        return Some((end, p));
+                    }
+                    None => {
+                        self.end = self.start;
+                        return Some((self.start, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a str> for LineTerminatorSearcher<'a> {}
+
+    impl<'a> Pattern<&'a str> for LineTerminator {
+        type Searcher = LineTerminatorSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            LineTerminatorSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            haystack.starts_with('\n') || haystack.starts_with("\r\n")
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            haystack.ends_with('\n')
+        }
+    }
+
+    // Matches any of several needles, using a byte trie built by
+    // `into_searcher` so that each position is only scanned as deep as the
+    // longest needle, independent of the number of needles.
+    //
+    // If several needles match at the same position the longest one wins
+    // (leftmost-longest), with ties between duplicate needles going to
+    // the one listed first. Empty needles never match.
+    // The index of the needle of the last match is available through
+    // `MultiStrSearcher::matched_needle` and `TaggedSearcher`.
+    #[cfg(feature = "alloc")]
+    #[derive(Clone, Copy)]
+    pub struct MultiStr<'b>(pub &'b [&'b str]);
+
+    #[cfg(feature = "alloc")]
+    #[derive(Clone)]
+    struct TrieNode {
+        children: Vec<(u8, usize)>,
+        needle: Option<usize>,
+    }
+
+    #[cfg(feature = "alloc")]
+    #[derive(Clone)]
+    pub struct MultiStrSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        trie: Vec<TrieNode>,
+        matched_needle: Option<usize>,
+        _marker: ::core::marker::PhantomData<&'a str>
+    }
+
+    #[cfg(feature = "alloc")]
+    impl_searcher_debug! { &'a str => ['a] MultiStrSearcher<'a> { matched_needle } }
+
+    #[cfg(feature = "alloc")]
+    impl<'a> MultiStrSearcher<'a> {
+        // The index of the needle that produced the last match returned
+        // from `next_match`.
+        pub fn matched_needle(&self) -> Option<usize> {
+            self.matched_needle
+        }
+
+        // Returns the length and index of the longest needle
+        // starting at `p`.
+        fn longest_at(&self, p: *const u8) -> Option<(usize, usize)> {
+            let mut node = 0;
+            let mut best = None;
+            let mut q = p;
+            while q != self.end {
+                let b = unsafe { *q };
+                match self.trie[node].children.iter().find(|c| c.0 == b) {
+                    Some(&(_, child)) => node = child,
+                    None => break,
+                }
+                q = unsafe { q.offset(1) };
+                if let Some(id) = self.trie[node].needle {
+                    best = Some((q as usize - p as usize, id));
+                }
+            }
+            best
+        }
+
+        // Finds the next match in `[start, end)` without advancing
+        // the searcher.
+        fn find(&self) -> Option<(*const u8, *const u8, usize)> {
+            let mut p = self.start;
+            while p != self.end {
+                if let Some((len, id)) = self.longest_at(p) {
+                    return Some((p, unsafe { p.add(len) }, id));
+                }
+                p = unsafe { p.offset(1) };
+            }
+            None
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    unsafe impl<'a> Searcher<&'a str> for MultiStrSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            let p = self.start;
+            match self.find() {
+                Some((begin, end, id)) if begin == p => {
+                    self.start = end;
+                    self.matched_needle = Some(id);
+                    SearchStep::Match(begin, end)
+                }
+                Some((begin, _, _)) => {
+                    self.start = begin;
+                    SearchStep::Reject(p, begin)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            match self.find() {
+                Some((begin, end, id)) => {
+                    self.start = end;
+                    self.matched_needle = Some(id);
+                    Some((begin, end))
+                }
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some((begin, end, _)) if begin == p => {
+                        self.start = end;
+                    }
+                    Some((begin, _, _)) => {
+                        self.start = begin;
+                        return Some((p, begin));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some((p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'a> FusedSearcher<&'a str> for MultiStrSearcher<'a> {}
+
+    // Any id would name a needle, so there is none to give before the
+    // first match.
+    #[cfg(feature = "alloc")]
+    impl<'a> TaggedSearcher<&'a str> for MultiStrSearcher<'a> {
+        fn last_match_id(&self) -> usize {
+            self.matched_needle.expect("no match has been found yet")
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<'a, 'b> Pattern<&'a str> for MultiStr<'b> {
+        type Searcher = MultiStrSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let mut trie = vec![TrieNode { children: vec![], needle: None }];
+            for (id, needle) in self.0.iter().enumerate() {
+                if needle.is_empty() {
+                    continue;
+                }
+                let mut node = 0;
+                for &b in needle.as_bytes() {
+                    let existing = trie[node].children.iter()
+                        .find(|c| c.0 == b)
+                        .map(|c| c.1);
+                    node = match existing {
+                        Some(child) => child,
+                        None => {
+                            trie.push(TrieNode { children: vec![], needle: None });
+                            let child = trie.len() - 1;
+                            trie[node].children.push((b, child));
+                            child
+                        }
+                    };
+                }
+                if trie[node].needle.is_none() {
+                    trie[node].needle = Some(id);
+                }
+            }
+
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            MultiStrSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                trie,
+                matched_needle: None,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            self.0.iter().any(|n| !n.is_empty() && haystack.starts_with(n))
+        }
+
+        fn is_suffix_of(self, haystack: &'a str) -> bool
+            where Self::Searcher: ReverseSearcher<&'a str> {
+            self.0.iter().any(|n| !n.is_empty() && haystack.ends_with(n))
+        }
+    }
+
+
+    impl_pattern_for_ref! {
+        [] Ascii,
+        [] AsciiNoCase,
+        [] AsciiSet,
+        [] Ascii2,
+        [] Ascii3,
+        [] CharRange,
+        [] Whitespace,
+        [] DigitRun,
+        [] LineTerminator,
+        ['b] NoCaseAscii<'b>,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl_pattern_for_ref! {
+        ['b] Glob<'b>,
+        ['b] MultiStr<'b>,
+    }
+}
+
+pub mod slice {
+    use super::*;
+    use core::ops::ControlFlow;
+
+    // `SearchPtrs for &mut [u8]` is the `T = u8` case of the impl
+    // in `generic_slice`.
+
+    // Derives the bounds of the haystack from a single raw pointer.
+    // All cursors, and the pieces later created from them, have to be
+    // derived from that one pointer: calling `as_mut_ptr` again would
+    // reborrow the slice uniquely and invalidate the earlier pointers.
+    fn bounds(haystack: &mut [u8]) -> (*mut u8, *mut u8) {
+        let len = haystack.len();
+        let begin = haystack.as_mut_ptr();
+        (begin, unsafe { begin.add(len) })
+    }
+
+    #[derive(Clone, Copy)]
+    pub struct Ascii(pub u8);
+
+    // Matches like `Ascii`, but ignoring ASCII case differences.
+    #[derive(Clone, Copy)]
+    pub struct AsciiNoCase(pub u8);
+
+    // The searchers of mutable haystacks are not `Clone`, since two of
+    // them would hand out aliasing pieces.
+    pub struct AsciiSearcher<'a> {
+        haystack: (*mut u8, *mut u8),
+        start: *mut u8,
+        end: *mut u8,
+        ascii: u8,
+        ignore_case: bool,
+        _marker: ::core::marker::PhantomData<&'a mut [u8]>
+    }
+
+    impl_searcher_debug! { &'a mut [u8] => ['a] AsciiSearcher<'a> { ascii as char, ignore_case } }
+
+    impl<'a> AsciiSearcher<'a> {
+        fn is_match(&self, b: u8) -> bool {
+            if self.ignore_case {
+                b.eq_ignore_ascii_case(&self.ascii)
+            } else {
+                b == self.ascii
+            }
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a mut [u8]> for AsciiSearcher<'a> {
+        fn haystack(&self) -> (*mut u8, *mut u8) {
+            self.haystack
+        }
+
+        fn position(&self) -> *mut u8 {
+            self.start
+        }
+
+        // There's no state besides the position, so the searcher jumps to
+        // the cursor directly. It's rederived from `start` to keep the
+        // provenance of the haystack.
+        fn skip_to(&mut self, cursor: *mut u8) {
+            assert!(self.start <= cursor && cursor <= self.end,
+                    "cursor lies outside the remaining range");
+            let offset = cursor as usize - self.start as usize;
+            self.start = unsafe { self.start.add(offset) };
+        }
+
+        fn next(&mut self) -> SearchStep<*mut u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                self.start = p.offset(1);
+                if self.is_match(*p) {
+                    SearchStep::Match(p, self.start)
+                } else {
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if self.is_match(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn try_fold_matches<B, F>(&mut self, init: B, mut f: F) -> ControlFlow<B, B>
+            where F: FnMut(B, (*mut u8, *mut u8)) -> ControlFlow<B, B>
+        {
+            let mut acc = init;
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if self.is_match(*p) {
+                        acc = match f(acc, (p, self.start)) {
+                            ControlFlow::Continue(acc) => acc,
+                            brk => return brk,
+                        };
+                    }
+                }
+            }
+            ControlFlow::Continue(acc)
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if !self.is_match(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a mut [u8]> for AsciiSearcher<'a> {}
+
+    // Both ends only ever move towards each other and yield what they
+    // step over, so no byte is yielded twice and the `&mut` pieces
+    // created from the ranges can't alias.
+    unsafe impl<'a> ReverseSearcher<&'a mut [u8]> for AsciiSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if self.is_match(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if !self.is_match(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a mut [u8]> for AsciiSearcher<'a> {}
+
+    impl<'a> Pattern<&'a mut [u8]> for Ascii {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            let (begin, end) = bounds(haystack);
+
+            AsciiSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end: end,
+                ascii: self.0,
+                ignore_case: false,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn first_match_offset(self, haystack: &'a mut [u8]) -> Option<usize> {
+            memchr::memchr(self.0, haystack)
+        }
+
+        unsafe fn into_searcher_at(self, haystack: &'a mut [u8], cursor: *mut u8)
+            -> Self::Searcher
+        {
+            let mut searcher = self.into_searcher(haystack);
+            searcher.start = resume_cursor::<&mut [u8]>(searcher.haystack, cursor);
+            searcher
+        }
+
+        fn count_in(self, haystack: &'a mut [u8]) -> usize {
+            haystack.iter().filter(|&&b| b == self.0).count()
+        }
+    }
+
+    // Shorthand for `Ascii`.
+    impl<'a> Pattern<&'a mut [u8]> for u8 {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            Ascii(self).into_searcher(haystack)
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack.first() == Some(&self)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack.last() == Some(&self)
+        }
+    }
+
+    impl<'a> Pattern<&'a mut [u8]> for AsciiNoCase {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            AsciiSearcher {
+                ignore_case: true,
+                ..Ascii(self.0).into_searcher(haystack)
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack
+                .first()
+                .map(|b| b.eq_ignore_ascii_case(&self.0))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack
+                .last()
+                .map(|b| b.eq_ignore_ascii_case(&self.0))
+                .unwrap_or(false)
+        }
+    }
+
+    // A set of bytes backed by a 256 bit table,
+    // matching any single one of them.
+    #[derive(Clone, Copy)]
+    pub struct AsciiSet {
+        table: [u64; 4],
+    }
+
+    impl AsciiSet {
+        pub fn new(bytes: &[u8]) -> AsciiSet {
+            bytes.iter().cloned().collect()
+        }
+
+        pub fn insert(&mut self, b: u8) {
+            self.table[(b >> 6) as usize] |= 1 << (b & 63);
+        }
+
+        pub fn contains(&self, b: u8) -> bool {
+            self.table[(b >> 6) as usize] & (1 << (b & 63)) != 0
+        }
+    }
+
+    impl ::core::iter::FromIterator<u8> for AsciiSet {
+        fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> AsciiSet {
+            let mut set = AsciiSet { table: [0; 4] };
+            for b in iter {
+                set.insert(b);
+            }
+            set
+        }
+    }
+
+    pub struct AsciiSetSearcher<'a> {
+        haystack: (*mut u8, *mut u8),
+        start: *mut u8,
+        end: *mut u8,
+        set: AsciiSet,
+        _marker: ::core::marker::PhantomData<&'a mut [u8]>
+    }
+
+    impl_searcher_debug! { &'a mut [u8] => ['a] AsciiSetSearcher<'a> {} }
+
+    unsafe impl<'a> Searcher<&'a mut [u8]> for AsciiSetSearcher<'a> {
+        fn haystack(&self) -> (*mut u8, *mut u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*mut u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                self.start = p.offset(1);
+                if self.set.contains(*p) {
+                    SearchStep::Match(p, self.start)
+                } else {
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if self.set.contains(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if !self.set.contains(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a mut [u8]> for AsciiSetSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a mut [u8]> for AsciiSetSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if self.set.contains(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if !self.set.contains(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a mut [u8]> for AsciiSetSearcher<'a> {}
+
+    impl<'a> Pattern<&'a mut [u8]> for AsciiSet {
+        type Searcher = AsciiSetSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            let (begin, end) = bounds(haystack);
+
+            AsciiSetSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                set: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack
+                .first()
+                .map(|&b| self.contains(b))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack
+                .last()
+                .map(|&b| self.contains(b))
+                .unwrap_or(false)
+        }
+    }
+
+    // Matches either of two bytes, see `AsciiFewSearcher`.
+    #[derive(Clone, Copy)]
+    pub struct Ascii2(pub u8, pub u8);
+
+    // Matches any of three bytes, see `AsciiFewSearcher`.
+    #[derive(Clone, Copy)]
+    pub struct Ascii3(pub u8, pub u8, pub u8);
+
+    // Searcher for two or three bytes, which scans a word at a time
+    // instead of looking up every byte in a table like `AsciiSet` does.
+    pub struct AsciiFewSearcher<'a> {
+        haystack: (*mut u8, *mut u8),
+        start: *mut u8,
+        end: *mut u8,
+        // For two needles the last one is a repeat of the second
+        needles: [u8; 3],
+        three: bool,
+        _marker: ::core::marker::PhantomData<&'a mut [u8]>
+    }
+
+    impl_searcher_debug! { &'a mut [u8] => ['a] AsciiFewSearcher<'a> { needles, three } }
+
+    impl<'a> AsciiFewSearcher<'a> {
+        fn new(haystack: &'a mut [u8], needles: [u8; 3], three: bool) -> Self {
+            let (begin, end) = bounds(haystack);
+            AsciiFewSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                needles,
+                three,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a mut [u8]> for AsciiFewSearcher<'a> {
+        fn haystack(&self) -> (*mut u8, *mut u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*mut u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                self.start = p.offset(1);
+                if self.needles.contains(&*p) {
+                    SearchStep::Match(p, self.start)
+                } else {
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
+            let [n1, n2, n3] = self.needles;
+            unsafe {
+                let rest = ::core::slice::from_raw_parts(self.start,
+                    self.end as usize - self.start as usize);
+
+                let found = if self.three {
+                    memchr::memchr3(n1, n2, n3, rest)
+                } else {
+                    memchr::memchr2(n1, n2, rest)
+                };
+
+                match found {
+                    Some(i) => {
+                        let p = self.start.add(i);
+                        self.start = p.offset(1);
+                        Some((p, self.start))
+                    }
+                    None => {
+                        self.start = self.end;
+                        None
+                    }
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if !self.needles.contains(&*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a mut [u8]> for AsciiFewSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a mut [u8]> for AsciiFewSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if self.needles.contains(&*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if !self.needles.contains(&*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a mut [u8]> for AsciiFewSearcher<'a> {}
+
+    impl<'a> Pattern<&'a mut [u8]> for Ascii2 {
+        type Searcher = AsciiFewSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            AsciiFewSearcher::new(haystack, [self.0, self.1, self.1], false)
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack
+                .first()
+                .map(|&b| b == self.0 || b == self.1)
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack
+                .last()
+                .map(|&b| b == self.0 || b == self.1)
+                .unwrap_or(false)
+        }
+    }
+
+    impl<'a> Pattern<&'a mut [u8]> for Ascii3 {
+        type Searcher = AsciiFewSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            AsciiFewSearcher::new(haystack, [self.0, self.1, self.2], true)
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack
+                .first()
+                .map(|&b| b == self.0 || b == self.1 || b == self.2)
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack
+                .last()
+                .map(|&b| b == self.0 || b == self.1 || b == self.2)
+                .unwrap_or(false)
+        }
+    }
+
+    // Substring searcher, generic over how the needle is stored so that
+    // needles like `EncodedChar` can be owned by it.
+    pub struct BytesSearcher<'a, N> {
+        haystack: (*mut u8, *mut u8),
+        start: *mut u8,
+        end: *mut u8,
+        needle: N,
+        // Continue searching one byte behind the begin of a match
+        // instead of behind its end
+        overlapping: bool,
+        // Only set for long needles
+        #[cfg(feature = "alloc")]
+        skip_table: Option<::alloc::boxed::Box<horspool::SkipTable>>,
+        // Only set for the empty needle
+        empty_needle: Option<EmptyNeedle>,
+        _marker: ::core::marker::PhantomData<&'a mut [u8]>
+    }
+
+    impl<'a, N> ::core::fmt::Debug for BytesSearcher<'a, N>
+        where N: ::core::ops::Deref<Target = [u8]>
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let remaining = unsafe {
+                debug_range::<&mut [u8]>(self.haystack, self.start, self.end)
+            };
+            f.debug_struct("BytesSearcher")
+                .field("remaining", &remaining)
+                .field("needle", &&self.needle[..])
+                .field("overlapping", &self.overlapping)
+                .finish()
+        }
+    }
+
+    impl<'a, N> BytesSearcher<'a, N> where N: ::core::ops::Deref<Target = [u8]> {
+        fn new(haystack: &'a mut [u8], needle: N) -> Self {
+            let (begin, end) = bounds(haystack);
+            let empty_needle = if needle.is_empty() {
+                Some(EmptyNeedle::new())
+            } else {
+                None
+            };
+            #[cfg(feature = "alloc")]
+            let skip_table = if needle.len() >= horspool::MIN_NEEDLE_LEN {
+                Some(horspool::SkipTable::new(&needle))
+            } else {
+                None
+            };
+            BytesSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                needle,
+                overlapping: false,
+                #[cfg(feature = "alloc")]
+                skip_table,
+                empty_needle,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        // Finds the begin of the next occurence of the (non-empty) needle
+        // in `[start, end)` without advancing the searcher.
+        fn find(&self) -> Option<*mut u8> {
+            let needle = &*self.needle;
+            unsafe {
+                let rest = ::core::slice::from_raw_parts(self.start,
+                    self.end as usize - self.start as usize);
+
+                #[cfg(feature = "alloc")]
+                let found = match self.skip_table {
+                    Some(ref table) => table.find(needle, rest),
+                    None => rest.windows(needle.len()).position(|w| w == needle),
+                };
+                #[cfg(not(feature = "alloc"))]
+                let found = rest.windows(needle.len()).position(|w| w == needle);
+                found.map(|i| self.start.add(i))
+            }
+        }
+    }
+
+    unsafe impl<'a, N> Searcher<&'a mut [u8]> for BytesSearcher<'a, N>
+        where N: ::core::ops::Deref<Target = [u8]>,
+    {
+        fn haystack(&self) -> (*mut u8, *mut u8) {
+            self.haystack
+        }
+
+        // Like for `StrSearcher`, the skip table only depends on the
+        // needle, and the empty needle is resumed behind a match at
+        // `start` unless the searcher is done.
+        fn position(&self) -> *mut u8 {
+            match self.empty_needle {
+                Some(ref empty) if !empty.is_match_fw && self.start != self.end => unsafe {
+                    self.start.add(1)
+                },
+                _ => self.start,
+            }
+        }
+
+        fn next(&mut self) -> SearchStep<*mut u8> {
+            assert!(!self.overlapping,
+                    "overlapping matches have no rejects in between");
+
+            if let Some(ref mut empty) = self.empty_needle {
+                return empty.next(&mut self.start, self.end, |p| unsafe {
+                    p.offset(1)
+                });
+            }
+
+            let p = self.start;
+            match self.find() {
+                Some(m) if m == p => unsafe {
+                    self.start = p.add(self.needle.len());
+                    SearchStep::Match(p, self.start)
+                },
+                Some(m) => {
+                    self.start = m;
+                    SearchStep::Reject(p, m)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                return empty.next_match(&mut self.start, self.end, |p| unsafe {
+                    p.offset(1)
+                });
+            }
+
+            match self.find() {
+                Some(p) => unsafe {
+                    let match_end = p.add(self.needle.len());
+                    self.start = if self.overlapping {
+                        p.offset(1)
+                    } else {
+                        match_end
+                    };
+                    Some((p, match_end))
+                },
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
+            assert!(!self.overlapping,
+                    "overlapping matches have no rejects in between");
+
+            if let Some(ref mut empty) = self.empty_needle {
+                return empty.next_reject(&mut self.start, self.end, |p| unsafe {
+                    p.offset(1)
+                });
+            }
+
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some(m) if m == p => unsafe {
+                        self.start = p.add(self.needle.len());
+                    },
+                    Some(m) => {
+                        self.start = m;
+                        return Some((p, m));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some((p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, N> FusedSearcher<&'a mut [u8]> for BytesSearcher<'a, N>
+        where N: ::core::ops::Deref<Target = [u8]>,
+    {}
+
+    impl<'a, 'b> Pattern<&'a mut [u8]> for &'b [u8] {
+        type Searcher = BytesSearcher<'a, &'b [u8]>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            BytesSearcher::new(haystack, self)
+        }
+
+        unsafe fn into_searcher_at(self, haystack: &'a mut [u8], cursor: *mut u8)
+            -> Self::Searcher
+        {
+            let mut searcher = BytesSearcher::new(haystack, self);
+            searcher.start = resume_cursor::<&mut [u8]>(searcher.haystack, cursor);
+            searcher
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack.starts_with(self)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack.ends_with(self)
+        }
+    }
+
+    impl<'a, 'b> Pattern<&'a mut [u8]> for generic::Overlapping<&'b [u8]> {
+        type Searcher = BytesSearcher<'a, &'b [u8]>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            BytesSearcher {
+                overlapping: true,
+                ..BytesSearcher::new(haystack, self.0)
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack.starts_with(self.0)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack.ends_with(self.0)
+        }
+    }
+
+    // The UTF-8 encoding of a char, used as the needle of the `char` pattern.
+    #[derive(Clone, Copy)]
+    pub struct EncodedChar {
+        buf: [u8; 4],
+        len: usize,
+    }
+
+    impl EncodedChar {
+        pub fn new(c: char) -> Self {
+            let mut buf = [0; 4];
+            let len = c.encode_utf8(&mut buf).len();
+            EncodedChar { buf, len }
+        }
+    }
+
+    impl ::core::ops::Deref for EncodedChar {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            &self.buf[..self.len]
+        }
+    }
+
+    impl AsRef<[u8]> for EncodedChar {
+        fn as_ref(&self) -> &[u8] {
+            self
+        }
+    }
+
+    // Matches the UTF-8 encoding of the char.
+    //
+    // The haystack is not required to be valid UTF-8, so a match is only
+    // an occurrence of the encoded bytes and might not start at a char
+    // boundary of the surrounding data, eg. when the haystack contains
+    // truncated sequences.
+    impl<'a> Pattern<&'a mut [u8]> for char {
+        type Searcher = BytesSearcher<'a, EncodedChar>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            BytesSearcher::new(haystack, EncodedChar::new(self))
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack.starts_with(&EncodedChar::new(self))
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack.ends_with(&EncodedChar::new(self))
+        }
+    }
+
+    // Matches maximal runs of ASCII whitespace as single ranges, so that
+    // splitting behaves like `split_ascii_whitespace` apart from the empty
+    // fields at the ends. Rejects are maximal runs of other bytes.
+    //
+    // Unlike `u8::is_ascii_whitespace` this includes the vertical tab.
+    #[derive(Clone, Copy)]
+    pub struct AsciiWhitespace;
+
+    fn is_ascii_whitespace(b: u8) -> bool {
+        matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'\x0b' | b'\x0c')
+    }
+
+    pub struct AsciiWhitespaceSearcher<'a> {
+        haystack: (*mut u8, *mut u8),
+        start: *mut u8,
+        end: *mut u8,
+        _marker: ::core::marker::PhantomData<&'a mut [u8]>
+    }
+
+    impl_searcher_debug! { &'a mut [u8] => ['a] AsciiWhitespaceSearcher<'a> {} }
+
+    impl<'a> AsciiWhitespaceSearcher<'a> {
+        // Advances `start` over bytes whose whitespace-ness equals `ws`
+        fn skip_fw(&mut self, ws: bool) {
+            while self.start != self.end {
+                if is_ascii_whitespace(unsafe { *self.start }) != ws {
+                    break;
+                }
+                self.start = unsafe { self.start.offset(1) };
+            }
+        }
+
+        // Moves `end` back over bytes whose whitespace-ness equals `ws`
+        fn skip_bw(&mut self, ws: bool) {
+            while self.start != self.end {
+                if is_ascii_whitespace(unsafe { *self.end.offset(-1) }) != ws {
+                    break;
+                }
+                self.end = unsafe { self.end.offset(-1) };
+            }
+        }
+
+        fn next_run(&mut self, ws: bool) -> Option<(*mut u8, *mut u8)> {
+            self.skip_fw(!ws);
+            if self.start == self.end {
+                return None;
+            }
+            let p = self.start;
+            self.skip_fw(ws);
+            Some((p, self.start))
+        }
+
+        fn next_run_back(&mut self, ws: bool) -> Option<(*mut u8, *mut u8)> {
+            self.skip_bw(!ws);
+            if self.start == self.end {
+                return None;
+            }
+            let p = self.end;
+            self.skip_bw(ws);
+            Some((self.end, p))
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a mut [u8]> for AsciiWhitespaceSearcher<'a> {
+        fn haystack(&self) -> (*mut u8, *mut u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*mut u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let ws = is_ascii_whitespace(unsafe { *self.start });
+            let p = self.start;
+            self.skip_fw(ws);
+            if ws {
+                SearchStep::Match(p, self.start)
+            } else {
+                SearchStep::Reject(p, self.start)
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.next_run(true)
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.next_run(false)
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a mut [u8]> for AsciiWhitespaceSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a mut [u8]> for AsciiWhitespaceSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.next_run_back(true)
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            self.next_run_back(false)
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a mut [u8]> for AsciiWhitespaceSearcher<'a> {}
+
+    impl<'a> Pattern<&'a mut [u8]> for AsciiWhitespace {
+        type Searcher = AsciiWhitespaceSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            let (begin, end) = bounds(haystack);
+            AsciiWhitespaceSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [u8]) -> bool {
+            haystack.first()
+                .map(|&b| is_ascii_whitespace(b))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack.last()
+                .map(|&b| is_ascii_whitespace(b))
+                .unwrap_or(false)
+        }
+    }
+
+    pub struct BytePredicateSearcher<'a, F> {
+        haystack: (*mut u8, *mut u8),
+        start: *mut u8,
+        end: *mut u8,
+        predicate: F,
+        _marker: ::core::marker::PhantomData<&'a mut [u8]>
+    }
+
+    impl_searcher_debug! { &'a mut [u8] => ['a, F] BytePredicateSearcher<'a, F> {} }
+
+    unsafe impl<'a, F> Searcher<&'a mut [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool
+    {
+        fn haystack(&self) -> (*mut u8, *mut u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*mut u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                self.start = p.offset(1);
+                if (self.predicate)(*p) {
+                    SearchStep::Match(p, self.start)
+                } else {
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if (self.predicate)(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if !(self.predicate)(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a, F> FusedSearcher<&'a mut [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool
+    {}
+
+    unsafe impl<'a, F> ReverseSearcher<&'a mut [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool
+    {
+        fn next_match_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if (self.predicate)(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut u8, *mut u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if !(self.predicate)(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, F> DoubleEndedSearcher<&'a mut [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool {}
+
+    impl<'a, F> Pattern<&'a mut [u8]> for F where F: FnMut(u8) -> bool {
+        type Searcher = BytePredicateSearcher<'a, F>;
+
+        fn into_searcher(self, haystack: &'a mut [u8]) -> Self::Searcher {
+            let (begin, end) = bounds(haystack);
+            BytePredicateSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                predicate: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(mut self, haystack: &'a mut [u8]) -> bool {
+            haystack.first()
+                .map(|&b| self(b))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(mut self, haystack: &'a mut [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [u8]> {
+            haystack.last()
+                .map(|&b| self(b))
+                .unwrap_or(false)
+        }
+    }
+
+    impl_pattern_for_ref! {
+        [] Ascii,
+        [] AsciiNoCase,
+        [] AsciiSet,
+        [] Ascii2,
+        [] Ascii3,
+        [] AsciiWhitespace,
+    }
+}
+
+// Shared byte slices, for haystacks that are only read.
+// The patterns are the ones from `slice`.
+pub mod shared_slice {
+    use super::*;
+    use super::slice::Ascii;
+    use core::ops::ControlFlow;
+
+    // `SearchPtrs for &[u8]` is the `T = u8` case of the impl
+    // in `generic_slice`.
+
+    #[derive(Clone)]
+    pub struct AsciiSearcher<'a> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        ascii: u8,
+        _marker: ::core::marker::PhantomData<&'a [u8]>
+    }
+
+    impl_searcher_debug! { &'a [u8] => ['a] AsciiSearcher<'a> { ascii as char } }
+
+    impl<'a> AsciiSearcher<'a> {
+        fn rest(&self) -> &'a [u8] {
+            unsafe {
+                ::core::slice::from_raw_parts(self.start,
+                    self.end as usize - self.start as usize)
+            }
+        }
+    }
+
+    unsafe impl<'a> Searcher<&'a [u8]> for AsciiSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                self.start = p.offset(1);
+                if *p == self.ascii {
+                    SearchStep::Match(p, self.start)
+                } else {
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            match memchr::memchr(self.ascii, self.rest()) {
+                Some(i) => unsafe {
+                    let p = self.start.add(i);
+                    self.start = p.offset(1);
+                    Some((p, self.start))
+                },
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn try_fold_matches<B, F>(&mut self, init: B, mut f: F) -> ControlFlow<B, B>
+            where F: FnMut(B, (*const u8, *const u8)) -> ControlFlow<B, B>
+        {
+            let mut acc = init;
+            while let Some(i) = memchr::memchr(self.ascii, self.rest()) {
+                unsafe {
+                    let p = self.start.add(i);
+                    self.start = p.offset(1);
+                    acc = match f(acc, (p, self.start)) {
+                        ControlFlow::Continue(acc) => acc,
+                        brk => return brk,
+                    };
+                }
+            }
+            self.start = self.end;
+            ControlFlow::Continue(acc)
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if *p != self.ascii {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a [u8]> for AsciiSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a [u8]> for AsciiSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            match self.rest().iter().rposition(|&b| b == self.ascii) {
+                Some(i) => unsafe {
+                    let p = self.start.add(i);
+                    self.end = p;
+                    Some((p, p.offset(1)))
+                },
+                None => {
+                    self.end = self.start;
+                    None
+                }
+            }
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if *self.end != self.ascii {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a [u8]> for AsciiSearcher<'a> {}
+
+    impl<'a> Pattern<&'a [u8]> for Ascii {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a [u8]) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            AsciiSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                ascii: self.0,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a [u8]) -> bool {
+            haystack.first() == Some(&self.0)
+        }
+
+        fn is_suffix_of(self, haystack: &'a [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [u8]> {
+            haystack.last() == Some(&self.0)
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct BytePredicateSearcher<'a, F> {
+        haystack: (*const u8, *const u8),
+        start: *const u8,
+        end: *const u8,
+        predicate: F,
+        _marker: ::core::marker::PhantomData<&'a [u8]>
+    }
+
+    impl_searcher_debug! { &'a [u8] => ['a, F] BytePredicateSearcher<'a, F> {} }
+
+    unsafe impl<'a, F> Searcher<&'a [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool
+    {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            let p = self.start;
+            unsafe {
+                self.start = p.offset(1);
+                if (self.predicate)(*p) {
+                    SearchStep::Match(p, self.start)
+                } else {
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if (self.predicate)(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if !(self.predicate)(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a, F> FusedSearcher<&'a [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool
+    {}
+
+    unsafe impl<'a, F> ReverseSearcher<&'a [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool
+    {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if (self.predicate)(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if !(self.predicate)(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, F> DoubleEndedSearcher<&'a [u8]> for BytePredicateSearcher<'a, F>
+        where F: FnMut(u8) -> bool {}
+
+    impl<'a, F> Pattern<&'a [u8]> for F where F: FnMut(u8) -> bool {
+        type Searcher = BytePredicateSearcher<'a, F>;
+
+        fn into_searcher(self, haystack: &'a [u8]) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            BytePredicateSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                predicate: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(mut self, haystack: &'a [u8]) -> bool {
+            haystack.first()
+                .map(|&b| self(b))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(mut self, haystack: &'a [u8]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [u8]> {
+            haystack.last()
+                .map(|&b| self(b))
+                .unwrap_or(false)
+        }
+    }
+}
+
+// `OsStr` haystacks, searched in their platform encoding as returned by
+// `OsStr::as_encoded_bytes`: the raw bytes on Unix and WTF-8 on Windows.
+// Both are ASCII compatible, and ASCII bytes never occur inside
+// a multi-byte sequence, so ranges between ASCII matches are valid
+// `OsStr` slices on every platform.
+#[cfg(feature = "std")]
+pub mod os_string {
+    use super::*;
+    use std::ffi::OsStr;
+
+    // `cursor_at_offset` leaves encoding boundaries to the caller.
+    impl SearchPtrs for &OsStr {
+        type Haystack = (*const u8, *const u8);
+        type Cursor = *const u8;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            begin as usize - haystack.0 as usize
+        }
+
+        unsafe fn range_to_self(hs: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            let bytes = ::std::slice::from_raw_parts(start,
+                Self::cursor_distance(hs, start, end));
+            OsStr::from_encoded_bytes_unchecked(bytes)
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            hs.0
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            hs.1
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> ::std::cmp::Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            b as usize - a as usize
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            hs.1 as usize - hs.0 as usize
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            hs.1 as usize - cursor as usize
+        }
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            hs.0.add(n)
+        }
+        unsafe fn cursor_advance(hs: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            let bytes = ::std::slice::from_raw_parts(hs.0, Self::haystack_len(hs));
+            let i = Self::offset_from_start(hs, cursor) + n;
+            assert!(is_boundary(bytes, i), "cursor is not at an encoding boundary");
+            cursor.add(n)
+        }
+        unsafe fn cursor_next(hs: Self::Haystack, cursor: Self::Cursor) -> Self::Cursor {
+            let bytes = ::std::slice::from_raw_parts(hs.0, Self::haystack_len(hs));
+            let i = Self::offset_from_start(hs, cursor);
+            let n = (i + 1..).find(|&n| is_boundary(bytes, n)).unwrap() - i;
+            cursor.add(n)
+        }
+    }
+
+    // A single ASCII byte, validated like `string::Ascii` so that matches
+    // always fall on encoding boundaries.
+    #[derive(Clone, Copy)]
+    pub struct Ascii(u8);
+
+    impl Ascii {
+        // Returns `None` if the byte is not ASCII.
+        pub const fn new(byte: u8) -> Option<Ascii> {
+            if byte.is_ascii() {
+                Some(Ascii(byte))
+            } else {
+                None
+            }
+        }
+    }
+
+    // Runs the byte searcher on the encoded bytes, whose cursors
+    // are valid for the `OsStr` as well.
+    #[derive(Clone, Debug)]
+    pub struct AsciiSearcher<'a>(shared_slice::AsciiSearcher<'a>);
+
+    unsafe impl<'a> Searcher<&'a OsStr> for AsciiSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.0.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            self.0.next()
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_match()
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_reject()
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            self.0.match_bounds()
+        }
+    }
+
+    impl<'a> FusedSearcher<&'a OsStr> for AsciiSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<&'a OsStr> for AsciiSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_match_back()
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_reject_back()
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<&'a OsStr> for AsciiSearcher<'a> {}
+
+    impl<'a> Pattern<&'a OsStr> for Ascii {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: &'a OsStr) -> Self::Searcher {
+            let bytes = haystack.as_encoded_bytes();
+            AsciiSearcher(slice::Ascii(self.0).into_searcher(bytes))
+        }
+
+        fn is_prefix_of(self, haystack: &'a OsStr) -> bool {
+            haystack.as_encoded_bytes().first() == Some(&self.0)
+        }
+
+        fn is_suffix_of(self, haystack: &'a OsStr) -> bool
+            where Self::Searcher: ReverseSearcher<&'a OsStr> {
+            haystack.as_encoded_bytes().last() == Some(&self.0)
+        }
+    }
+
+    // Whether an `OsStr` may be split at the offset. Any byte offset is
+    // fine for the raw bytes on Unix, elsewhere the encoding is UTF-8 or
+    // WTF-8, which can only be split in front of a non-continuation byte.
+    #[cfg(unix)]
+    fn is_boundary(_: &[u8], _: usize) -> bool {
+        true
+    }
+
+    #[cfg(not(unix))]
+    fn is_boundary(bytes: &[u8], i: usize) -> bool {
+        bytes.get(i).map_or(true, |&b| b & 0xC0 != 0x80)
+    }
+
+    #[derive(Clone)]
+    pub struct OsStrSearcher<'a, 'b> {
+        haystack: &'a [u8],
+        start: usize,
+        end: usize,
+        needle: &'b [u8],
+        // Not set for the empty needle
+        two_way: Option<two_way::TwoWay>,
+        // Only set for the empty needle
+        empty_needle: Option<EmptyNeedle>,
+    }
+
+    impl<'a, 'b> ::std::fmt::Debug for OsStrSearcher<'a, 'b> {
+        fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            f.debug_struct("OsStrSearcher")
+                .field("remaining", &(self.start..self.end))
+                .field("needle", &String::from_utf8_lossy(self.needle))
+                .finish()
+        }
+    }
+
+    impl<'a, 'b> OsStrSearcher<'a, 'b> {
+        fn range(&self, start: usize, end: usize) -> (*const u8, *const u8) {
+            let p = self.haystack.as_ptr();
+            unsafe {
+                (p.add(start), p.add(end))
+            }
+        }
+
+        // Finds the offset of the next occurence of the (non-empty) needle
+        // in `[start, end)` without advancing the searcher. Occurences that
+        // don't begin and end on a boundary, like a lone surrogate needle
+        // inside a WTF-8 char, are skipped.
+        fn find(&mut self) -> Option<usize> {
+            let mut from = self.start;
+            while let Some(i) = self.two_way.as_mut().unwrap()
+                .find(self.needle, &self.haystack[from..self.end])
+            {
+                let begin = from + i;
+                if is_boundary(self.haystack, begin) &&
+                    is_boundary(self.haystack, begin + self.needle.len()) {
+                    return Some(begin);
+                }
+                from = begin + 1;
+            }
+            None
+        }
+    }
+
+    // Steps over the code point starting at the offset, or a single
+    // byte on Unix.
+    fn next_boundary(bytes: &[u8], offset: usize) -> usize {
+        let mut next = offset + 1;
+        while !is_boundary(bytes, next) {
+            next += 1;
+        }
+        next
+    }
+
+    unsafe impl<'a, 'b> Searcher<&'a OsStr> for OsStrSearcher<'a, 'b> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.range(0, self.haystack.len())
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            let bytes = self.haystack;
+            if let Some(ref mut empty) = self.empty_needle {
+                let step = empty.next(&mut self.start, self.end, |p| {
+                    next_boundary(bytes, p)
+                });
+                return step.map(|offset| unsafe { bytes.as_ptr().add(offset) });
+            }
+
+            let p = self.start;
+            match self.find() {
+                Some(m) if m == p => {
+                    self.start = p + self.needle.len();
+                    let (begin, end) = self.range(p, self.start);
+                    SearchStep::Match(begin, end)
+                }
+                Some(m) => {
+                    self.start = m;
+                    let (begin, end) = self.range(p, m);
+                    SearchStep::Reject(begin, end)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    let (begin, end) = self.range(p, self.end);
+                    SearchStep::Reject(begin, end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let bytes = self.haystack;
+                let range = empty.next_match(&mut self.start, self.end, |p| {
+                    next_boundary(bytes, p)
+                });
+                return range.map(|(begin, end)| self.range(begin, end));
+            }
+
+            match self.find() {
+                Some(begin) => {
+                    self.start = begin + self.needle.len();
+                    Some(self.range(begin, self.start))
+                }
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let bytes = self.haystack;
+                let range = empty.next_reject(&mut self.start, self.end, |p| {
+                    next_boundary(bytes, p)
+                });
+                return range.map(|(begin, end)| self.range(begin, end));
+            }
+
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some(m) if m == p => {
+                        self.start = p + self.needle.len();
+                    }
+                    Some(m) => {
+                        self.start = m;
+                        return Some(self.range(p, m));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some(self.range(p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, 'b> FusedSearcher<&'a OsStr> for OsStrSearcher<'a, 'b> {}
+
+    // Searches the encoded bytes of the haystack for those of the needle,
+    // yielding only matches that can be split off as an `OsStr`.
+    impl<'a, 'b> Pattern<&'a OsStr> for &'b OsStr {
+        type Searcher = OsStrSearcher<'a, 'b>;
+
+        fn into_searcher(self, haystack: &'a OsStr) -> Self::Searcher {
+            let haystack = haystack.as_encoded_bytes();
+            let needle = self.as_encoded_bytes();
+            let (two_way, empty_needle) = if needle.is_empty() {
+                (None, Some(EmptyNeedle::new()))
+            } else {
+                (Some(two_way::TwoWay::new(needle)), None)
+            };
+            OsStrSearcher {
+                haystack,
+                start: 0,
+                end: haystack.len(),
+                needle,
+                two_way,
+                empty_needle,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a OsStr) -> bool {
+            let haystack = haystack.as_encoded_bytes();
+            let needle = self.as_encoded_bytes();
+            haystack.starts_with(needle) && is_boundary(haystack, needle.len())
+        }
+
+        fn is_suffix_of(self, haystack: &'a OsStr) -> bool
+            where Self::Searcher: ReverseSearcher<&'a OsStr> {
+            let haystack = haystack.as_encoded_bytes();
+            let needle = self.as_encoded_bytes();
+            haystack.ends_with(needle) &&
+                is_boundary(haystack, haystack.len() - needle.len())
+        }
+    }
+
+    impl_pattern_for_ref! {
+        [] Ascii,
+    }
+}
+
+// `CStr` haystacks. A `&CStr` has to end at its NUL terminator, so only
+// suffixes of it could be returned as pieces. Instead, the haystack is
+// a `CStrSlice`: a slice of the bytes of a `CStr` without the terminator,
+// which therefore contains no NUL byte.
+pub mod c_string {
+    use super::*;
+    use core::ffi::CStr;
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct CStrSlice<'a>(&'a [u8]);
+
+    impl<'a> CStrSlice<'a> {
+        pub fn new(s: &'a CStr) -> Self {
+            CStrSlice(s.to_bytes())
+        }
+
+        pub fn as_bytes(&self) -> &'a [u8] {
+            self.0
+        }
+    }
+
+    impl<'a> From<&'a CStr> for CStrSlice<'a> {
+        fn from(s: &'a CStr) -> Self {
+            CStrSlice::new(s)
+        }
+    }
+
+    impl<'a> SearchPtrs for CStrSlice<'a> {
+        type Haystack = (*const u8, *const u8);
+        type Cursor = *const u8;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            begin as usize - haystack.0 as usize
+        }
+
+        unsafe fn range_to_self(hs: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            CStrSlice(::core::slice::from_raw_parts(start,
+                Self::cursor_distance(hs, start, end)))
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            hs.0
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            hs.1
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> ::core::cmp::Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            b as usize - a as usize
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            hs.1 as usize - hs.0 as usize
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            hs.1 as usize - cursor as usize
+        }
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            hs.0.add(n)
+        }
+        unsafe fn cursor_advance(_: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            cursor.add(n)
+        }
+    }
+
+    // Runs the byte searcher on the bytes of the slice, whose cursors
+    // are valid for the `CStrSlice` as well.
+    #[derive(Clone, Debug)]
+    pub struct AsciiSearcher<'a>(shared_slice::AsciiSearcher<'a>);
+
+    unsafe impl<'a> Searcher<CStrSlice<'a>> for AsciiSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.0.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            self.0.next()
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_match()
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_reject()
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            self.0.match_bounds()
+        }
+    }
+
+    impl<'a> FusedSearcher<CStrSlice<'a>> for AsciiSearcher<'a> {}
+
+    unsafe impl<'a> ReverseSearcher<CStrSlice<'a>> for AsciiSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_match_back()
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.0.next_reject_back()
+        }
+    }
+
+    impl<'a> DoubleEndedSearcher<CStrSlice<'a>> for AsciiSearcher<'a> {}
+
+    // Despite the name any byte can be searched for, as C strings have
+    // no particular encoding. A NUL byte never matches.
+    impl<'a> Pattern<CStrSlice<'a>> for slice::Ascii {
+        type Searcher = AsciiSearcher<'a>;
+
+        fn into_searcher(self, haystack: CStrSlice<'a>) -> Self::Searcher {
+            AsciiSearcher(self.into_searcher(haystack.0))
+        }
+
+        fn is_prefix_of(self, haystack: CStrSlice<'a>) -> bool {
+            haystack.0.first() == Some(&self.0)
+        }
+
+        fn is_suffix_of(self, haystack: CStrSlice<'a>) -> bool
+            where Self::Searcher: ReverseSearcher<CStrSlice<'a>> {
+            haystack.0.last() == Some(&self.0)
+        }
+    }
+}
+
+// Two string segments searched as if they were concatenated, like the
+// two halves of a ring buffer. Matches may straddle the seam between them.
+pub mod chain {
+    use super::*;
+    use core::cmp::{self, Ordering};
+    #[cfg(feature = "alloc")]
+    use alloc::string::String;
+
+    // The haystack, and also the type of its pieces: a range crossing the
+    // seam can't be borrowed as a single `&str`, so a piece holds up to
+    // two parts. Pieces that lie in one segment leave the second part empty.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Chain<'a>(pub &'a str, pub &'a str);
+
+    impl<'a> Chain<'a> {
+        pub fn len(&self) -> usize {
+            self.0.len() + self.1.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        #[cfg(feature = "alloc")]
+        pub fn concat(&self) -> String {
+            [self.0, self.1].concat()
+        }
+
+        fn byte(&self, offset: usize) -> u8 {
+            match offset.checked_sub(self.0.len()) {
+                None => self.0.as_bytes()[offset],
+                Some(i) => self.1.as_bytes()[i],
+            }
+        }
+
+        fn starts_with(&self, needle: &[u8]) -> bool {
+            needle.len() <= self.len() &&
+                needle.iter().enumerate().all(|(i, &b)| self.byte(i) == b)
+        }
+
+        fn ends_with(&self, needle: &[u8]) -> bool {
+            let start = match self.len().checked_sub(needle.len()) {
+                Some(start) => start,
+                None => return false,
+            };
+            needle.iter().enumerate().all(|(i, &b)| self.byte(start + i) == b)
+        }
+
+        fn cursor(&self, offset: usize) -> ChainCursor {
+            match offset.checked_sub(self.0.len()) {
+                Some(i) => ChainCursor { segment: 1, ptr: self.1[i..].as_ptr() },
+                None => ChainCursor { segment: 0, ptr: self.0[offset..].as_ptr() },
+            }
+        }
+
+        // Cursors of a sub-haystack from `slice` may name a different
+        // segment, so they're only valid here after mapping their offsets
+        fn offset(&self, cursor: ChainCursor) -> usize {
+            let (before, segment) = match cursor.segment {
+                0 => (0, self.0),
+                _ => (self.0.len(), self.1),
+            };
+            let i = (cursor.ptr as usize).wrapping_sub(segment.as_ptr() as usize);
+            assert!(i <= segment.len(), "cursor doesn't belong to this chain");
+            before + i
+        }
+
+        fn slice(&self, start: usize, end: usize) -> Chain<'a> {
+            let seam = self.0.len();
+            if end <= seam {
+                Chain(&self.0[start..end], "")
+            } else if start >= seam {
+                Chain(&self.1[start - seam..end - seam], "")
+            } else {
+                Chain(&self.0[start..], &self.1[..end - seam])
+            }
+        }
+    }
+
+    // A segment index and a pointer into that segment. A position at the
+    // seam always refers to the front of the second segment,
+    // so every offset has exactly one cursor.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct ChainCursor {
+        segment: usize,
+        ptr: *const u8,
+    }
+
+    impl<'a> SearchPtrs for Chain<'a> {
+        type Haystack = Chain<'a>;
+        type Cursor = ChainCursor;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            haystack.offset(begin)
+        }
+
+        unsafe fn range_to_self(haystack: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            haystack.slice(haystack.offset(start), haystack.offset(end))
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            hs.cursor(0)
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            hs.cursor(hs.len())
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> Ordering {
+            (a.segment, a.ptr).cmp(&(b.segment, b.ptr))
+        }
+        // `cursor` slices the segments, which checks for char boundaries
+        unsafe fn cursor_distance(hs: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            hs.offset(b) - hs.offset(a)
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            hs.len()
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            hs.len() - hs.offset(cursor)
+        }
+        // Checks for char boundaries all the same, see `cursor_distance`
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            hs.cursor(n)
+        }
+        unsafe fn cursor_advance(hs: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            hs.cursor(hs.offset(cursor) + n)
+        }
+        unsafe fn cursor_next(hs: Self::Haystack, cursor: Self::Cursor) -> Self::Cursor {
+            hs.cursor(next_char(hs, hs.len(), hs.offset(cursor)))
+        }
+    }
+
+    // Searches for a byte sequence across both segments. Needles that are
+    // valid UTF-8 only ever match on char boundaries, as each segment
+    // is valid UTF-8 on its own.
+    //
+    // The search state is kept as offsets into the chain,
+    // which are converted to cursors when yielding a range.
+    #[derive(Clone)]
+    pub struct ChainSearcher<'a, N> {
+        haystack: Chain<'a>,
+        start: usize,
+        end: usize,
+        needle: N,
+        // Only set for needles longer than one byte
+        two_way: Option<two_way::TwoWay>,
+        // Only set for the empty needle
+        empty_needle: Option<EmptyNeedle>,
+    }
+
+    impl<'a, N: AsRef<[u8]>> ::core::fmt::Debug for ChainSearcher<'a, N> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_struct("ChainSearcher")
+                .field("remaining", &(self.start..self.end))
+                .field("needle", &DebugLossy(self.needle.as_ref()))
+                .finish()
+        }
+    }
+
+    fn find_in(two_way: &mut Option<two_way::TwoWay>,
+               needle: &[u8],
+               haystack: &[u8]) -> Option<usize> {
+        match *two_way {
+            Some(ref mut two_way) => two_way.find(needle, haystack),
+            None => memchr::memchr(needle[0], haystack),
+        }
+    }
+
+    impl<'a, N> ChainSearcher<'a, N> where N: AsRef<[u8]> {
+        fn new(haystack: Chain<'a>, needle: N) -> Self {
+            let len = needle.as_ref().len();
+            let two_way = if len > 1 {
+                Some(two_way::TwoWay::new(needle.as_ref()))
+            } else {
+                None
+            };
+            let empty_needle = if len == 0 {
+                Some(EmptyNeedle::new())
+            } else {
+                None
+            };
+            ChainSearcher {
+                haystack,
+                start: 0,
+                end: haystack.len(),
+                needle,
+                two_way,
+                empty_needle,
+            }
+        }
+
+        fn range(&self, start: usize, end: usize) -> (ChainCursor, ChainCursor) {
+            (self.haystack.cursor(start), self.haystack.cursor(end))
+        }
+
+        // Finds the offset of the next occurence of the (non-empty) needle
+        // in `[start, end)` without advancing the searcher.
+        fn find(&mut self) -> Option<usize> {
+            let needle = self.needle.as_ref();
+            let a = self.haystack.0.as_bytes();
+            let b = self.haystack.1.as_bytes();
+            let seam = a.len();
+
+            if self.start < seam {
+                // Entirely inside the first segment
+                let stop = cmp::min(self.end, seam);
+                if let Some(i) = find_in(&mut self.two_way, needle,
+                                         &a[self.start..stop]) {
+                    return Some(self.start + i);
+                }
+                if self.end <= seam {
+                    return None;
+                }
+
+                // Straddling the seam, with `k` bytes in the first segment
+                let first = cmp::max(self.start, (seam + 1).saturating_sub(needle.len()));
+                for begin in first..seam {
+                    let k = seam - begin;
+                    if begin + needle.len() <= self.end &&
+                        a[begin..] == needle[..k] && b[..needle.len() - k] == needle[k..] {
+                        return Some(begin);
+                    }
+                }
+            }
+
+            // Entirely inside the second segment
+            let from = cmp::max(self.start, seam) - seam;
+            find_in(&mut self.two_way, needle, &b[from..self.end - seam])
+                .map(|i| seam + from + i)
+        }
+    }
+
+    // Steps over the char starting at the offset.
+    fn next_char(haystack: Chain, end: usize, offset: usize) -> usize {
+        let mut next = offset + 1;
+        while next < end && haystack.byte(next) & 0xC0 == 0x80 {
+            next += 1;
+        }
+        next
+    }
+
+    unsafe impl<'a, N> Searcher<Chain<'a>> for ChainSearcher<'a, N>
+        where N: AsRef<[u8]>,
+    {
+        fn haystack(&self) -> Chain<'a> {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<ChainCursor> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let (haystack, end) = (self.haystack, self.end);
+                let step = empty.next(&mut self.start, end, |p| {
+                    next_char(haystack, end, p)
+                });
+                let haystack = self.haystack;
+                return step.map(|offset| haystack.cursor(offset));
+            }
+
+            let p = self.start;
+            let step = match self.find() {
+                Some(m) if m == p => {
+                    self.start = p + self.needle.as_ref().len();
+                    SearchStep::Match(p, self.start)
+                }
+                Some(m) => {
+                    self.start = m;
+                    SearchStep::Reject(p, m)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            };
+            step.map(|offset| self.haystack.cursor(offset))
+        }
+
+        fn next_match(&mut self) -> Option<(ChainCursor, ChainCursor)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let (haystack, end) = (self.haystack, self.end);
+                let range = empty.next_match(&mut self.start, end, |p| {
+                    next_char(haystack, end, p)
+                });
+                return range.map(|(begin, end)| self.range(begin, end));
+            }
+
+            match self.find() {
+                Some(begin) => {
+                    self.start = begin + self.needle.as_ref().len();
+                    Some(self.range(begin, self.start))
+                }
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(ChainCursor, ChainCursor)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let (haystack, end) = (self.haystack, self.end);
+                let range = empty.next_reject(&mut self.start, end, |p| {
+                    next_char(haystack, end, p)
+                });
+                return range.map(|(begin, end)| self.range(begin, end));
+            }
+
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some(m) if m == p => {
+                        self.start = p + self.needle.as_ref().len();
+                    }
+                    Some(m) => {
+                        self.start = m;
+                        return Some(self.range(p, m));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some(self.range(p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, N> FusedSearcher<Chain<'a>> for ChainSearcher<'a, N>
+        where N: AsRef<[u8]>,
+    {}
+
+    impl<'a> Pattern<Chain<'a>> for string::Ascii {
+        type Searcher = ChainSearcher<'a, [u8; 1]>;
+
+        fn into_searcher(self, haystack: Chain<'a>) -> Self::Searcher {
+            ChainSearcher::new(haystack, [self.to_u8()])
+        }
+
+        fn is_prefix_of(self, haystack: Chain<'a>) -> bool {
+            haystack.starts_with(&[self.to_u8()])
+        }
+
+        fn is_suffix_of(self, haystack: Chain<'a>) -> bool
+            where Self::Searcher: ReverseSearcher<Chain<'a>> {
+            haystack.ends_with(&[self.to_u8()])
+        }
+    }
+
+    impl<'a, 'b> Pattern<Chain<'a>> for &'b str {
+        type Searcher = ChainSearcher<'a, &'b [u8]>;
+
+        fn into_searcher(self, haystack: Chain<'a>) -> Self::Searcher {
+            ChainSearcher::new(haystack, self.as_bytes())
+        }
+
+        fn is_prefix_of(self, haystack: Chain<'a>) -> bool {
+            haystack.starts_with(self.as_bytes())
+        }
+
+        fn is_suffix_of(self, haystack: Chain<'a>) -> bool
+            where Self::Searcher: ReverseSearcher<Chain<'a>> {
+            haystack.ends_with(self.as_bytes())
+        }
+    }
+}
+
+// Any number of string segments searched as if they were concatenated,
+// like the chunks of a rope. This generalizes `chain::Chain`.
+#[cfg(feature = "alloc")]
+pub mod segments {
+    use super::*;
+    use alloc::borrow::Cow;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::cmp::{self, Ordering};
+
+    // The haystack, and also the type of its pieces. A piece inside one
+    // segment borrows it, while a piece spanning several segments needs
+    // a new list of their parts. Only that list is allocated, the text
+    // itself is never copied.
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub enum Segments<'a> {
+        Borrowed(&'a str),
+        List(Cow<'a, [&'a str]>),
+    }
+
+    impl<'a> Segments<'a> {
+        pub fn new(segments: &'a [&'a str]) -> Self {
+            Segments::List(Cow::Borrowed(segments))
+        }
+
+        pub fn segments(&self) -> &[&'a str] {
+            match *self {
+                Segments::Borrowed(ref s) => ::core::slice::from_ref(s),
+                Segments::List(ref list) => list,
+            }
+        }
+
+        pub fn len(&self) -> usize {
+            self.segments().iter().map(|s| s.len()).sum()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.len() == 0
+        }
+
+        pub fn concat(&self) -> String {
+            self.segments().concat()
+        }
+
+        fn bytes(&self) -> impl DoubleEndedIterator<Item = u8> + '_ {
+            self.segments().iter().flat_map(|s| s.bytes())
+        }
+
+        fn starts_with(&self, needle: &[u8]) -> bool {
+            needle.len() <= self.len() &&
+                self.bytes().zip(needle).all(|(a, &b)| a == b)
+        }
+
+        fn ends_with(&self, needle: &[u8]) -> bool {
+            needle.len() <= self.len() &&
+                self.bytes().rev().zip(needle.iter().rev()).all(|(a, &b)| a == b)
+        }
+    }
+
+    // A segment index and a pointer into that segment. A position at the
+    // end of a segment always refers to the front of the next non-empty
+    // one, so every offset has exactly one cursor.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct SegmentCursor {
+        segment: usize,
+        ptr: *const u8,
+    }
+
+    fn cursor(list: &[&str], offset: usize) -> SegmentCursor {
+        let mut start = 0;
+        for (i, s) in list.iter().enumerate() {
+            if offset < start + s.len() || i + 1 == list.len() {
+                let ptr = s.as_bytes()[offset - start..].as_ptr();
+                return SegmentCursor { segment: i, ptr };
+            }
+            start += s.len();
+        }
+        // There are no segments at all
+        SegmentCursor { segment: 0, ptr: ::core::ptr::null() }
+    }
+
+    // Cursors of a sub-haystack from `range_to_self` may name a different
+    // segment, so they're only valid here after mapping their offsets
+    fn offset(list: &[&str], cursor: SegmentCursor) -> usize {
+        match list.get(cursor.segment) {
+            Some(s) => {
+                let i = (cursor.ptr as usize).wrapping_sub(s.as_ptr() as usize);
+                assert!(i <= s.len(), "cursor doesn't belong to these segments");
+                let before: usize = list[..cursor.segment].iter().map(|s| s.len()).sum();
+                before + i
+            }
+            None => 0,
+        }
+    }
+
+    impl<'a> SearchPtrs for Segments<'a> {
+        // The list of segments of the searched haystack
+        type Haystack = (*const &'a str, usize);
+        type Cursor = SegmentCursor;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            let list = ::core::slice::from_raw_parts(haystack.0, haystack.1);
+            offset(list, begin)
+        }
+
+        unsafe fn range_to_self(haystack: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            let list = ::core::slice::from_raw_parts(haystack.0, haystack.1);
+            let mut parts = vec![];
+            let segments = list.iter().enumerate().take(end.segment + 1);
+            for (i, &s) in segments.skip(start.segment) {
+                let from = if i == start.segment {
+                    start.ptr as usize - s.as_ptr() as usize
+                } else {
+                    0
+                };
+                let to = if i == end.segment {
+                    end.ptr as usize - s.as_ptr() as usize
+                } else {
+                    s.len()
+                };
+                if from < to {
+                    parts.push(&s[from..to]);
+                }
+            }
+            match parts.len() {
+                0 => Segments::Borrowed(""),
+                1 => Segments::Borrowed(parts[0]),
+                _ => Segments::List(Cow::Owned(parts)),
+            }
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            cursor(::core::slice::from_raw_parts(hs.0, hs.1), 0)
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            let list = ::core::slice::from_raw_parts(hs.0, hs.1);
+            cursor(list, list.iter().map(|s| s.len()).sum())
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> Ordering {
+            (a.segment, a.ptr).cmp(&(b.segment, b.ptr))
+        }
+        unsafe fn cursor_distance(hs: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            let list = ::core::slice::from_raw_parts(hs.0, hs.1);
+            offset(list, b) - offset(list, a)
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            let list = ::core::slice::from_raw_parts(hs.0, hs.1);
+            list.iter().map(|s| s.len()).sum()
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            Self::haystack_len(hs) - Self::offset_from_start(hs, cursor)
+        }
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            cursor(::core::slice::from_raw_parts(hs.0, hs.1), n)
+        }
+        unsafe fn cursor_advance(hs: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            let list = ::core::slice::from_raw_parts(hs.0, hs.1);
+            let c = self::cursor(list, offset(list, cursor) + n);
+            if let Some(s) = list.get(c.segment) {
+                let i = c.ptr as usize - s.as_ptr() as usize;
+                assert!(s.is_char_boundary(i), "cursor is not at a char boundary");
+            }
+            c
+        }
+        unsafe fn cursor_next(hs: Self::Haystack, cursor: Self::Cursor) -> Self::Cursor {
+            let list = ::core::slice::from_raw_parts(hs.0, hs.1);
+            let s = list[cursor.segment];
+            let i = cursor.ptr as usize - s.as_ptr() as usize;
+            let c = s[i..].chars().next().unwrap();
+            self::cursor(list, offset(list, cursor) + c.len_utf8())
+        }
+    }
+
+    // Searches for a byte sequence across all segments, like
+    // `chain::ChainSearcher`. The search state is kept as offsets
+    // into the concatenation.
+    //
+    // The searcher owns the list of segments, which `haystack` points to,
+    // so the pieces have to be created before the searcher is dropped.
+    #[derive(Clone)]
+    pub struct SegmentsSearcher<'a, N> {
+        list: Cow<'a, [&'a str]>,
+        // The offset of the begin of each segment
+        starts: Vec<usize>,
+        start: usize,
+        end: usize,
+        needle: N,
+        // Only set for needles longer than one byte
+        two_way: Option<two_way::TwoWay>,
+        // Only set for the empty needle
+        empty_needle: Option<EmptyNeedle>,
+    }
+
+    impl<'a, N: AsRef<[u8]>> ::core::fmt::Debug for SegmentsSearcher<'a, N> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_struct("SegmentsSearcher")
+                .field("remaining", &(self.start..self.end))
+                .field("needle", &DebugLossy(self.needle.as_ref()))
+                .finish()
+        }
+    }
+
+    fn find_in(two_way: &mut Option<two_way::TwoWay>,
+               needle: &[u8],
+               haystack: &[u8]) -> Option<usize> {
+        match *two_way {
+            Some(ref mut two_way) => two_way.find(needle, haystack),
+            None => memchr::memchr(needle[0], haystack),
+        }
+    }
+
+    // The last segment beginning at or before the offset, which is
+    // the one containing it unless it's the end of the haystack.
+    fn segment(starts: &[usize], offset: usize) -> usize {
+        starts.partition_point(|&s| s <= offset).saturating_sub(1)
+    }
+
+    fn byte(list: &[&str], starts: &[usize], offset: usize) -> u8 {
+        let i = segment(starts, offset);
+        list[i].as_bytes()[offset - starts[i]]
+    }
+
+    // Steps over the char starting at `offset`, or stays at `end`.
+    fn next_char(list: &[&str], starts: &[usize], end: usize, offset: usize) -> usize {
+        if offset == end {
+            return end;
+        }
+        let mut next = offset + 1;
+        while next < end && byte(list, starts, next) & 0xC0 == 0x80 {
+            next += 1;
+        }
+        next
+    }
+
+    impl<'a, N> SegmentsSearcher<'a, N> where N: AsRef<[u8]> {
+        fn new(haystack: Segments<'a>, needle: N) -> Self {
+            let list = match haystack {
+                Segments::Borrowed(s) => Cow::Owned(vec![s]),
+                Segments::List(list) => list,
+            };
+            let mut starts = Vec::with_capacity(list.len());
+            let mut len = 0;
+            for s in list.iter() {
+                starts.push(len);
+                len += s.len();
+            }
+            let two_way = if needle.as_ref().len() > 1 {
+                Some(two_way::TwoWay::new(needle.as_ref()))
+            } else {
+                None
+            };
+            let empty_needle = if needle.as_ref().is_empty() {
+                Some(EmptyNeedle::new())
+            } else {
+                None
+            };
+            SegmentsSearcher {
+                list,
+                starts,
+                start: 0,
+                end: len,
+                needle,
+                two_way,
+                empty_needle,
+            }
+        }
+
+        fn byte(&self, offset: usize) -> u8 {
+            byte(&self.list, &self.starts, offset)
+        }
+
+        fn matches_at(&self, begin: usize) -> bool {
+            let needle = self.needle.as_ref();
+            begin + needle.len() <= self.end &&
+                needle.iter().enumerate().all(|(k, &b)| self.byte(begin + k) == b)
+        }
+
+        fn range(&self, start: usize, end: usize) -> (SegmentCursor, SegmentCursor) {
+            (cursor(&self.list, start), cursor(&self.list, end))
+        }
+
+        // Finds the offset of the next occurence of the (non-empty) needle
+        // in `[start, end)` without advancing the searcher.
+        fn find(&mut self) -> Option<usize> {
+            let n = self.needle.as_ref().len();
+            for i in segment(&self.starts, self.start)..self.list.len() {
+                let s = self.list[i].as_bytes();
+                let base = self.starts[i];
+                if base >= self.end {
+                    break;
+                }
+
+                // Entirely inside the segment
+                let from = cmp::max(self.start, base) - base;
+                let stop = cmp::min(self.end, base + s.len()) - base;
+                if let Some(j) = find_in(&mut self.two_way, self.needle.as_ref(),
+                                         &s[from..stop]) {
+                    return Some(base + from + j);
+                }
+
+                // Beginning in the segment and continuing into the next ones
+                let first = cmp::max(from, (s.len() + 1).saturating_sub(n));
+                if let Some(j) = (first..s.len()).find(|&j| self.matches_at(base + j)) {
+                    return Some(base + j);
+                }
+            }
+            None
+        }
+
+    }
+
+    unsafe impl<'a, N> Searcher<Segments<'a>> for SegmentsSearcher<'a, N>
+        where N: AsRef<[u8]>,
+    {
+        fn haystack(&self) -> (*const &'a str, usize) {
+            (self.list.as_ptr(), self.list.len())
+        }
+
+        fn next(&mut self) -> SearchStep<SegmentCursor> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let (list, starts, end) = (&self.list, &self.starts, self.end);
+                let step = empty.next(&mut self.start, end, |p| {
+                    next_char(list, starts, end, p)
+                });
+                return step.map(|offset| cursor(list, offset));
+            }
+
+            let p = self.start;
+            let step = match self.find() {
+                Some(m) if m == p => {
+                    self.start = p + self.needle.as_ref().len();
+                    SearchStep::Match(p, self.start)
+                }
+                Some(m) => {
+                    self.start = m;
+                    SearchStep::Reject(p, m)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            };
+            step.map(|offset| cursor(&self.list, offset))
+        }
+
+        fn next_match(&mut self) -> Option<(SegmentCursor, SegmentCursor)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let (list, starts, end) = (&self.list, &self.starts, self.end);
+                let range = empty.next_match(&mut self.start, end, |p| {
+                    next_char(list, starts, end, p)
+                });
+                return range.map(|(begin, end)| self.range(begin, end));
+            }
+
+            match self.find() {
+                Some(begin) => {
+                    self.start = begin + self.needle.as_ref().len();
+                    Some(self.range(begin, self.start))
+                }
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(SegmentCursor, SegmentCursor)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                let (list, starts, end) = (&self.list, &self.starts, self.end);
+                let range = empty.next_reject(&mut self.start, end, |p| {
+                    next_char(list, starts, end, p)
+                });
+                return range.map(|(begin, end)| self.range(begin, end));
+            }
+
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some(m) if m == p => {
+                        self.start = p + self.needle.as_ref().len();
+                    }
+                    Some(m) => {
+                        self.start = m;
+                        return Some(self.range(p, m));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some(self.range(p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, N> FusedSearcher<Segments<'a>> for SegmentsSearcher<'a, N>
+        where N: AsRef<[u8]>,
+    {}
+
+    impl<'a> Pattern<Segments<'a>> for string::Ascii {
+        type Searcher = SegmentsSearcher<'a, [u8; 1]>;
+
+        fn into_searcher(self, haystack: Segments<'a>) -> Self::Searcher {
+            SegmentsSearcher::new(haystack, [self.to_u8()])
+        }
+
+        fn is_prefix_of(self, haystack: Segments<'a>) -> bool {
+            haystack.starts_with(&[self.to_u8()])
+        }
+
+        fn is_suffix_of(self, haystack: Segments<'a>) -> bool
+            where Self::Searcher: ReverseSearcher<Segments<'a>> {
+            haystack.ends_with(&[self.to_u8()])
+        }
+    }
+
+    impl<'a, 'b> Pattern<Segments<'a>> for &'b str {
+        type Searcher = SegmentsSearcher<'a, &'b [u8]>;
+
+        fn into_searcher(self, haystack: Segments<'a>) -> Self::Searcher {
+            SegmentsSearcher::new(haystack, self.as_bytes())
+        }
+
+        fn is_prefix_of(self, haystack: Segments<'a>) -> bool {
+            haystack.starts_with(self.as_bytes())
+        }
+
+        fn is_suffix_of(self, haystack: Segments<'a>) -> bool
+            where Self::Searcher: ReverseSearcher<Segments<'a>> {
+            haystack.ends_with(self.as_bytes())
+        }
+    }
+}
+
+// Haystacks of arbitrary elements. The cursors are element pointers,
+// except for zero-sized types, whose elements all share one address:
+// there the cursors are counters disguised as pointers, the same trick
+// `std::slice::Iter` uses, and sub-slices are rebuilt from the
+// haystack's start pointer.
+pub mod generic_slice {
+    use super::*;
+    use core::mem;
+
+    fn is_zst<T>() -> bool {
+        mem::size_of::<T>() == 0
+    }
+
+    unsafe fn cursor_add<T>(p: *const T, n: usize) -> *const T {
+        if is_zst::<T>() {
+            (p as *const u8).wrapping_add(n) as *const T
+        } else {
+            p.add(n)
+        }
+    }
+
+    fn distance<T>(start: *const T, end: *const T) -> usize {
+        (end as usize - start as usize) / mem::size_of::<T>().max(1)
+    }
+
+    fn haystack_bounds<T>(haystack: &[T]) -> (*const T, *const T) {
+        let begin = haystack.as_ptr();
+        (begin, unsafe { cursor_add(begin, haystack.len()) })
+    }
+
+    unsafe fn slice_between<'a, T>(haystack: (*const T, *const T),
+                                   start: *const T,
+                                   end: *const T) -> &'a [T] {
+        let data = if is_zst::<T>() { haystack.0 } else { start };
+        ::core::slice::from_raw_parts(data, distance(start, end))
+    }
+
+    impl<T> SearchPtrs for &[T] {
+        type Haystack = (*const T, *const T);
+        type Cursor = *const T;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            distance(haystack.0, begin)
+        }
+
+        unsafe fn range_to_self(haystack: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            slice_between(haystack, start, end)
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            hs.0
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            hs.1
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> ::core::cmp::Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            distance(a, b)
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            distance(hs.0, hs.1)
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            distance(cursor, hs.1)
+        }
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            cursor_add(hs.0, n)
+        }
+        unsafe fn cursor_advance(_: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            cursor_add(cursor, n)
+        }
+    }
+
+    // Matches a single element equal to the given one.
+    #[derive(Clone, Copy)]
+    pub struct Elem<T>(pub T);
+
+    #[derive(Clone)]
+    pub struct ElemSearcher<'a, T: 'a> {
+        haystack: (*const T, *const T),
+        start: *const T,
+        end: *const T,
+        elem: T,
+        _marker: ::core::marker::PhantomData<&'a [T]>
+    }
+
+    impl_searcher_debug! { &'a [T] => ['a, T: ::core::fmt::Debug] ElemSearcher<'a, T> { elem } }
+
+    impl<'a, T> ElemSearcher<'a, T> {
+        fn rest(&self) -> &'a [T] {
+            unsafe {
+                slice_between(self.haystack, self.start, self.end)
+            }
+        }
+    }
+
+    unsafe impl<'a, T: PartialEq> Searcher<&'a [T]> for ElemSearcher<'a, T> {
+        fn haystack(&self) -> (*const T, *const T) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const T> {
+            match self.rest().first() {
+                Some(e) => unsafe {
+                    let p = self.start;
+                    self.start = cursor_add(p, 1);
+                    if *e == self.elem {
+                        SearchStep::Match(p, self.start)
+                    } else {
+                        SearchStep::Reject(p, self.start)
+                    }
+                },
+                None => SearchStep::Done,
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const T, *const T)> {
+            match self.rest().iter().position(|e| *e == self.elem) {
+                Some(i) => unsafe {
+                    let p = cursor_add(self.start, i);
+                    self.start = cursor_add(p, 1);
+                    Some((p, self.start))
+                },
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const T, *const T)> {
+            while let Some(e) = self.rest().first() {
+                unsafe {
+                    let p = self.start;
+                    self.start = cursor_add(p, 1);
+
+                    if *e != self.elem {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(self.rest().len()))
+        }
+    }
+
+    impl<'a, T: PartialEq> FusedSearcher<&'a [T]> for ElemSearcher<'a, T> {}
+
+    unsafe impl<'a, T: PartialEq> ReverseSearcher<&'a [T]> for ElemSearcher<'a, T> {
+        fn next_match_back(&mut self) -> Option<(*const T, *const T)> {
+            match self.rest().iter().rposition(|e| *e == self.elem) {
+                Some(i) => unsafe {
+                    let p = cursor_add(self.start, i);
+                    self.end = p;
+                    Some((p, cursor_add(p, 1)))
+                },
+                None => {
+                    self.end = self.start;
+                    None
+                }
+            }
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const T, *const T)> {
+            let mut rest = self.rest();
+            while let Some((e, init)) = rest.split_last() {
+                let p = self.end;
+                self.end = unsafe { cursor_add(self.start, init.len()) };
+                rest = init;
+
+                if *e != self.elem {
+                    return Some((self.end, p));
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, T: PartialEq> DoubleEndedSearcher<&'a [T]> for ElemSearcher<'a, T> {}
+
+    impl<'a, T: PartialEq> Pattern<&'a [T]> for Elem<T> {
+        type Searcher = ElemSearcher<'a, T>;
+
+        fn into_searcher(self, haystack: &'a [T]) -> Self::Searcher {
+            let (begin, end) = haystack_bounds(haystack);
+            ElemSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                elem: self.0,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a [T]) -> bool {
+            haystack.first() == Some(&self.0)
+        }
+
+        fn is_suffix_of(self, haystack: &'a [T]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [T]> {
+            haystack.last() == Some(&self.0)
+        }
+    }
+
+    impl<T> SearchPtrs for &mut [T] {
+        type Haystack = (*mut T, *mut T);
+        type Cursor = *mut T;
+
+        unsafe fn offset_from_start(haystack: Self::Haystack,
+                                    begin: Self::Cursor) -> usize {
+            distance(haystack.0, begin)
+        }
+
+        unsafe fn range_to_self(haystack: Self::Haystack,
+                                start: Self::Cursor,
+                                end: Self::Cursor) -> Self {
+            let data = if is_zst::<T>() { haystack.0 } else { start };
+            ::core::slice::from_raw_parts_mut(data, distance(start, end))
+        }
+        unsafe fn cursor_at_front(hs: Self::Haystack) -> Self::Cursor {
+            hs.0
+        }
+        unsafe fn cursor_at_back(hs: Self::Haystack) -> Self::Cursor {
+            hs.1
+        }
+        unsafe fn cursor_cmp(_: Self::Haystack,
+                             a: Self::Cursor,
+                             b: Self::Cursor) -> ::core::cmp::Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: Self::Haystack,
+                                  a: Self::Cursor,
+                                  b: Self::Cursor) -> usize {
+            distance(a, b)
+        }
+        unsafe fn haystack_len(hs: Self::Haystack) -> usize {
+            distance(hs.0, hs.1)
+        }
+        unsafe fn offset_from_end(hs: Self::Haystack, cursor: Self::Cursor) -> usize {
+            distance(cursor, hs.1)
+        }
+        unsafe fn cursor_at_offset(hs: Self::Haystack, n: usize) -> Self::Cursor {
+            cursor_add(hs.0, n) as *mut T
+        }
+        unsafe fn cursor_advance(_: Self::Haystack,
+                                 cursor: Self::Cursor,
+                                 n: usize) -> Self::Cursor {
+            cursor_add(cursor, n) as *mut T
+        }
+    }
+
+    // Adapts a `&[T]` searcher to a `&mut [T]` haystack. The ranges
+    // it returns never overlap, so the pieces built from them
+    // are disjoint.
+    pub struct MutSliceSearcher<T, S> {
+        haystack: (*mut T, *mut T),
+        // The begin of the shared view the inner searcher runs on
+        view: *const T,
+        inner: S,
+    }
+
+    // Not `Clone`, like `MutStrSearcher`. The inner searcher runs on
+    // a view with the same offsets.
+    impl<T, S: ::core::fmt::Debug> ::core::fmt::Debug for MutSliceSearcher<T, S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_tuple("MutSliceSearcher").field(&self.inner).finish()
+        }
+    }
+
+    impl<'a, T: 'a, S> MutSliceSearcher<T, S> where S: Searcher<&'a [T]> {
+        fn new<F>(haystack: &'a mut [T], make_searcher: F) -> Self
+            where F: FnOnce(&'a [T]) -> S
+        {
+            let len = haystack.len();
+            let begin = haystack.as_mut_ptr();
+            let end = unsafe {
+                cursor_add(begin, len) as *mut T
+            };
+            // The view is derived from `begin` as well, so that it stays
+            // valid next to the mutable pointers.
+            let view = unsafe {
+                ::core::slice::from_raw_parts(begin, len)
+            };
+            MutSliceSearcher {
+                haystack: (begin, end),
+                view: view.as_ptr(),
+                inner: make_searcher(view),
+            }
+        }
+    }
+
+    impl<T, S> MutSliceSearcher<T, S> {
+        // Like for `MutStrSearcher`, the cursors of the inner searcher
+        // only grant read access, so they are re-derived from `begin`.
+        fn rebase(&self, range: (*const T, *const T)) -> (*mut T, *mut T) {
+            let begin = self.haystack.0 as *const T;
+            unsafe {
+                (cursor_add(begin, distance(self.view, range.0)) as *mut T,
+                 cursor_add(begin, distance(self.view, range.1)) as *mut T)
+            }
+        }
+    }
+
+    unsafe impl<'a, T, S> Searcher<&'a mut [T]> for MutSliceSearcher<T, S>
+        where S: Searcher<&'a [T]>
+    {
+        fn haystack(&self) -> (*mut T, *mut T) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*mut T> {
+            match self.inner.next() {
+                SearchStep::Match(begin, end) => {
+                    let (begin, end) = self.rebase((begin, end));
+                    SearchStep::Match(begin, end)
+                }
+                SearchStep::Reject(begin, end) => {
+                    let (begin, end) = self.rebase((begin, end));
+                    SearchStep::Reject(begin, end)
+                }
+                SearchStep::Done => SearchStep::Done,
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*mut T, *mut T)> {
+            self.inner.next_match().map(|range| self.rebase(range))
+        }
+
+        fn next_reject(&mut self) -> Option<(*mut T, *mut T)> {
+            self.inner.next_reject().map(|range| self.rebase(range))
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            self.inner.match_bounds()
+        }
+    }
+
+    impl<'a, T, S> FusedSearcher<&'a mut [T]> for MutSliceSearcher<T, S>
+        where S: FusedSearcher<&'a [T]>
+    {}
+
+    unsafe impl<'a, T, S> ReverseSearcher<&'a mut [T]> for MutSliceSearcher<T, S>
+        where S: ReverseSearcher<&'a [T]>
+    {
+        fn next_match_back(&mut self) -> Option<(*mut T, *mut T)> {
+            self.inner.next_match_back().map(|range| self.rebase(range))
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*mut T, *mut T)> {
+            self.inner.next_reject_back().map(|range| self.rebase(range))
+        }
+    }
+
+    impl<'a, T, S> DoubleEndedSearcher<&'a mut [T]> for MutSliceSearcher<T, S>
+        where S: DoubleEndedSearcher<&'a [T]> {}
+
+    impl<'a, T: PartialEq> Pattern<&'a mut [T]> for Elem<T> {
+        type Searcher = MutSliceSearcher<T, ElemSearcher<'a, T>>;
+
+        fn into_searcher(self, haystack: &'a mut [T]) -> Self::Searcher {
+            MutSliceSearcher::new(haystack, |view| self.into_searcher(view))
+        }
+
+        fn is_prefix_of(self, haystack: &'a mut [T]) -> bool {
+            haystack.first() == Some(&self.0)
+        }
+
+        fn is_suffix_of(self, haystack: &'a mut [T]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a mut [T]> {
+            haystack.last() == Some(&self.0)
+        }
+    }
+
+    // Searches for a sequence of elements, like the substring patterns.
+    #[derive(Clone)]
+    pub struct SubsliceSearcher<'a, T: 'a, N> {
+        haystack: (*const T, *const T),
+        start: *const T,
+        end: *const T,
+        needle: N,
+        // Only set for the empty needle
+        empty_needle: Option<EmptyNeedle>,
+        _marker: ::core::marker::PhantomData<&'a [T]>
+    }
+
+    impl<'a, T, N> ::core::fmt::Debug for SubsliceSearcher<'a, T, N>
+        where T: ::core::fmt::Debug,
+              N: AsRef<[T]>,
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let remaining = unsafe {
+                debug_range::<&[T]>(self.haystack, self.start, self.end)
+            };
+            f.debug_struct("SubsliceSearcher")
+                .field("remaining", &remaining)
+                .field("needle", &self.needle.as_ref())
+                .finish()
+        }
+    }
+
+    impl<'a, T, N> SubsliceSearcher<'a, T, N>
+        where T: PartialEq,
+              N: AsRef<[T]>,
+    {
+        pub fn new(haystack: &'a [T], needle: N) -> Self {
+            let (begin, end) = haystack_bounds(haystack);
+            let empty_needle = if needle.as_ref().is_empty() {
+                Some(EmptyNeedle::new())
+            } else {
+                None
+            };
+            SubsliceSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                needle,
+                empty_needle,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        // Finds the begin of the next occurence of the (non-empty) needle
+        // in `[start, end)` without advancing the searcher.
+        fn find(&self) -> Option<*const T> {
+            let needle = self.needle.as_ref();
+            unsafe {
+                slice_between(self.haystack, self.start, self.end)
+                    .windows(needle.len())
+                    .position(|w| w == needle)
+                    .map(|i| cursor_add(self.start, i))
+            }
+        }
+    }
+
+    unsafe impl<'a, T, N> Searcher<&'a [T]> for SubsliceSearcher<'a, T, N>
+        where T: PartialEq,
+              N: AsRef<[T]>,
+    {
+        fn haystack(&self) -> (*const T, *const T) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const T> {
+            if let Some(ref mut empty) = self.empty_needle {
+                return empty.next(&mut self.start, self.end, |p| unsafe {
+                    cursor_add(p, 1)
+                });
+            }
+
+            let p = self.start;
+            match self.find() {
+                Some(m) if m == p => unsafe {
+                    self.start = cursor_add(p, self.needle.as_ref().len());
+                    SearchStep::Match(p, self.start)
+                },
+                Some(m) => {
+                    self.start = m;
+                    SearchStep::Reject(p, m)
+                }
+                None if p == self.end => SearchStep::Done,
+                None => {
+                    self.start = self.end;
+                    SearchStep::Reject(p, self.end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const T, *const T)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                return empty.next_match(&mut self.start, self.end, |p| unsafe {
+                    cursor_add(p, 1)
+                });
+            }
+
+            match self.find() {
+                Some(p) => unsafe {
+                    self.start = cursor_add(p, self.needle.as_ref().len());
+                    Some((p, self.start))
+                },
+                None => {
+                    self.start = self.end;
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const T, *const T)> {
+            if let Some(ref mut empty) = self.empty_needle {
+                return empty.next_reject(&mut self.start, self.end, |p| unsafe {
+                    cursor_add(p, 1)
+                });
+            }
+
+            // Rejects are the gaps between matches, so skip any match
+            // that begins right at the current position.
+            while self.start != self.end {
+                let p = self.start;
+                match self.find() {
+                    Some(m) if m == p => unsafe {
+                        self.start = cursor_add(p, self.needle.as_ref().len());
+                    },
+                    Some(m) => {
+                        self.start = m;
+                        return Some((p, m));
+                    }
+                    None => {
+                        self.start = self.end;
+                        return Some((p, self.end));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, T, N> FusedSearcher<&'a [T]> for SubsliceSearcher<'a, T, N>
+        where T: PartialEq,
+              N: AsRef<[T]>,
+    {}
+
+    impl_pattern_for_ref! {
+        [T] Elem<T>,
+    }
+}
+
+// Haystacks of decoded chars, like a `Vec<char>` kept for indexing.
+// `SearchPtrs for &[char]` is provided by `generic_slice`, so unlike
+// for `&str` the offsets returned by `offset_from_start` are char
+// indices, not byte indices.
+pub mod char_slice {
+    use super::*;
+    use super::generic_slice::{Elem, ElemSearcher, SubsliceSearcher};
+
+    impl<'a> Pattern<&'a [char]> for char {
+        type Searcher = ElemSearcher<'a, char>;
+
+        fn into_searcher(self, haystack: &'a [char]) -> Self::Searcher {
+            Elem(self).into_searcher(haystack)
+        }
+
+        fn is_prefix_of(self, haystack: &'a [char]) -> bool {
+            haystack.first() == Some(&self)
+        }
+
+        fn is_suffix_of(self, haystack: &'a [char]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [char]> {
+            haystack.last() == Some(&self)
+        }
+    }
+
+    // Matches the chars as a sequence. Note that on `&str` haystacks
+    // a `&[char]` matches any single one of the chars instead.
+    impl<'a, 'b> Pattern<&'a [char]> for &'b [char] {
+        type Searcher = SubsliceSearcher<'a, char, &'b [char]>;
+
+        fn into_searcher(self, haystack: &'a [char]) -> Self::Searcher {
+            SubsliceSearcher::new(haystack, self)
+        }
+
+        fn is_prefix_of(self, haystack: &'a [char]) -> bool {
+            haystack.starts_with(self)
+        }
+
+        fn is_suffix_of(self, haystack: &'a [char]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [char]> {
+            haystack.ends_with(self)
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct CharPredicateSearcher<'a, F> {
+        haystack: (*const char, *const char),
+        start: *const char,
+        end: *const char,
+        predicate: F,
+        _marker: ::core::marker::PhantomData<&'a [char]>
+    }
+
+    impl_searcher_debug! { &'a [char] => ['a, F] CharPredicateSearcher<'a, F> {} }
+
+    unsafe impl<'a, F> Searcher<&'a [char]> for CharPredicateSearcher<'a, F>
+        where F: FnMut(char) -> bool
+    {
+        fn haystack(&self) -> (*const char, *const char) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const char> {
+            if self.start == self.end {
+                return SearchStep::Done;
+            }
+            unsafe {
+                let p = self.start;
+                self.start = self.start.offset(1);
+
+                if (self.predicate)(*p) {
+                    SearchStep::Match(p, self.start)
+                } else {
+                    SearchStep::Reject(p, self.start)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const char, *const char)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if (self.predicate)(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject(&mut self) -> Option<(*const char, *const char)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.start;
+                    self.start = self.start.offset(1);
+
+                    if !(self.predicate)(*p) {
+                        return Some((p, self.start));
+                    }
+                }
+            }
+            None
+        }
+
+        // Every remaining element can be a match at most once
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (0, Some(unsafe { self.end.offset_from(self.start) } as usize))
+        }
+    }
+
+    impl<'a, F> FusedSearcher<&'a [char]> for CharPredicateSearcher<'a, F>
+        where F: FnMut(char) -> bool
+    {}
+
+    unsafe impl<'a, F> ReverseSearcher<&'a [char]> for CharPredicateSearcher<'a, F>
+        where F: FnMut(char) -> bool
+    {
+        fn next_match_back(&mut self) -> Option<(*const char, *const char)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if (self.predicate)(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const char, *const char)> {
+            while self.start != self.end {
+                unsafe {
+                    let p = self.end;
+                    self.end = self.end.offset(-1);
+
+                    if !(self.predicate)(*self.end) {
+                        return Some((self.end, p));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, F> DoubleEndedSearcher<&'a [char]> for CharPredicateSearcher<'a, F>
+        where F: FnMut(char) -> bool {}
+
+    impl<'a, F> Pattern<&'a [char]> for F where F: FnMut(char) -> bool {
+        type Searcher = CharPredicateSearcher<'a, F>;
+
+        fn into_searcher(self, haystack: &'a [char]) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            CharPredicateSearcher {
+                haystack: (begin, end),
+                start: begin,
+                end,
+                predicate: self,
+                _marker: ::core::marker::PhantomData,
+            }
+        }
+
+        fn is_prefix_of(mut self, haystack: &'a [char]) -> bool {
+            haystack.first()
+                .map(|&c| self(c))
+                .unwrap_or(false)
+        }
+
+        fn is_suffix_of(mut self, haystack: &'a [char]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [char]> {
+            haystack.last()
+                .map(|&c| self(c))
+                .unwrap_or(false)
+        }
+    }
+}
+
+// UTF-16 haystacks like Windows wide strings. `SearchPtrs for &[u16]`
+// is provided by `generic_slice`, so cursors are `*const u16` and
+// offsets are counted in code units.
+pub mod utf16 {
+    use super::*;
+
+    // Matches a single code unit, which may be half of a surrogate pair.
+    #[derive(Clone, Copy)]
+    pub struct Unit(pub u16);
+
+    impl<'a> Pattern<&'a [u16]> for Unit {
+        type Searcher = generic_slice::ElemSearcher<'a, u16>;
+
+        fn into_searcher(self, haystack: &'a [u16]) -> Self::Searcher {
+            generic_slice::Elem(self.0).into_searcher(haystack)
+        }
+
+        fn is_prefix_of(self, haystack: &'a [u16]) -> bool {
+            haystack.first() == Some(&self.0)
+        }
+
+        fn is_suffix_of(self, haystack: &'a [u16]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [u16]> {
+            haystack.last() == Some(&self.0)
+        }
+    }
+
+    // The needle is generic so that needles like `EncodedChar`
+    // can be owned by the searcher.
+    pub type UnitsSearcher<'a, N> = generic_slice::SubsliceSearcher<'a, u16, N>;
+
+    impl<'a, 'b> Pattern<&'a [u16]> for &'b [u16] {
+        type Searcher = UnitsSearcher<'a, &'b [u16]>;
+
+        fn into_searcher(self, haystack: &'a [u16]) -> Self::Searcher {
+            generic_slice::SubsliceSearcher::new(haystack, self)
+        }
+
+        fn is_prefix_of(self, haystack: &'a [u16]) -> bool {
+            haystack.starts_with(self)
+        }
+
+        fn is_suffix_of(self, haystack: &'a [u16]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [u16]> {
+            haystack.ends_with(self)
+        }
+    }
+
+    // The UTF-16 encoding of a char: one unit, or a surrogate pair.
+    #[derive(Clone, Copy)]
+    pub struct EncodedChar {
+        buf: [u16; 2],
+        len: usize,
+    }
+
+    impl EncodedChar {
+        pub fn new(c: char) -> Self {
+            let mut buf = [0; 2];
+            let len = c.encode_utf16(&mut buf).len();
+            EncodedChar { buf, len }
+        }
+    }
+
+    impl AsRef<[u16]> for EncodedChar {
+        fn as_ref(&self) -> &[u16] {
+            &self.buf[..self.len]
+        }
+    }
+
+    // Matches the UTF-16 encoding of the char, so a supplementary char
+    // matches its whole surrogate pair. A char is never a surrogate
+    // itself, so a BMP char can't match half of a pair.
+    impl<'a> Pattern<&'a [u16]> for char {
+        type Searcher = UnitsSearcher<'a, EncodedChar>;
+
+        fn into_searcher(self, haystack: &'a [u16]) -> Self::Searcher {
+            generic_slice::SubsliceSearcher::new(haystack, EncodedChar::new(self))
+        }
+
+        fn is_prefix_of(self, haystack: &'a [u16]) -> bool {
+            haystack.starts_with(EncodedChar::new(self).as_ref())
+        }
+
+        fn is_suffix_of(self, haystack: &'a [u16]) -> bool
+            where Self::Searcher: ReverseSearcher<&'a [u16]> {
+            haystack.ends_with(EncodedChar::new(self).as_ref())
+        }
+    }
+
+    impl_pattern_for_ref! {
+        [] Unit,
+    }
+}
+
+#[cfg(feature = "regex")]
+pub mod regex {
+    use super::*;
+
+    // Searches a `&str` with a regular expression.
+    //
+    // Matches follow the same rules as `Regex::find_iter`, in particular
+    // an empty match directly after the previous match is skipped.
+    // Rejects are the gaps between the matches.
+    #[derive(Clone)]
+    pub struct RegexSearcher<'a, 'b> {
+        haystack: (*const u8, *const u8),
+        text: &'a str,
+        regex: &'b ::regex::Regex,
+        // Byte offset of the next position to search from
+        pos: usize,
+        last_match_end: Option<usize>,
+    }
+
+    impl<'a, 'b> ::core::fmt::Debug for RegexSearcher<'a, 'b> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_struct("RegexSearcher")
+                .field("remaining", &(self.pos..self.text.len()))
+                .field("regex", &self.regex.as_str())
+                .finish()
+        }
+    }
+
+    impl<'a, 'b> RegexSearcher<'a, 'b> {
+        // Finds the next match at or after `pos`
+        // without advancing the searcher.
+        fn find(&self) -> Option<(usize, usize)> {
+            let mut at = self.pos;
+            loop {
+                let m = self.regex.find_at(self.text, at)?;
+                if m.start() == m.end() && Some(m.end()) == self.last_match_end {
+                    at += self.text[at..].chars().next()?.len_utf8();
+                    continue;
+                }
+                return Some((m.start(), m.end()));
+            }
+        }
+
+        fn cursors(&self, begin: usize, end: usize) -> (*const u8, *const u8) {
+            unsafe {
+                (self.haystack.0.add(begin), self.haystack.0.add(end))
+            }
+        }
+    }
+
+    unsafe impl<'a, 'b> Searcher<&'a str> for RegexSearcher<'a, 'b> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            let (p, len) = (self.pos, self.text.len());
+            match self.find() {
+                Some((begin, end)) if begin == p => {
+                    self.pos = end;
+                    self.last_match_end = Some(end);
+                    let (begin, end) = self.cursors(begin, end);
+                    SearchStep::Match(begin, end)
+                }
+                Some((begin, _)) => {
+                    self.pos = begin;
+                    let (p, begin) = self.cursors(p, begin);
+                    SearchStep::Reject(p, begin)
+                }
+                None if p == len => SearchStep::Done,
+                None => {
+                    self.pos = len;
+                    let (p, end) = self.cursors(p, len);
+                    SearchStep::Reject(p, end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            match self.find() {
+                Some((begin, end)) => {
+                    self.pos = end;
+                    self.last_match_end = Some(end);
+                    Some(self.cursors(begin, end))
+                }
+                None => {
+                    self.pos = self.text.len();
+                    None
+                }
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            let len = self.text.len();
+            while self.pos != len {
+                let p = self.pos;
+                match self.find() {
+                    Some((begin, end)) if begin == p => {
+                        // Skip a match at the current position
+                        self.pos = end;
+                        self.last_match_end = Some(end);
+                    }
+                    Some((begin, _)) => {
+                        self.pos = begin;
+                        return Some(self.cursors(p, begin));
+                    }
+                    None => {
+                        self.pos = len;
+                        return Some(self.cursors(p, len));
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<'a, 'b> FusedSearcher<&'a str> for RegexSearcher<'a, 'b> {}
+
+    impl<'a, 'b> Pattern<&'a str> for &'b ::regex::Regex {
+        type Searcher = RegexSearcher<'a, 'b>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let begin = haystack.as_ptr();
+            let end = unsafe {
+                haystack.as_ptr().add(haystack.len())
+            };
+            RegexSearcher {
+                haystack: (begin, end),
+                text: haystack,
+                regex: self,
+                pos: 0,
+                last_match_end: None,
+            }
+        }
+
+        // The leftmost match starts at the front exactly if there is
+        // a match anchored there.
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            self.find(haystack).map(|m| m.start() == 0).unwrap_or(false)
+        }
+
+        // `is_suffix_of` is left to the default, as the searcher can't
+        // search backwards and so the method can't be called anyway.
+    }
+
+    #[test]
+    fn test_regex_split() {
+        use super::api_consumer::{split, match_indices};
+
+        let re = ::regex::Regex::new(r"\s+").unwrap();
+        for &haystack in &["a b\t\tc\n", "  lead", "", "none", "é \u{a0}ü"] {
+            assert_eq!(split(haystack, &re),
+                       re.split(haystack).collect::<Vec<_>>());
+        }
+
+        let re = ::regex::Regex::new(r"[0-9]+").unwrap();
+        assert_eq!(match_indices("a12b3", &re), vec![(1, "12"), (4, "3")]);
+        assert!(re.is_prefix_of("12a"));
+        assert!(!re.is_prefix_of("a12"));
+
+        let mut searcher = re.into_searcher("a12b3");
+        let hs = searcher.haystack();
+        let mut rejects = vec![];
+        while let Some((begin, end)) = searcher.next_reject() {
+            unsafe {
+                rejects.push(<&str>::range_to_self(hs, begin, end));
+            }
+        }
+        assert_eq!(rejects, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_regex_empty_matches() {
+        use super::api_consumer::match_indices;
+
+        let re = ::regex::Regex::new(r"x*").unwrap();
+        for &haystack in &["axxbé", "", "xx"] {
+            assert_eq!(match_indices(haystack, &re),
+                       re.find_iter(haystack)
+                         .map(|m| (m.start(), m.as_str()))
+                         .collect::<Vec<_>>());
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+pub mod std_pattern {
+    use super::*;
+    use core::str::pattern as std_pattern;
+
+    // Uses a pattern of `std::str::pattern`, like a `char`, a `&str`,
+    // a char array or a closure, as a pattern of the sketch. The byte
+    // indices of the std searcher become cursors at the same offsets.
+    #[derive(Clone, Copy)]
+    pub struct StdPattern<P>(pub P);
+
+    #[derive(Clone)]
+    pub struct StdSearcher<S> {
+        haystack: (*const u8, *const u8),
+        inner: S,
+    }
+
+    impl<S: ::core::fmt::Debug> ::core::fmt::Debug for StdSearcher<S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_tuple("StdSearcher").field(&self.inner).finish()
+        }
+    }
+
+    impl<S> StdSearcher<S> {
+        fn cursors(&self, (begin, end): (usize, usize)) -> (*const u8, *const u8) {
+            unsafe {
+                (self.haystack.0.add(begin), self.haystack.0.add(end))
+            }
+        }
+
+        fn step(&self, step: std_pattern::SearchStep) -> SearchStep<*const u8> {
+            match step {
+                std_pattern::SearchStep::Match(begin, end) => {
+                    let (begin, end) = self.cursors((begin, end));
+                    SearchStep::Match(begin, end)
+                }
+                std_pattern::SearchStep::Reject(begin, end) => {
+                    let (begin, end) = self.cursors((begin, end));
+                    SearchStep::Reject(begin, end)
+                }
+                std_pattern::SearchStep::Done => SearchStep::Done,
+            }
+        }
+    }
+
+    unsafe impl<'a, S> Searcher<&'a str> for StdSearcher<S>
+        where S: std_pattern::Searcher<'a>
+    {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            let step = self.inner.next();
+            self.step(step)
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            self.inner.next_match().map(|m| self.cursors(m))
+        }
+
+        fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+            self.inner.next_reject().map(|r| self.cursors(r))
+        }
+    }
+
+    unsafe impl<'a, S> ReverseSearcher<&'a str> for StdSearcher<S>
+        where S: std_pattern::ReverseSearcher<'a>
+    {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.inner.next_match_back().map(|m| self.cursors(m))
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            self.inner.next_reject_back().map(|r| self.cursors(r))
+        }
+    }
+
+    impl<'a, S> DoubleEndedSearcher<&'a str> for StdSearcher<S>
+        where S: std_pattern::DoubleEndedSearcher<'a>
+    {}
+
+    impl<'a, P: std_pattern::Pattern> Pattern<&'a str> for StdPattern<P> {
+        type Searcher = StdSearcher<P::Searcher<'a>>;
+
+        fn into_searcher(self, haystack: &'a str) -> Self::Searcher {
+            let range = haystack.as_bytes().as_ptr_range();
+            StdSearcher {
+                haystack: (range.start, range.end),
+                inner: self.0.into_searcher(haystack),
+            }
+        }
+
+        fn is_prefix_of(self, haystack: &'a str) -> bool {
+            self.0.is_prefix_of(haystack)
+        }
+
+        fn is_contained_in(self, haystack: &'a str) -> bool {
+            self.0.is_contained_in(haystack)
+        }
+    }
+
+    impl_pattern_for_ref! {
+        [P] StdPattern<P>,
+    }
+
+    // The other direction: uses a pattern of the sketch as a std pattern,
+    // so it can be passed to `str::find`, `str::split` and so on.
+    //
+    // Std asks for steps covering the whole haystack, so the searcher
+    // asks the inner one for matches only, which takes its fast path,
+    // and yields the gaps in between as rejects.
+    #[derive(Clone, Copy)]
+    pub struct AsStd<P>(pub P);
+
+    #[derive(Clone)]
+    pub struct AsStdSearcher<'a, S> {
+        haystack: &'a str,
+        inner: S,
+        // The part not covered by steps yet
+        front: usize,
+        back: usize,
+        // Matches found behind a gap, yielded after the reject for the gap
+        pending: Option<(usize, usize)>,
+        pending_back: Option<(usize, usize)>,
+    }
+
+    impl<'a, S: ::core::fmt::Debug> ::core::fmt::Debug for AsStdSearcher<'a, S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_struct("AsStdSearcher")
+                .field("remaining", &(self.front..self.back))
+                .field("inner", &self.inner)
+                .finish()
+        }
+    }
+
+    impl<'a, S: Searcher<&'a str>> AsStdSearcher<'a, S> {
+        fn offsets(&self, (begin, end): (*const u8, *const u8)) -> (usize, usize) {
+            let hs = self.inner.haystack();
+            unsafe {
+                (<&str>::offset_from_start(hs, begin), <&str>::offset_from_start(hs, end))
+            }
+        }
+    }
+
+    unsafe impl<'a, S> std_pattern::Searcher<'a> for AsStdSearcher<'a, S>
+        where S: Searcher<&'a str>
+    {
+        fn haystack(&self) -> &'a str {
+            self.haystack
+        }
+
+        fn next(&mut self) -> std_pattern::SearchStep {
+            if let Some((begin, end)) = self.pending.take() {
+                self.front = end;
+                return std_pattern::SearchStep::Match(begin, end);
+            }
+            match self.inner.next_match().map(|m| self.offsets(m)) {
+                Some((begin, end)) if begin == self.front => {
+                    self.front = end;
+                    std_pattern::SearchStep::Match(begin, end)
+                }
+                Some((begin, end)) => {
+                    let gap = std_pattern::SearchStep::Reject(self.front, begin);
+                    self.front = begin;
+                    self.pending = Some((begin, end));
+                    gap
+                }
+                None => {
+                    let limit = self.pending_back.map_or(self.back, |(begin, _)| begin);
+                    if self.front < limit {
+                        let gap = std_pattern::SearchStep::Reject(self.front, limit);
+                        self.front = limit;
+                        gap
+                    } else if let Some((begin, end)) = self.pending_back.take() {
+                        self.front = end;
+                        std_pattern::SearchStep::Match(begin, end)
+                    } else {
+                        std_pattern::SearchStep::Done
+                    }
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(usize, usize)> {
+            let m = self.pending.take()
+                .or_else(|| self.inner.next_match().map(|m| self.offsets(m)))
+                .or_else(|| self.pending_back.take());
+            match m {
+                Some((_, end)) => self.front = end,
+                None => self.front = self.back,
+            }
+            m
+        }
+    }
+
+    unsafe impl<'a, S> std_pattern::ReverseSearcher<'a> for AsStdSearcher<'a, S>
+        where S: ReverseSearcher<&'a str>
+    {
+        fn next_back(&mut self) -> std_pattern::SearchStep {
+            if let Some((begin, end)) = self.pending_back.take() {
+                self.back = begin;
+                return std_pattern::SearchStep::Match(begin, end);
+            }
+            match self.inner.next_match_back().map(|m| self.offsets(m)) {
+                Some((begin, end)) if end == self.back => {
+                    self.back = begin;
+                    std_pattern::SearchStep::Match(begin, end)
+                }
+                Some((begin, end)) => {
+                    let gap = std_pattern::SearchStep::Reject(end, self.back);
+                    self.back = end;
+                    self.pending_back = Some((begin, end));
+                    gap
+                }
+                None => {
+                    let limit = self.pending.map_or(self.front, |(_, end)| end);
+                    if limit < self.back {
+                        let gap = std_pattern::SearchStep::Reject(limit, self.back);
+                        self.back = limit;
+                        gap
+                    } else if let Some((begin, end)) = self.pending.take() {
+                        self.back = begin;
+                        std_pattern::SearchStep::Match(begin, end)
+                    } else {
+                        std_pattern::SearchStep::Done
+                    }
+                }
+            }
+        }
+
+        fn next_match_back(&mut self) -> Option<(usize, usize)> {
+            let m = self.pending_back.take()
+                .or_else(|| self.inner.next_match_back().map(|m| self.offsets(m)))
+                .or_else(|| self.pending.take());
+            match m {
+                Some((begin, _)) => self.back = begin,
+                None => self.back = self.front,
+            }
+            m
+        }
+    }
+
+    impl<'a, S> std_pattern::DoubleEndedSearcher<'a> for AsStdSearcher<'a, S>
+        where S: DoubleEndedSearcher<&'a str>
+    {}
+
+    impl<P> std_pattern::Pattern for AsStd<P>
+        where P: for<'a> Pattern<&'a str>
+    {
+        type Searcher<'a> = AsStdSearcher<'a, <P as Pattern<&'a str>>::Searcher>;
+
+        fn into_searcher(self, haystack: &str) -> Self::Searcher<'_> {
+            AsStdSearcher {
+                haystack,
+                inner: self.0.into_searcher(haystack),
+                front: 0,
+                back: haystack.len(),
+                pending: None,
+                pending_back: None,
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_as_std() {
+        use super::string::{Ascii, AsciiSet, MultiStr};
+        use std::str::pattern::{ReverseSearcher as _, SearchStep as Step, Searcher as _};
+
+        let a = Ascii::new(b'a').unwrap();
+        for &haystack in &["", "a", "banana", "xaay", "éaé", "aab"] {
+            assert_eq!(haystack.split(AsStd(a)).collect::<Vec<_>>(),
+                       haystack.split('a').collect::<Vec<_>>());
+            assert_eq!(haystack.rsplit(AsStd(a)).collect::<Vec<_>>(),
+                       haystack.rsplit('a').collect::<Vec<_>>());
+            assert_eq!(haystack.split(AsStd(a)).rev().collect::<Vec<_>>(),
+                       haystack.split('a').rev().collect::<Vec<_>>());
+            assert_eq!(haystack.trim_matches(AsStd(a)), haystack.trim_matches('a'));
+            assert_eq!(haystack.find(AsStd("ab")), haystack.find("ab"));
+            assert_eq!(haystack.matches(AsStd(MultiStr(&["a", "b"]))).collect::<Vec<_>>(),
+                       haystack.matches(['a', 'b']).collect::<Vec<_>>());
+        }
+
+        assert_eq!("banana".split(AsStd(a)).collect::<Vec<_>>(), vec!["b", "n", "n", ""]);
+        assert_eq!("a,b;c".split(AsStd(AsciiSet::new(b",;"))).collect::<Vec<_>>(),
+                   vec!["a", "b", "c"]);
+        assert_eq!("xxaxx".find(AsStd(a)), Some(2));
+        assert!("banana".starts_with(AsStd("ban")));
+        assert_eq!("ab ab".replace(AsStd("ab"), "c"), "c c");
+
+        // Mixing both ends: the gap yielded from the back ends where
+        // the match found from the front was left pending
+        let mut searcher = std_pattern::Pattern::into_searcher(AsStd(a), "xaxx");
+        assert_eq!(searcher.next(), Step::Reject(0, 1));
+        assert_eq!(searcher.next_back(), Step::Reject(2, 4));
+        assert_eq!(searcher.next_back(), Step::Match(1, 2));
+        assert_eq!(searcher.next(), Step::Done);
+        assert_eq!(searcher.next_back(), Step::Done);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_std_pattern_split() {
+        use super::api_consumer::{find_not, match_indices, rsplit, split};
+
+        for &haystack in &["", "a", "banana", "abab", "xaby", "éaé"] {
+            assert_eq!(split(haystack, StdPattern('a')),
+                       haystack.split('a').collect::<Vec<_>>());
+            assert_eq!(split(haystack, StdPattern("ab")),
+                       haystack.split("ab").collect::<Vec<_>>());
+            assert_eq!(split(haystack, StdPattern(['b', 'é'])),
+                       haystack.split(['b', 'é']).collect::<Vec<_>>());
+            assert_eq!(rsplit(haystack, StdPattern(char::is_alphabetic)),
+                       haystack.rsplit(char::is_alphabetic).collect::<Vec<_>>());
+        }
+
+        assert_eq!(match_indices("banana", StdPattern("an")), vec![(1, "an"), (3, "an")]);
+        assert_eq!(find_not("aaba", StdPattern('a')), Some(2));
+        assert!(StdPattern('b').is_prefix_of("banana"));
+        assert!(!StdPattern("nab").is_contained_in("banana"));
+    }
+}
+
+pub mod generic {
+    use super::*;
+    use core::cmp::Ordering;
+
+    // Inverts a pattern by swapping its matches and rejects.
+    //
+    // Note that this is only a true complement for patterns matching
+    // single elements. For variable-width patterns the matches of `Not<P>`
+    // are whatever `P`s searcher yields as rejects, eg. the gaps between
+    // two occurrences of a substring, and its rejects are the occurrences.
+    #[derive(Clone, Copy)]
+    pub struct Not<P>(pub P);
+
+    #[derive(Clone, Debug)]
+    pub struct NotSearcher<S>(S);
+
+    unsafe impl<H, S> Searcher<H> for NotSearcher<S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.0.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            match self.0.next() {
+                SearchStep::Match(begin, end) => SearchStep::Reject(begin, end),
+                SearchStep::Reject(begin, end) => SearchStep::Match(begin, end),
+                SearchStep::Done => SearchStep::Done,
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.0.next_reject()
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.0.next_match()
+        }
+    }
+
+    impl<H, S> FusedSearcher<H> for NotSearcher<S>
+        where H: SearchPtrs,
+              S: FusedSearcher<H>,
+    {}
+
+    unsafe impl<H, S> ReverseSearcher<H> for NotSearcher<S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.0.next_reject_back()
+        }
+
+        fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.0.next_match_back()
+        }
+    }
+
+    impl<H, S> DoubleEndedSearcher<H> for NotSearcher<S>
+        where H: SearchPtrs,
+              S: DoubleEndedSearcher<H> {}
+
+    impl<H, P> Pattern<H> for Not<P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        type Searcher = NotSearcher<P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            NotSearcher(self.0.into_searcher(haystack))
+        }
+
+        // This is the inverse of the inner prefix check, except for
+        // the empty haystack which contains neither matches nor rejects.
+        fn is_prefix_of(self, haystack: H) -> bool {
+            first_match_at_front(self.into_searcher(haystack))
+        }
+
+        fn is_suffix_of(self, haystack: H) -> bool
+            where Self::Searcher: ReverseSearcher<H> {
+            last_match_at_back(self.into_searcher(haystack))
+        }
+    }
+
+    // Merges runs of adjacent rejects of the inner pattern into single
+    // maximal rejects, so that eg. the rejects of `slice::Ascii` cover
+    // whole gaps instead of single bytes. Matches are passed through.
+    //
+    // Finding the end of a run needs one step of lookahead, which is
+    // buffered. A buffered match is returned by the next `next_match`,
+    // but `next_reject` has already stepped over the matches in front of
+    // a buffered reject, so `next_match` can't return those: it discards
+    // the buffer and continues behind it.
+    #[derive(Clone, Copy)]
+    pub struct Coalesce<P>(pub P);
+
+    #[derive(Clone)]
+    pub struct CoalescingSearcher<H: SearchPtrs, S> {
+        inner: S,
+        buffered: Option<SearchStep<H::Cursor>>,
+    }
+
+    impl<H, S> ::core::fmt::Debug for CoalescingSearcher<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H> + ::core::fmt::Debug,
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let hs = self.inner.haystack();
+            let buffered = self.buffered.map(|step| {
+                step.map(|c| unsafe { H::offset_from_start(hs, c) })
+            });
+            f.debug_struct("CoalescingSearcher")
+                .field("inner", &self.inner)
+                .field("buffered", &buffered)
+                .finish()
+        }
+    }
+
+    unsafe impl<H, S> Searcher<H> for CoalescingSearcher<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        // The steps of the inner searcher are contiguous,
+        // so every reject following a reject is adjacent to it.
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            let step = match self.buffered.take() {
+                Some(step) => step,
+                None => self.inner.next(),
+            };
+            let (begin, mut end) = match step {
+                SearchStep::Reject(begin, end) => (begin, end),
+                step => return step,
+            };
+
+            loop {
+                match self.inner.next() {
+                    SearchStep::Reject(_, next_end) => end = next_end,
+                    step => {
+                        self.buffered = Some(step);
+                        break;
+                    }
+                }
+            }
+            SearchStep::Reject(begin, end)
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            match self.buffered.take() {
+                Some(SearchStep::Match(begin, end)) => Some((begin, end)),
+                Some(SearchStep::Done) => None,
+                _ => self.inner.next_match(),
+            }
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let hs = self.inner.haystack();
+            let (begin, mut end) = match self.buffered.take() {
+                Some(SearchStep::Reject(begin, end)) => (begin, end),
+                Some(SearchStep::Done) => return None,
+                _ => self.inner.next_reject()?,
+            };
+
+            while let Some((next_begin, next_end)) = self.inner.next_reject() {
+                if unsafe { H::cursor_cmp(hs, next_begin, end) } == Ordering::Equal {
+                    end = next_end;
+                } else {
+                    self.buffered = Some(SearchStep::Reject(next_begin, next_end));
+                    break;
+                }
+            }
+            Some((begin, end))
+        }
+    }
+
+    impl<H, S> FusedSearcher<H> for CoalescingSearcher<H, S>
+        where H: SearchPtrs,
+              S: FusedSearcher<H>,
+    {}
+
+    impl<H, P> Pattern<H> for Coalesce<P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        type Searcher = CoalescingSearcher<H, P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            CoalescingSearcher { inner: self.0.into_searcher(haystack), buffered: None }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            self.0.is_prefix_of(haystack)
+        }
+    }
+
+    // Checks whether the first match of the searcher starts at the front
+    // of the haystack.
+    fn first_match_at_front<H, S>(mut searcher: S) -> bool
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        match_at_front(&mut searcher).is_some()
+    }
+
+    // Returns the first match of the searcher if it starts at the front
+    // of the haystack. Only that one match is consumed, so the searcher
+    // can be used further afterwards.
+    fn match_at_front<H, S>(searcher: &mut S) -> Option<(H::Cursor, H::Cursor)>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        let hs = searcher.haystack();
+        let (begin, end) = searcher.next_match()?;
+        unsafe {
+            let front = H::cursor_at_front(hs);
+            if H::cursor_cmp(hs, begin, front) == Ordering::Equal {
+                Some((begin, end))
+            } else {
+                None
+            }
+        }
+    }
+
+    // Maps a cursor of `sub`, a sub-haystack of `hs` created by
+    // `range_to_self` that starts `start` elements into it, to the same
+    // position in `hs`. Only the pointer based haystacks share their
+    // cursors with sub-haystacks, others like `Indexed` or `Chain` count
+    // from the front of the sub-haystack.
+    unsafe fn parent_cursor<H: SearchPtrs>(hs: H::Haystack,
+                                           start: usize,
+                                           sub: H::Haystack,
+                                           cursor: H::Cursor) -> H::Cursor {
+        H::cursor_at_offset(hs, start + H::offset_from_start(sub, cursor))
+    }
+
+    // Returns the match of `pattern` that begins right at `at`. Unless
+    // `is_prefix_of` finds one there, no searcher is started, so this only
+    // scans as far as the pattern needs to decide.
+    unsafe fn match_at<H, P>(pattern: &P, hs: H::Haystack, at: H::Cursor)
+        -> Option<(H::Cursor, H::Cursor)>
+        where H: SearchPtrs,
+              P: Pattern<H> + Clone,
+    {
+        let back = H::cursor_at_back(hs);
+        if !pattern.clone().is_prefix_of(H::range_to_self(hs, at, back)) {
+            return None;
+        }
+        let mut searcher = pattern.clone().into_searcher(H::range_to_self(hs, at, back));
+        let sub = searcher.haystack();
+        let start = H::offset_from_start(hs, at);
+        searcher.next_match().map(|(begin, end)| {
+            (parent_cursor::<H>(hs, start, sub, begin), parent_cursor::<H>(hs, start, sub, end))
+        })
+    }
+
+    // Checks whether the last match of the searcher ends at the back
+    // of the haystack.
+    fn last_match_at_back<H, S>(mut searcher: S) -> bool
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        match_at_back(&mut searcher).is_some()
+    }
+
+    // Returns the last match of the searcher if it ends at the back
+    // of the haystack, consuming only that one match.
+    fn match_at_back<H, S>(searcher: &mut S) -> Option<(H::Cursor, H::Cursor)>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        let hs = searcher.haystack();
+        let (begin, end) = searcher.next_match_back()?;
+        unsafe {
+            let back = H::cursor_at_back(hs);
+            if H::cursor_cmp(hs, end, back) == Ordering::Equal {
+                Some((begin, end))
+            } else {
+                None
+            }
+        }
+    }
+
+    // Shared logic for searchers that find matches through some lookahead
+    // and whose rejects are exactly the gaps between those matches.
+    trait GapSearcher<H: SearchPtrs> {
+        fn gap_haystack(&self) -> H::Haystack;
+
+        // Everything in front of this cursor has been yielded
+        // as either a match or a reject.
+        fn position(&mut self) -> &mut H::Cursor;
+
+        // Finds the next match at or after the position,
+        // without consuming it.
+        fn peek_match(&mut self) -> Option<(H::Cursor, H::Cursor)>;
+
+        // Drops the match returned by the last `peek_match`.
+        fn drop_peeked(&mut self);
+    }
+
+    fn gap_next_match<H, S>(s: &mut S) -> Option<(H::Cursor, H::Cursor)>
+        where H: SearchPtrs,
+              S: GapSearcher<H>,
+    {
+        let (begin, end) = s.peek_match()?;
+        s.drop_peeked();
+        *s.position() = end;
+        Some((begin, end))
+    }
+
+    fn gap_next<H, S>(s: &mut S) -> SearchStep<H::Cursor>
+        where H: SearchPtrs,
+              S: GapSearcher<H>,
+    {
+        let hs = s.gap_haystack();
+        let back = unsafe { H::cursor_at_back(hs) };
+        let p = *s.position();
+
+        match s.peek_match() {
+            Some((begin, end)) => {
+                if unsafe { H::cursor_cmp(hs, begin, p) } == Ordering::Equal {
+                    s.drop_peeked();
+                    *s.position() = end;
+                    return SearchStep::Match(begin, end);
+                }
+                // The match stays buffered for the next call
+                *s.position() = begin;
+                SearchStep::Reject(p, begin)
+            }
+            None => {
+                if unsafe { H::cursor_cmp(hs, p, back) } == Ordering::Equal {
+                    return SearchStep::Done;
+                }
+                *s.position() = back;
+                SearchStep::Reject(p, back)
+            }
+        }
+    }
+
+    fn gap_next_reject<H, S>(s: &mut S) -> Option<(H::Cursor, H::Cursor)>
+        where H: SearchPtrs,
+              S: GapSearcher<H>,
+    {
+        let hs = s.gap_haystack();
+        let back = unsafe { H::cursor_at_back(hs) };
+
+        loop {
+            let p = *s.position();
+            if unsafe { H::cursor_cmp(hs, p, back) } == Ordering::Equal {
+                return None;
+            }
+
+            match s.peek_match() {
+                Some((begin, end)) => {
+                    if unsafe { H::cursor_cmp(hs, begin, p) } == Ordering::Equal {
+                        // Skip a match at the current position
+                        s.drop_peeked();
+                        *s.position() = end;
+                        continue;
+                    }
+                    // The match stays buffered for the next call
+                    *s.position() = begin;
+                    return Some((p, begin));
+                }
+                None => {
+                    *s.position() = back;
+                    return Some((p, back));
+                }
+            }
+        }
+    }
+
+    // Matches wherever either `A` or `B` matches.
+    //
+    // Both inner searchers run independently, and the earlier of their
+    // next matches is yielded, preferring the longer one if both start
+    // at the same position (and `A` if they are identical).
+    // The yielded matches never overlap: a buffered match of one branch
+    // that overlaps a match just yielded from the other branch is dropped.
+    // Matches of that branch overlapping the dropped one may still follow,
+    // so the branch is restarted behind the yielded match with a fresh
+    // searcher on the rest of the haystack. Like for `Then` this requires
+    // patterns that are `Clone`, and the cursors of the fresh searcher are
+    // mapped back to the haystack through their offsets.
+    // Rejects are the gaps between the yielded matches.
+    //
+    // As a `TaggedSearcher` the matches of `A` have the id 0 and those
+    // of `B` the id 1.
+    #[derive(Clone, Copy)]
+    pub struct Or<A, B>(pub A, pub B);
+
+    #[derive(Clone)]
+    pub struct OrSearcher<H: SearchPtrs, A: Pattern<H>, B: Pattern<H>> {
+        haystack: H::Haystack,
+        a: A::Searcher,
+        b: B::Searcher,
+        // Where the sub-haystacks of `a` and `b` start
+        a_start: usize,
+        b_start: usize,
+        pattern_a: A,
+        pattern_b: B,
+        pending_a: Option<(H::Cursor, H::Cursor)>,
+        pending_b: Option<(H::Cursor, H::Cursor)>,
+        a_done: bool,
+        b_done: bool,
+        peeked_a: bool,
+        position: H::Cursor,
+    }
+
+    impl<H, A, B> ::core::fmt::Debug for OrSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H>,
+              B: Pattern<H>,
+              A::Searcher: ::core::fmt::Debug,
+              B::Searcher: ::core::fmt::Debug,
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let hs = self.haystack;
+            let range = |(begin, end)| unsafe { debug_range::<H>(hs, begin, end) };
+            f.debug_struct("OrSearcher")
+                .field("a", &self.a)
+                .field("b", &self.b)
+                .field("pending_a", &self.pending_a.map(range))
+                .field("pending_b", &self.pending_b.map(range))
+                .field("position", &unsafe { H::offset_from_start(hs, self.position) })
+                .finish()
+        }
+    }
+
+    impl<H, A, B> OrSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        // Refills a pending slot. A match that begins in front of the
+        // current position is dropped, and the branch restarted there.
+        fn fill<P: Pattern<H> + Clone>(pattern: &P,
+                                       searcher: &mut P::Searcher,
+                                       start: &mut usize,
+                                       pending: &mut Option<(H::Cursor, H::Cursor)>,
+                                       done: &mut bool,
+                                       hs: H::Haystack,
+                                       position: H::Cursor) {
+            match *pending {
+                Some((begin, _)) => unsafe {
+                    if H::cursor_cmp(hs, begin, position) != Ordering::Less {
+                        return;
+                    }
+                    let rest = H::range_to_self(hs, position, H::cursor_at_back(hs));
+                    *searcher = pattern.clone().into_searcher(rest);
+                    *start = H::offset_from_start(hs, position);
+                },
+                None if *done => return,
+                None => {}
+            }
+            let sub = searcher.haystack();
+            let start = *start;
+            *pending = searcher.next_match().map(|(begin, end)| unsafe {
+                (parent_cursor::<H>(hs, start, sub, begin), parent_cursor::<H>(hs, start, sub, end))
+            });
+            *done = pending.is_none();
+        }
+    }
+
+    impl<H, A, B> GapSearcher<H> for OrSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        fn gap_haystack(&self) -> H::Haystack {
+            self.haystack
+        }
+
+        fn position(&mut self) -> &mut H::Cursor {
+            &mut self.position
+        }
+
+        fn peek_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let hs = self.haystack;
+            Self::fill(&self.pattern_a, &mut self.a, &mut self.a_start,
+                       &mut self.pending_a, &mut self.a_done, hs, self.position);
+            Self::fill(&self.pattern_b, &mut self.b, &mut self.b_start,
+                       &mut self.pending_b, &mut self.b_done, hs, self.position);
+
+            self.peeked_a = match (self.pending_a, self.pending_b) {
+                (None, None) => return None,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(a), Some(b)) => unsafe {
+                    match H::cursor_cmp(hs, a.0, b.0) {
+                        Ordering::Less => true,
+                        Ordering::Greater => false,
+                        Ordering::Equal => {
+                            H::cursor_cmp(hs, a.1, b.1) != Ordering::Less
+                        }
+                    }
+                },
+            };
+
+            if self.peeked_a { self.pending_a } else { self.pending_b }
+        }
+
+        fn drop_peeked(&mut self) {
+            if self.peeked_a {
+                self.pending_a = None;
+            } else {
+                self.pending_b = None;
+            }
+        }
+    }
+
+    unsafe impl<H, A, B> Searcher<H> for OrSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            gap_next(self)
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_match(self)
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_reject(self)
+        }
+    }
+
+    impl<H, A, B> FusedSearcher<H> for OrSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+              A::Searcher: FusedSearcher<H>,
+              B::Searcher: FusedSearcher<H>,
+    {}
+
+    // The branch of the last match is still the peeked one,
+    // as `next_match` drops it without peeking again.
+    impl<H, A, B> TaggedSearcher<H> for OrSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        fn last_match_id(&self) -> usize {
+            if self.peeked_a { 0 } else { 1 }
+        }
+    }
+
+    impl<H, A, B> Pattern<H> for Or<A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        type Searcher = OrSearcher<H, A, B>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            let a = self.0.clone().into_searcher(haystack);
+            let hs = a.haystack();
+            let (b, position) = unsafe {
+                let front = H::cursor_at_front(hs);
+                let back = H::cursor_at_back(hs);
+                (self.1.clone().into_searcher(H::range_to_self(hs, front, back)), front)
+            };
+
+            OrSearcher {
+                haystack: hs,
+                a,
+                b,
+                a_start: 0,
+                b_start: 0,
+                pattern_a: self.0,
+                pattern_b: self.1,
+                pending_a: None,
+                pending_b: None,
+                a_done: false,
+                b_done: false,
+                peeked_a: false,
+                position,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            first_match_at_front(self.into_searcher(haystack))
+        }
+
+        fn is_suffix_of(self, haystack: H) -> bool
+            where Self::Searcher: ReverseSearcher<H> {
+            last_match_at_back(self.into_searcher(haystack))
+        }
+    }
+
+    // Matches an `A` match that is immediately followed by a `B` match,
+    // yielding both as one combined range.
+    //
+    // Candidates are the matches of `A`. For each of them `B` is checked
+    // right at its end, through `is_prefix_of` on the rest of the haystack.
+    // If `B` does not match there, `A` is started over one char behind the
+    // begin of the candidate, so candidates overlapping it are tried as
+    // well. Starting the searchers on sub-haystacks requires both patterns
+    // to be `Clone`, and their cursors are mapped back to the haystack
+    // through their offsets.
+    // Rejects are the gaps between the yielded matches.
+    #[derive(Clone, Copy)]
+    pub struct Then<A, B>(pub A, pub B);
+
+    #[derive(Clone)]
+    pub struct ThenSearcher<H: SearchPtrs, A: Pattern<H>, B> {
+        haystack: H::Haystack,
+        a: A::Searcher,
+        // Where the sub-haystack of `a` starts
+        a_start: usize,
+        pattern_a: A,
+        b: B,
+        pending: Option<(H::Cursor, H::Cursor)>,
+        position: H::Cursor,
+    }
+
+    impl<H, A, B> ::core::fmt::Debug for ThenSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H>,
+              A::Searcher: ::core::fmt::Debug,
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let hs = self.haystack;
+            let range = |(begin, end)| unsafe { debug_range::<H>(hs, begin, end) };
+            f.debug_struct("ThenSearcher")
+                .field("a", &self.a)
+                .field("pending", &self.pending.map(range))
+                .field("position", &unsafe { H::offset_from_start(hs, self.position) })
+                .finish()
+        }
+    }
+
+    impl<H, A, B> ThenSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+    {
+        // Starts `A` over at `at`.
+        unsafe fn restart_a(&mut self, at: H::Cursor) {
+            let rest = H::range_to_self(self.haystack, at, H::cursor_at_back(self.haystack));
+            self.a = self.pattern_a.clone().into_searcher(rest);
+            self.a_start = H::offset_from_start(self.haystack, at);
+        }
+    }
+
+    impl<H, A, B> GapSearcher<H> for ThenSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        fn gap_haystack(&self) -> H::Haystack {
+            self.haystack
+        }
+
+        fn position(&mut self) -> &mut H::Cursor {
+            &mut self.position
+        }
+
+        fn peek_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.pending.is_some() {
+                return self.pending;
+            }
+
+            let hs = self.haystack;
+            while let Some((a_begin, a_end)) = self.a.next_match() {
+                unsafe {
+                    let sub = self.a.haystack();
+                    let a_begin = parent_cursor::<H>(hs, self.a_start, sub, a_begin);
+                    let a_end = parent_cursor::<H>(hs, self.a_start, sub, a_end);
+                    if H::cursor_cmp(hs, a_begin, self.position) == Ordering::Less {
+                        // Overlaps the previous match, later candidates
+                        // may begin anywhere behind it
+                        let position = self.position;
+                        self.restart_a(position);
+                        continue;
+                    }
+
+                    if let Some((_, b_end)) = match_at(&self.b, hs, a_end) {
+                        self.pending = Some((a_begin, b_end));
+                        return self.pending;
+                    }
+
+                    if H::cursor_cmp(hs, a_begin, H::cursor_at_back(hs)) == Ordering::Equal {
+                        break;
+                    }
+                    self.restart_a(H::cursor_next(hs, a_begin));
+                }
+            }
+            None
+        }
+
+        fn drop_peeked(&mut self) {
+            self.pending = None;
+        }
+    }
+
+    unsafe impl<H, A, B> Searcher<H> for ThenSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            gap_next(self)
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_match(self)
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_reject(self)
+        }
+    }
+
+    impl<H, A, B> FusedSearcher<H> for ThenSearcher<H, A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+              A::Searcher: FusedSearcher<H>,
+    {}
+
+    impl<H, A, B> Pattern<H> for Then<A, B>
+        where H: SearchPtrs,
+              A: Pattern<H> + Clone,
+              B: Pattern<H> + Clone,
+    {
+        type Searcher = ThenSearcher<H, A, B>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            let a = self.0.clone().into_searcher(haystack);
+            let hs = a.haystack();
+            let position = unsafe { H::cursor_at_front(hs) };
+
+            ThenSearcher {
+                haystack: hs,
+                a,
+                a_start: 0,
+                pattern_a: self.0,
+                b: self.1,
+                pending: None,
+                position,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            first_match_at_front(self.into_searcher(haystack))
+        }
+
+        fn is_suffix_of(self, haystack: H) -> bool
+            where Self::Searcher: ReverseSearcher<H> {
+            last_match_at_back(self.into_searcher(haystack))
+        }
+    }
+
+    // Finds all occurrences of a substring, including ones that overlap,
+    // by continuing the search one element behind the begin of each match.
+    // Implemented for the `&str` and `&[u8]` substring patterns.
+    //
+    // As the matches can overlap there are no gaps between them,
+    // so the searchers panic on `next_reject`. For the same reason
+    // the matches in a mutable haystack must not all be turned into
+    // pieces, as those would alias; use their offsets instead.
+    #[derive(Clone, Copy)]
+    pub struct Overlapping<P>(pub P);
+
+    // Matches only if `P` matches at the very front of the haystack,
+    // yielding at most that one match without scanning any further.
+    // Everything behind the match, or the whole haystack if the anchor
+    // fails, is yielded as a single reject.
+    //
+    // Whether there is a match at the front is decided by `is_prefix_of`,
+    // which needs a clone of the pattern. Only if it succeeds the inner
+    // searcher is asked for its first match, which then is the one at
+    // the front.
+    #[derive(Clone, Copy)]
+    pub struct Anchored<P>(pub P);
+
+    #[derive(Clone)]
+    pub struct AnchoredSearcher<H: SearchPtrs, P: Pattern<H>> {
+        inner: P::Searcher,
+        pattern: P,
+        // `None` until the anchor has been checked
+        anchor: Option<Option<(H::Cursor, H::Cursor)>>,
+        position: H::Cursor,
+    }
+
+    impl<H, P> ::core::fmt::Debug for AnchoredSearcher<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ::core::fmt::Debug,
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let hs = self.inner.haystack();
+            let range = |(begin, end)| unsafe { debug_range::<H>(hs, begin, end) };
+            f.debug_struct("AnchoredSearcher")
+                .field("inner", &self.inner)
+                .field("anchor", &self.anchor.map(|anchor| anchor.map(range)))
+                .field("position", &unsafe { H::offset_from_start(hs, self.position) })
+                .finish()
+        }
+    }
+
+    impl<H, P> GapSearcher<H> for AnchoredSearcher<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H> + Clone,
+    {
+        fn gap_haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn position(&mut self) -> &mut H::Cursor {
+            &mut self.position
+        }
+
+        fn peek_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.anchor.is_none() {
+                let hs = self.inner.haystack();
+                let at_front = unsafe {
+                    let whole = H::range_to_self(hs, H::cursor_at_front(hs), H::cursor_at_back(hs));
+                    self.pattern.clone().is_prefix_of(whole)
+                };
+                self.anchor = Some(if at_front { self.inner.next_match() } else { None });
+            }
+            self.anchor.unwrap()
+        }
+
+        fn drop_peeked(&mut self) {
+            self.anchor = Some(None);
+        }
+    }
+
+    unsafe impl<H, P> Searcher<H> for AnchoredSearcher<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H> + Clone,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            gap_next(self)
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_match(self)
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_reject(self)
+        }
+    }
+
+    impl<H, P> FusedSearcher<H> for AnchoredSearcher<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H> + Clone,
+              P::Searcher: FusedSearcher<H>,
+    {}
+
+    impl<H, P> Pattern<H> for Anchored<P>
+        where H: SearchPtrs,
+              P: Pattern<H> + Clone,
+    {
+        type Searcher = AnchoredSearcher<H, P>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            let inner = self.0.clone().into_searcher(haystack);
+            let position = unsafe { H::cursor_at_front(inner.haystack()) };
+
+            AnchoredSearcher {
+                inner,
+                pattern: self.0,
+                anchor: None,
+                position,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            self.0.is_prefix_of(haystack)
+        }
+
+        fn is_suffix_of(self, haystack: H) -> bool
+            where Self::Searcher: ReverseSearcher<H> {
+            last_match_at_back(self.into_searcher(haystack))
+        }
+
+        fn is_contained_in(self, haystack: H) -> bool {
+            self.is_prefix_of(haystack)
+        }
+    }
+
+    // Matches only if `P` matches at the very back of the haystack,
+    // the counterpart to `Anchored`. The inner searcher is only asked for
+    // its last match, and everything in front of it, or the whole haystack
+    // if the anchor fails, is yielded as a single reject.
+    #[derive(Clone, Copy)]
+    pub struct AnchoredEnd<P>(pub P);
+
+    #[derive(Clone)]
+    pub struct AnchoredEndSearcher<H: SearchPtrs, S> {
+        inner: S,
+        // `None` until the inner searcher has been asked for its last match
+        anchor: Option<Option<(H::Cursor, H::Cursor)>>,
+        // The not yet yielded range, followed by the anchored match if any
+        front: H::Cursor,
+        back: H::Cursor,
+    }
+
+    impl<H, S> ::core::fmt::Debug for AnchoredEndSearcher<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H> + ::core::fmt::Debug,
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let hs = self.inner.haystack();
+            let range = |(begin, end)| unsafe { debug_range::<H>(hs, begin, end) };
+            f.debug_struct("AnchoredEndSearcher")
+                .field("inner", &self.inner)
+                .field("anchor", &self.anchor.map(|anchor| anchor.map(range)))
+                .field("remaining", &range((self.front, self.back)))
+                .finish()
+        }
+    }
+
+    impl<H, S> AnchoredEndSearcher<H, S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn peek_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.anchor.is_none() {
+                self.anchor = Some(match_at_back(&mut self.inner));
+            }
+            self.anchor.unwrap()
+        }
+
+        fn take_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let m = self.peek_match()?;
+            self.anchor = Some(None);
+            self.back = m.0;
+            Some(m)
+        }
+
+        fn is_empty(&self) -> bool {
+            let hs = self.inner.haystack();
+            unsafe { H::cursor_cmp(hs, self.front, self.back) == Ordering::Equal }
+        }
+    }
+
+    unsafe impl<H, S> Searcher<H> for AnchoredEndSearcher<H, S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            let hs = self.inner.haystack();
+            let p = self.front;
+            let limit = match self.peek_match() {
+                Some((begin, end)) => {
+                    if unsafe { H::cursor_cmp(hs, p, begin) } == Ordering::Equal {
+                        self.take_match();
+                        return SearchStep::Match(begin, end);
+                    }
+                    begin
+                }
+                None => {
+                    if self.is_empty() {
+                        return SearchStep::Done;
+                    }
+                    self.back
+                }
+            };
+            self.front = limit;
+            SearchStep::Reject(p, limit)
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.take_match()
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let limit = match self.peek_match() {
+                Some((begin, _)) => begin,
+                None => self.back,
+            };
+            if unsafe {
+                H::cursor_cmp(self.inner.haystack(), self.front, limit)
+            } == Ordering::Equal {
+                // Only the match is left, which rejects skip over
+                self.take_match();
+                self.front = self.back;
+                return None;
+            }
+
+            let p = self.front;
+            self.front = limit;
+            Some((p, limit))
+        }
+    }
+
+    impl<H, S> FusedSearcher<H> for AnchoredEndSearcher<H, S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H> + FusedSearcher<H>,
+    {}
+
+    unsafe impl<H, S> ReverseSearcher<H> for AnchoredEndSearcher<H, S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.take_match()
+        }
+
+        fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.take_match();
+            if self.is_empty() {
+                return None;
+            }
+
+            let p = self.back;
+            self.back = self.front;
+            Some((self.front, p))
+        }
+    }
+
+    impl<H, P> Pattern<H> for AnchoredEnd<P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        type Searcher = AnchoredEndSearcher<H, P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            let inner = self.0.into_searcher(haystack);
+            let hs = inner.haystack();
+            let (front, back) = unsafe {
+                (H::cursor_at_front(hs), H::cursor_at_back(hs))
+            };
+
+            AnchoredEndSearcher {
+                inner,
+                anchor: None,
+                front,
+                back,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            first_match_at_front(self.into_searcher(haystack))
+        }
+
+        fn is_suffix_of(self, haystack: H) -> bool
+            where Self::Searcher: ReverseSearcher<H> {
+            self.is_contained_in(haystack)
+        }
+    }
+
+    // Matches maximal runs of adjacent `P` matches as a single range,
+    // eg. `Repeat(Ascii::new(b' ').unwrap())` matches `"   "` once instead of three times.
+    // Rejects are likewise the maximal gaps between the runs.
+    #[derive(Clone, Copy)]
+    pub struct Repeat<P>(pub P);
+
+    #[derive(Clone)]
+    pub struct RepeatSearcher<H: SearchPtrs, S> {
+        inner: S,
+        // Lookahead match of the inner searcher that ended the last run
+        pending: Option<(H::Cursor, H::Cursor)>,
+        run: Option<(H::Cursor, H::Cursor)>,
+        position: H::Cursor,
+    }
+
+    impl<H, S> ::core::fmt::Debug for RepeatSearcher<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H> + ::core::fmt::Debug,
+    {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            let hs = self.inner.haystack();
+            let range = |(begin, end)| unsafe { debug_range::<H>(hs, begin, end) };
+            f.debug_struct("RepeatSearcher")
+                .field("inner", &self.inner)
+                .field("pending", &self.pending.map(range))
+                .field("run", &self.run.map(range))
+                .field("position", &unsafe { H::offset_from_start(hs, self.position) })
+                .finish()
+        }
+    }
+
+    impl<H, S> GapSearcher<H> for RepeatSearcher<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn gap_haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn position(&mut self) -> &mut H::Cursor {
+            &mut self.position
+        }
+
+        fn peek_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.run.is_some() {
+                return self.run;
+            }
+
+            let hs = self.inner.haystack();
+            let (begin, mut end) = match self.pending.take() {
+                Some(m) => m,
+                None => self.inner.next_match()?,
+            };
+            while let Some((b, e)) = self.inner.next_match() {
+                if unsafe { H::cursor_cmp(hs, b, end) } == Ordering::Equal {
+                    end = e;
+                } else {
+                    self.pending = Some((b, e));
+                    break;
+                }
+            }
+
+            self.run = Some((begin, end));
+            self.run
+        }
+
+        fn drop_peeked(&mut self) {
+            self.run = None;
+        }
+    }
+
+    unsafe impl<H, S> Searcher<H> for RepeatSearcher<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            gap_next(self)
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_match(self)
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            gap_next_reject(self)
+        }
+    }
+
+    impl<H, S> FusedSearcher<H> for RepeatSearcher<H, S>
+        where H: SearchPtrs,
+              S: FusedSearcher<H>,
+    {}
+
+    impl<H, P> Pattern<H> for Repeat<P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        type Searcher = RepeatSearcher<H, P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            let inner = self.0.into_searcher(haystack);
+            let position = unsafe { H::cursor_at_front(inner.haystack()) };
+
+            RepeatSearcher {
+                inner,
+                pending: None,
+                run: None,
+                position,
+            }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            self.0.is_prefix_of(haystack)
+        }
+
+        fn is_suffix_of(self, haystack: H) -> bool
+            where Self::Searcher: ReverseSearcher<H> {
+            last_match_at_back(self.into_searcher(haystack))
+        }
+    }
+
+    // A haystack wrapper whose cursors are plain offsets instead of
+    // pointers, for consumers that only want positions. The searchers
+    // are those of the wrapped haystack with their cursors converted,
+    // but pieces are created by safe indexing, so this also serves as
+    // a reference to test the pointer based impls against.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Indexed<H>(pub H);
+
+    impl<'a> SearchPtrs for Indexed<&'a str> {
+        type Haystack = &'a str;
+        type Cursor = usize;
+
+        unsafe fn offset_from_start(_: &'a str, begin: usize) -> usize {
+            begin
+        }
+
+        unsafe fn range_to_self(hs: &'a str, start: usize, end: usize) -> Self {
+            Indexed(&hs[start..end])
+        }
+        unsafe fn cursor_at_front(_: &'a str) -> usize {
+            0
+        }
+        unsafe fn cursor_at_back(hs: &'a str) -> usize {
+            hs.len()
+        }
+        unsafe fn cursor_cmp(_: &'a str, a: usize, b: usize) -> Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: &'a str, a: usize, b: usize) -> usize {
+            b - a
+        }
+        unsafe fn haystack_len(hs: &'a str) -> usize {
+            hs.len()
+        }
+        unsafe fn offset_from_end(hs: &'a str, cursor: usize) -> usize {
+            hs.len() - cursor
+        }
+        unsafe fn cursor_at_offset(_: &'a str, n: usize) -> usize {
+            n
+        }
+        unsafe fn cursor_advance(hs: &'a str, cursor: usize, n: usize) -> usize {
+            assert!(hs.is_char_boundary(cursor + n), "cursor is not at a char boundary");
+            cursor + n
+        }
+        unsafe fn cursor_next(hs: &'a str, cursor: usize) -> usize {
+            cursor + hs[cursor..].chars().next().unwrap().len_utf8()
+        }
+    }
+
+    impl<'a, T> SearchPtrs for Indexed<&'a [T]> {
+        type Haystack = &'a [T];
+        type Cursor = usize;
+
+        unsafe fn offset_from_start(_: &'a [T], begin: usize) -> usize {
+            begin
+        }
+
+        unsafe fn range_to_self(hs: &'a [T], start: usize, end: usize) -> Self {
+            Indexed(&hs[start..end])
+        }
+        unsafe fn cursor_at_front(_: &'a [T]) -> usize {
+            0
+        }
+        unsafe fn cursor_at_back(hs: &'a [T]) -> usize {
+            hs.len()
+        }
+        unsafe fn cursor_cmp(_: &'a [T], a: usize, b: usize) -> Ordering {
+            a.cmp(&b)
+        }
+        unsafe fn cursor_distance(_: &'a [T], a: usize, b: usize) -> usize {
+            b - a
+        }
+        unsafe fn haystack_len(hs: &'a [T]) -> usize {
+            hs.len()
+        }
+        unsafe fn offset_from_end(hs: &'a [T], cursor: usize) -> usize {
+            hs.len() - cursor
+        }
+        unsafe fn cursor_at_offset(_: &'a [T], n: usize) -> usize {
+            n
+        }
+        unsafe fn cursor_advance(_: &'a [T], cursor: usize, n: usize) -> usize {
+            cursor + n
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct IndexedSearcher<H, S> {
+        haystack: H,
+        inner: S,
+    }
+
+    // The inner searcher runs on the same haystack, so its offsets
+    // are the cursors of this one.
+    impl<H, S: ::core::fmt::Debug> ::core::fmt::Debug for IndexedSearcher<H, S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_tuple("IndexedSearcher").field(&self.inner).finish()
+        }
+    }
+
+    impl<H, S> IndexedSearcher<H, S> where H: SearchPtrs, S: Searcher<H> {
+        fn to_offsets(&self, range: (H::Cursor, H::Cursor)) -> (usize, usize) {
+            let hs = self.inner.haystack();
+            unsafe {
+                (H::offset_from_start(hs, range.0), H::offset_from_start(hs, range.1))
+            }
+        }
+    }
+
+    unsafe impl<H, S> Searcher<Indexed<H>> for IndexedSearcher<H, S>
+        where H: SearchPtrs + Copy,
+              Indexed<H>: SearchPtrs<Haystack = H, Cursor = usize>,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<usize> {
+            let hs = self.inner.haystack();
+            self.inner.next().map(|c| unsafe { H::offset_from_start(hs, c) })
+        }
+
+        fn next_match(&mut self) -> Option<(usize, usize)> {
+            self.inner.next_match().map(|range| self.to_offsets(range))
+        }
+
+        fn next_reject(&mut self) -> Option<(usize, usize)> {
+            self.inner.next_reject().map(|range| self.to_offsets(range))
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            self.inner.match_bounds()
+        }
+    }
+
+    impl<H, S> FusedSearcher<Indexed<H>> for IndexedSearcher<H, S>
+        where H: SearchPtrs + Copy,
+              Indexed<H>: SearchPtrs<Haystack = H, Cursor = usize>,
+              S: FusedSearcher<H>,
+    {}
+
+    unsafe impl<H, S> ReverseSearcher<Indexed<H>> for IndexedSearcher<H, S>
+        where H: SearchPtrs + Copy,
+              Indexed<H>: SearchPtrs<Haystack = H, Cursor = usize>,
+              S: ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<(usize, usize)> {
+            self.inner.next_match_back().map(|range| self.to_offsets(range))
+        }
+
+        fn next_reject_back(&mut self) -> Option<(usize, usize)> {
+            self.inner.next_reject_back().map(|range| self.to_offsets(range))
+        }
+    }
+
+    impl<H, S> DoubleEndedSearcher<Indexed<H>> for IndexedSearcher<H, S>
+        where H: SearchPtrs + Copy,
+              Indexed<H>: SearchPtrs<Haystack = H, Cursor = usize>,
+              S: DoubleEndedSearcher<H> {}
+
+    // Forwards the patterns for a haystack type to its `Indexed` wrapper.
+    //
+    // A single blanket impl over all patterns would overlap with the impls
+    // of the combinators in this module, which are generic over the
+    // haystack, so the base patterns are listed one by one.
+    // The brackets hold the generics of the impl besides `'a`.
+    macro_rules! impl_indexed_pattern {
+        ($hs:ty => $([$($gen:tt)*] $ty:ty),* $(,)*) => {$(
+            impl<'a, $($gen)*> Pattern<Indexed<$hs>> for $ty
+                where $ty: Pattern<$hs>
+            {
+                type Searcher = IndexedSearcher<$hs, <$ty as Pattern<$hs>>::Searcher>;
+
+                fn into_searcher(self, haystack: Indexed<$hs>) -> Self::Searcher {
+                    IndexedSearcher {
+                        haystack: haystack.0,
+                        inner: <$ty as Pattern<$hs>>::into_searcher(self, haystack.0),
+                    }
+                }
+
+                fn is_prefix_of(self, haystack: Indexed<$hs>) -> bool {
+                    <$ty as Pattern<$hs>>::is_prefix_of(self, haystack.0)
+                }
+
+                fn is_suffix_of(self, haystack: Indexed<$hs>) -> bool
+                    where Self::Searcher: ReverseSearcher<Indexed<$hs>> {
+                    last_match_at_back(
+                        <$ty as Pattern<Indexed<$hs>>>::into_searcher(self, haystack))
+                }
+            }
+        )*}
+    }
+
+    impl_indexed_pattern! { &'a str =>
+        [] string::Ascii,
+        [] u8,
+        [] string::AsciiNoCase,
+        [] string::AsciiSet,
+        [] string::Ascii2,
+        [] string::Ascii3,
+        ['b] &'b str,
+        ['b] &'b [u8],
+        ['b] Overlapping<&'b str>,
+        ['b] string::NoCaseAscii<'b>,
+        ['b] &'b [char],
+        [F: FnMut(char) -> bool] F,
+        [] string::classes::Alpha,
+        [] string::classes::Digit,
+        [] string::classes::AlphaNum,
+        [] string::classes::HexDigit,
+        [] string::CharRange,
+        [] string::Whitespace,
+        [] string::DigitRun,
+        [] string::LineTerminator,
+    }
+
+    #[cfg(feature = "alloc")]
+    impl_indexed_pattern! { &'a str =>
+        ['b] &'b ::alloc::string::String,
+        [] ::alloc::string::String,
+        ['b] string::Glob<'b>,
+        ['b] string::MultiStr<'b>,
+    }
+
+    impl_indexed_pattern! { &'a [u8] =>
+        [] slice::Ascii,
+        [F: FnMut(u8) -> bool] F,
+    }
+
+    impl_indexed_pattern! { &'a [u16] =>
+        [] utf16::Unit,
+        ['b] &'b [u16],
+        [] char,
+    }
+
+    impl_indexed_pattern! { &'a [T] =>
+        [T: PartialEq] generic_slice::Elem<T>,
+    }
+
+    impl_pattern_for_ref! {
+        [P] Not<P>,
+        [A, B] Or<A, B>,
+        [A, B] Then<A, B>,
+        [P] Anchored<P>,
+        [P] AnchoredEnd<P>,
+        [P] Overlapping<P>,
+        [P] Repeat<P>,
+    }
+}
+
+// Bridges between the v4 and the v5 design, for the haystacks both of
+// them support. `V4AsV5` runs a v4 pattern through the v5 API and
+// `V5AsV4` a v5 pattern through the v4 one.
+//
+// v5 is strictly more expressive. `V4AsV5` loses nothing, it only has to
+// synthesize the steps of `next` from the matches, since a v4 searcher
+// can't report matches and rejects in order. `V5AsV4` drops everything
+// v4 has no way to express: the steps, the `match_bounds`, the bounds of
+// the haystack and, for `&mut [u8]`, the position of every cursor but the
+// ones yielded last, since the v4 `Start` is just their offset.
+pub mod v4_bridge {
+    use super::*;
+    use core::cmp::Ordering;
+
+    // Both designs name their cursor type `Cursor`
+    type Cursor<H> = <H as SearchPtrs>::Cursor;
+    type V4Cursor<H> = <H as ::v4::SearchCursor>::Cursor;
+
+    // A haystack of both designs, along with the mapping between their
+    // cursors. A v4 cursor is a whole range, a v5 cursor just one end.
+    pub trait Bridged: SearchPtrs + ::v4::SearchCursor {
+        // Splits the haystack into the v5 bounds and the haystack to hand
+        // to a v4 searcher.
+        fn split(self) -> (<Self as SearchPtrs>::Haystack, Self);
+
+        fn from_v4(hs: <Self as SearchPtrs>::Haystack,
+                   range: <Self as ::v4::SearchCursor>::Cursor)
+                   -> (<Self as SearchPtrs>::Cursor, <Self as SearchPtrs>::Cursor);
+
+        fn to_v4(begin: <Self as SearchPtrs>::Cursor,
+                 end: <Self as SearchPtrs>::Cursor)
+                 -> <Self as ::v4::SearchCursor>::Cursor;
+
+        // The v4 `Start` for the range beginning at `last`, which has to be
+        // the one yielded last.
+        fn v4_start(hs: <Self as SearchPtrs>::Haystack,
+                    last: <Self as SearchPtrs>::Cursor)
+                    -> <Self as ::v4::SearchCursor>::Start;
+    }
+
+    impl Bridged for &str {
+        fn split(self) -> ((*const u8, *const u8), Self) {
+            let begin = self.as_ptr();
+            ((begin, begin.wrapping_add(self.len())), self)
+        }
+
+        fn from_v4(_: (*const u8, *const u8),
+                   range: (*const u8, *const u8)) -> (*const u8, *const u8) {
+            range
+        }
+
+        fn to_v4(begin: *const u8, end: *const u8) -> (*const u8, *const u8) {
+            (begin, end)
+        }
+
+        // The front locates every cursor, not just the last one
+        fn v4_start(hs: (*const u8, *const u8), _: *const u8) -> *const u8 {
+            hs.0
+        }
+    }
+
+    impl Bridged for &mut [u8] {
+        // Both halves come from the same pointer, so the cursors of the v4
+        // searcher can be rebased onto the bounds, which the v5 consumers
+        // derive the pieces from.
+        fn split(self) -> ((*mut u8, *mut u8), Self) {
+            let len = self.len();
+            let begin = self.as_mut_ptr();
+            unsafe {
+                ((begin, begin.add(len)), ::core::slice::from_raw_parts_mut(begin, len))
+            }
+        }
+
+        fn from_v4(hs: (*mut u8, *mut u8),
+                   (begin, end): (*mut u8, *mut u8)) -> (*mut u8, *mut u8) {
+            (hs.0.wrapping_add(begin as usize - hs.0 as usize),
+             hs.0.wrapping_add(end as usize - hs.0 as usize))
+        }
+
+        fn to_v4(begin: *mut u8, end: *mut u8) -> (*mut u8, *mut u8) {
+            (begin, end)
+        }
+
+        fn v4_start(hs: (*mut u8, *mut u8), last: *mut u8) -> usize {
+            last as usize - hs.0 as usize + 1
+        }
+    }
+
+    // Uses a v4 pattern as a v5 one.
+    pub struct V4AsV5<P>(pub P);
+
+    pub struct V4AsV5Searcher<H: SearchPtrs, S> {
+        haystack: H::Haystack,
+        inner: S,
+        // The part not covered by steps or matches yet
+        front: Cursor<H>,
+        back: Cursor<H>,
+        // A match found behind a gap, yielded after the reject for the gap
+        pending: Option<(Cursor<H>, Cursor<H>)>,
+    }
+
+    impl<H: SearchPtrs, S: ::core::fmt::Debug> ::core::fmt::Debug for V4AsV5Searcher<H, S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_tuple("V4AsV5Searcher").field(&self.inner).finish()
+        }
+    }
+
+    impl<H: Bridged, S> V4AsV5Searcher<H, S> {
+        fn range(&self, range: Option<V4Cursor<H>>)
+                 -> Option<(Cursor<H>, Cursor<H>)> {
+            range.map(|range| H::from_v4(self.haystack, range))
+        }
+    }
+
+    unsafe impl<H, S> Searcher<H> for V4AsV5Searcher<H, S>
+        where H: Bridged,
+              S: ::v4::Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.haystack
+        }
+
+        fn next(&mut self) -> SearchStep<Cursor<H>> {
+            if let Some((begin, end)) = self.pending.take() {
+                self.front = end;
+                return SearchStep::Match(begin, end);
+            }
+            let m = self.inner.next_match();
+            match self.range(m) {
+                Some((begin, end)) => {
+                    if unsafe { H::cursor_cmp(self.haystack, begin, self.front) }
+                        == Ordering::Equal {
+                        self.front = end;
+                        return SearchStep::Match(begin, end);
+                    }
+                    let gap = SearchStep::Reject(self.front, begin);
+                    self.front = begin;
+                    self.pending = Some((begin, end));
+                    gap
+                }
+                None => {
+                    if unsafe { H::cursor_cmp(self.haystack, self.front, self.back) }
+                        == Ordering::Equal {
+                        return SearchStep::Done;
+                    }
+                    let gap = SearchStep::Reject(self.front, self.back);
+                    self.front = self.back;
+                    gap
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(Cursor<H>, Cursor<H>)> {
+            let m = match self.pending.take() {
+                Some(m) => Some(m),
+                None => {
+                    let m = self.inner.next_match();
+                    self.range(m)
+                }
+            };
+            self.front = m.map_or(self.back, |(_, end)| end);
+            m
+        }
+
+        fn next_reject(&mut self) -> Option<(Cursor<H>, Cursor<H>)> {
+            // A pending match lies in front of anything the inner
+            // searcher could still reject
+            self.pending = None;
+            let r = self.inner.next_reject();
+            let r = self.range(r);
+            self.front = r.map_or(self.back, |(_, end)| end);
+            r
+        }
+    }
+
+    unsafe impl<H, S> ReverseSearcher<H> for V4AsV5Searcher<H, S>
+        where H: Bridged,
+              S: ::v4::ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<(Cursor<H>, Cursor<H>)> {
+            let m = self.inner.next_match_back();
+            let m = self.range(m).or_else(|| self.pending.take());
+            self.back = m.map_or(self.front, |(begin, _)| begin);
+            m
+        }
+
+        fn next_reject_back(&mut self) -> Option<(Cursor<H>, Cursor<H>)> {
+            let r = self.inner.next_reject_back();
+            let r = self.range(r);
+            if let Some((begin, _)) = r {
+                self.back = begin;
+            }
+            r
+        }
+    }
+
+    impl<H, S> DoubleEndedSearcher<H> for V4AsV5Searcher<H, S>
+        where H: Bridged,
+              S: ::v4::DoubleEndedSearcher<H>,
+    {}
+
+    impl<H, P> Pattern<H> for V4AsV5<P>
+        where H: Bridged,
+              P: ::v4::Pattern<H>,
+    {
+        type Searcher = V4AsV5Searcher<H, P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            let (hs, haystack) = haystack.split();
+            unsafe {
+                V4AsV5Searcher {
+                    haystack: hs,
+                    inner: self.0.into_searcher(haystack),
+                    front: H::cursor_at_front(hs),
+                    back: H::cursor_at_back(hs),
+                    pending: None,
+                }
+            }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            self.0.is_prefix_of(haystack)
+        }
+    }
+
+    // Uses a v5 pattern as a v4 one.
+    pub struct V5AsV4<P>(pub P);
+
+    pub struct V5AsV4Searcher<H: SearchPtrs, S> {
+        inner: S,
+        // The begin of the range yielded last, which the v4 `Start` of
+        // `&mut [u8]` has to describe
+        last: Cursor<H>,
+    }
+
+    impl<H: SearchPtrs, S: ::core::fmt::Debug> ::core::fmt::Debug for V5AsV4Searcher<H, S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_tuple("V5AsV4Searcher").field(&self.inner).finish()
+        }
+    }
+
+    impl<H: Bridged, S> V5AsV4Searcher<H, S> {
+        fn range(&mut self, range: Option<(Cursor<H>, Cursor<H>)>)
+                 -> Option<V4Cursor<H>> {
+            range.map(|(begin, end)| {
+                self.last = begin;
+                H::to_v4(begin, end)
+            })
+        }
+    }
+
+    unsafe impl<H, S> ::v4::Searcher<H> for V5AsV4Searcher<H, S>
+        where H: Bridged,
+              S: Searcher<H>,
+    {
+        fn haystack_start(&self) -> <H as ::v4::SearchCursor>::Start {
+            H::v4_start(self.inner.haystack(), self.last)
+        }
+
+        fn next_match(&mut self) -> Option<V4Cursor<H>> {
+            let m = self.inner.next_match();
+            self.range(m)
+        }
+
+        fn next_reject(&mut self) -> Option<V4Cursor<H>> {
+            let r = self.inner.next_reject();
+            self.range(r)
+        }
+    }
+
+    unsafe impl<H, S> ::v4::ReverseSearcher<H> for V5AsV4Searcher<H, S>
+        where H: Bridged,
+              S: ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<V4Cursor<H>> {
+            let m = self.inner.next_match_back();
+            self.range(m)
+        }
+
+        fn next_reject_back(&mut self) -> Option<V4Cursor<H>> {
+            let r = self.inner.next_reject_back();
+            self.range(r)
+        }
+    }
+
+    impl<H, S> ::v4::DoubleEndedSearcher<H> for V5AsV4Searcher<H, S>
+        where H: Bridged,
+              S: DoubleEndedSearcher<H>,
+    {}
+
+    impl<H, P> ::v4::Pattern<H> for V5AsV4<P>
+        where H: Bridged,
+              P: Pattern<H>,
+    {
+        type Searcher = V5AsV4Searcher<H, P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            let inner = self.0.into_searcher(haystack);
+            let last = unsafe { H::cursor_at_front(inner.haystack()) };
+            V5AsV4Searcher { inner, last }
+        }
+
+        fn is_prefix_of(self, haystack: H) -> bool {
+            self.0.is_prefix_of(haystack)
+        }
+
+        fn is_suffix_of(self, haystack: H) -> bool
+            where Self::Searcher: ::v4::ReverseSearcher<H>
+        {
+            let mut searcher = ::v4::Pattern::into_searcher(self, haystack);
+            let hs = searcher.inner.haystack();
+            ::v4::ReverseSearcher::next_match_back(&mut searcher)
+                .is_some_and(|range| unsafe {
+                    H::offset_from_end(hs, H::from_v4(hs, range).1) == 0
+                })
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_bridge_match_indices() {
+        use super::api_consumer::match_indices;
+        use ::v4::api_consumer::match_indices as v4_match_indices;
+
+        for &haystack in &["", "a", "banana", "xaay", "éaé"] {
+            let expected = match_indices(haystack, string::Ascii::new(b'a').unwrap());
+            assert_eq!(v4_match_indices(haystack, ::v4::string::Ascii(b'a')), expected);
+            assert_eq!(match_indices(haystack, V4AsV5(::v4::string::Ascii(b'a'))), expected);
+            assert_eq!(v4_match_indices(haystack, V5AsV4(string::Ascii::new(b'a').unwrap())),
+                       expected);
+            assert_eq!(match_indices(haystack, V4AsV5(V5AsV4("an"))),
+                       match_indices(haystack, "an"));
+        }
+
+        let slice = &mut {*b"banana"}[..];
+
+        {
+            let match_indices = match_indices(&mut*slice, V4AsV5(::v4::slice::Ascii(b'a')));
+
+            assert_eq!(match_indices.iter().map(|x| x.0).collect::<Vec<_>>(),
+                       vec![1, 3, 5]);
+
+            for m in match_indices {
+                m.1[0] = b'i';
+            }
+        }
+
+        assert_eq!(slice, b"binini");
+
+        {
+            let match_indices = v4_match_indices(&mut*slice, V5AsV4(slice::Ascii(b'i')));
+
+            assert_eq!(match_indices.iter().map(|x| x.0).collect::<Vec<_>>(),
+                       vec![1, 3, 5]);
+
+            for m in match_indices {
+                m.1[0] = b'o';
+            }
+        }
+
+        assert_eq!(slice, b"bonono");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_bridge_rmatch_indices() {
+        use ::v4::api_consumer::rmatch_indices;
+
+        for &haystack in &["", "a", "banana", "xaay", "éaé"] {
+            let expected = rmatch_indices(haystack, ::v4::string::Ascii(b'a'));
+            assert_eq!(rmatch_indices(haystack, V5AsV4(string::Ascii::new(b'a').unwrap())),
+                       expected);
+            assert_eq!(rmatch_indices(haystack, V5AsV4(V4AsV5(::v4::string::Ascii(b'a')))),
+                       expected);
+        }
+        assert!(::v4::Pattern::is_suffix_of(V5AsV4(string::Ascii::new(b'a').unwrap()), "banana"));
+        assert!(!::v4::Pattern::is_suffix_of(V5AsV4(string::Ascii::new(b'n').unwrap()), "banana"));
+        assert!(V4AsV5(::v4::string::Ascii(b'b')).is_prefix_of("banana"));
+
+        let slice = &mut {*b"banana"}[..];
+
+        {
+            let match_indices = rmatch_indices(&mut*slice, V5AsV4(slice::Ascii(b'a')));
+
+            assert_eq!(match_indices.iter().map(|x| x.0).collect::<Vec<_>>(),
+                       vec![5, 3, 1]);
+
+            for m in match_indices {
+                m.1[0] = b'o';
+            }
+        }
+
+        assert_eq!(slice, b"bonono");
+
+        // Steps synthesized from the v4 matches, and the back bounding
+        // the last gap
+        let mut searcher = V4AsV5(::v4::slice::Ascii(b'o')).into_searcher(&mut*slice);
+        let hs = searcher.haystack();
+        let offsets = |range: Option<(*mut u8, *mut u8)>| range.map(|(begin, end)| unsafe {
+            (<&mut [u8]>::offset_from_start(hs, begin), <&mut [u8]>::offset_from_start(hs, end))
+        });
+        assert_eq!(offsets(searcher.next_match_back()), Some((5, 6)));
+        let steps = ::std::iter::from_fn(|| match searcher.next() {
+            SearchStep::Match(begin, end) => Some((true, offsets(Some((begin, end))).unwrap())),
+            SearchStep::Reject(begin, end) => Some((false, offsets(Some((begin, end))).unwrap())),
+            SearchStep::Done => None,
+        }).collect::<Vec<_>>();
+        assert_eq!(steps, vec![(false, (0, 1)), (true, (1, 2)), (false, (2, 3)),
+                               (true, (3, 4)), (false, (4, 5))]);
+    }
+}
+
+pub mod api_consumer {
+    use super::*;
+    use core::cmp::Ordering;
+    use core::ops::{ControlFlow, Range};
+    #[cfg(feature = "alloc")]
+    use alloc::string::String;
+    #[cfg(feature = "alloc")]
+    use alloc::vec::Vec;
+
+    // Checks that a searcher makes progress: every match has to begin
+    // strictly behind the begin of the previous one.
+    // Otherwise consumers like `split` would loop forever.
+    fn debug_assert_advances<H>(haystack: H::Haystack,
+                                last: Option<(H::Cursor, H::Cursor)>,
+                                next: (H::Cursor, H::Cursor))
+        where H: SearchPtrs,
+    {
+        if let Some((last_begin, _)) = last {
+            unsafe {
+                debug_assert!(H::cursor_cmp(haystack, next.0, last_begin)
+                              == Ordering::Greater,
+                              "searcher did not advance");
+            }
+        }
+    }
+
+    // Creates the pieces for the ranges only once the searcher is done.
+    // For mutable haystacks each piece is a unique borrow of its range,
+    // which the searcher must not read anymore afterwards, while some
+    // searchers look at data they already yielded, like `Then` does.
+    #[cfg(feature = "alloc")]
+    fn ranges_to_pieces<H>(haystack: H::Haystack,
+                           ranges: Vec<(H::Cursor, H::Cursor)>) -> Vec<H>
+        where H: SearchPtrs,
+    {
+        ranges.into_iter()
+            .map(|(begin, end)| unsafe { H::range_to_self(haystack, begin, end) })
+            .collect()
+    }
+
+    // Forwards to `inner` and, with debug assertions, panics as soon as
+    // it yields a range that lies outside the haystack, ends in front of
+    // its begin, or begins in front of the end of the range the same end
+    // yielded before. Meant for trying out hand-written searchers.
+    //
+    // Matches, rejects and steps share one bound per end, so this also
+    // catches mixed calls going backwards. Overlapping matches fail it.
+    pub struct CheckedSearcher<H: SearchPtrs, S> {
+        inner: S,
+        // The end of the last range from the front
+        front: H::Cursor,
+        // The begin of the last range from the back
+        back: H::Cursor,
+    }
+
+    impl<H: SearchPtrs, S: Searcher<H>> CheckedSearcher<H, S> {
+        pub fn new(inner: S) -> Self {
+            let hs = inner.haystack();
+            let (front, back) = unsafe { (H::cursor_at_front(hs), H::cursor_at_back(hs)) };
+            CheckedSearcher { inner, front, back }
+        }
+
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+
+        fn check(&self, (begin, end): (H::Cursor, H::Cursor)) {
+            let hs = self.inner.haystack();
+            unsafe {
+                let (front, back) = (H::cursor_at_front(hs), H::cursor_at_back(hs));
+                debug_assert!(H::cursor_cmp(hs, front, begin) != Ordering::Greater &&
+                              H::cursor_cmp(hs, end, back) != Ordering::Greater,
+                              "searcher yielded a range outside the haystack");
+                debug_assert!(H::cursor_cmp(hs, begin, end) != Ordering::Greater,
+                              "searcher yielded an inverted range");
+            }
+        }
+
+        fn check_front(&mut self, range: Option<(H::Cursor, H::Cursor)>)
+            -> Option<(H::Cursor, H::Cursor)>
+        {
+            if let Some((begin, end)) = range {
+                self.check((begin, end));
+                debug_assert!(unsafe {
+                    H::cursor_cmp(self.inner.haystack(), self.front, begin)
+                } != Ordering::Greater, "searcher went backwards");
+                self.front = end;
+            }
+            range
+        }
+
+        fn check_back(&mut self, range: Option<(H::Cursor, H::Cursor)>)
+            -> Option<(H::Cursor, H::Cursor)>
+        {
+            if let Some((begin, end)) = range {
+                self.check((begin, end));
+                debug_assert!(unsafe {
+                    H::cursor_cmp(self.inner.haystack(), end, self.back)
+                } != Ordering::Greater, "searcher went forwards from the back");
+                self.back = begin;
+            }
+            range
+        }
+    }
+
+    impl<H: SearchPtrs, S: Clone> Clone for CheckedSearcher<H, S> {
+        fn clone(&self) -> Self {
+            CheckedSearcher { inner: self.inner.clone(), front: self.front, back: self.back }
+        }
+    }
+
+    impl<H: SearchPtrs, S: ::core::fmt::Debug> ::core::fmt::Debug for CheckedSearcher<H, S> {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            f.debug_tuple("CheckedSearcher").field(&self.inner).finish()
+        }
+    }
+
+    // `try_fold_matches` is left to the default, so the matches it
+    // passes on are checked as well.
+    unsafe impl<H, S> Searcher<H> for CheckedSearcher<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn position(&self) -> H::Cursor {
+            self.inner.position()
+        }
+
+        fn skip_to(&mut self, cursor: H::Cursor) {
+            self.inner.skip_to(cursor);
+            self.front = cursor;
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            let step = self.inner.next();
+            if let SearchStep::Match(begin, end) | SearchStep::Reject(begin, end) = step {
+                self.check_front(Some((begin, end)));
+            }
+            step
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let m = self.inner.next_match();
+            self.check_front(m)
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let r = self.inner.next_reject();
+            self.check_front(r)
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            self.inner.match_bounds()
+        }
+    }
+
+    impl<H, S> FusedSearcher<H> for CheckedSearcher<H, S>
+        where H: SearchPtrs,
+              S: FusedSearcher<H>,
+    {}
+
+    unsafe impl<H, S> ReverseSearcher<H> for CheckedSearcher<H, S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let m = self.inner.next_match_back();
+            self.check_back(m)
+        }
+
+        fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let r = self.inner.next_reject_back();
+            self.check_back(r)
+        }
+    }
+
+    impl<H, S> DoubleEndedSearcher<H> for CheckedSearcher<H, S>
+        where H: SearchPtrs,
+              S: DoubleEndedSearcher<H>,
+    {}
+
+    impl<H, S> TaggedSearcher<H> for CheckedSearcher<H, S>
+        where H: SearchPtrs,
+              S: TaggedSearcher<H>,
+    {
+        fn last_match_id(&self) -> usize {
+            self.inner.last_match_id()
+        }
+    }
+
+    // Yields every `a` from the front, but forgets to move on
+    // behind the first one.
+    #[cfg(test)]
+    struct StuckSearcher<'a>(&'a str);
+
+    #[cfg(test)]
+    unsafe impl<'a> Searcher<&'a str> for StuckSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            let range = self.0.as_bytes().as_ptr_range();
+            (range.start, range.end)
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            match self.0.find('a') {
+                Some(i) => {
+                    let m = &self.0.as_bytes()[i..i + 1];
+                    let range = m.as_ptr_range();
+                    SearchStep::Match(range.start, range.end)
+                }
+                None => SearchStep::Done,
+            }
+        }
+    }
+
+    #[test]
+    fn test_checked_searcher() {
+        let a = string::Ascii::new(b'a').unwrap();
+        let mut searcher = CheckedSearcher::new(a.into_searcher("banana"));
+        assert!(searcher.next_match_back().is_some());
+        assert!(searcher.next_reject().is_some());
+        assert_eq!(matches_iter(searcher).count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "searcher went backwards")]
+    fn test_checked_searcher_stuck() {
+        let mut searcher = CheckedSearcher::new(StuckSearcher("banana"));
+        assert!(searcher.next_match().is_some());
+        searcher.next_match();
+    }
+
+    // Makes any searcher a `FusedSearcher`, by remembering when either
+    // end ran out and not asking the inner searcher at that end anymore.
+    #[derive(Clone, Debug)]
+    pub struct Fuse<S> {
+        inner: S,
+        front_done: bool,
+        back_done: bool,
+    }
+
+    impl<S> Fuse<S> {
+        pub fn new(inner: S) -> Self {
+            Fuse { inner, front_done: false, back_done: false }
+        }
+
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+    }
+
+    unsafe impl<H, S> Searcher<H> for Fuse<S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn position(&self) -> H::Cursor {
+            self.inner.position()
+        }
+
+        fn skip_to(&mut self, cursor: H::Cursor) {
+            if !self.front_done {
+                self.inner.skip_to(cursor)
+            }
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            if self.front_done {
+                return SearchStep::Done;
+            }
+            let step = self.inner.next();
+            self.front_done = matches!(step, SearchStep::Done);
+            step
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.front_done {
+                return None;
+            }
+            let m = self.inner.next_match();
+            self.front_done = m.is_none();
+            m
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.front_done {
+                return None;
+            }
+            let r = self.inner.next_reject();
+            self.front_done = r.is_none();
+            r
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            if self.front_done {
+                (0, Some(0))
+            } else {
+                self.inner.match_bounds()
+            }
+        }
+    }
+
+    unsafe impl<H, S> ReverseSearcher<H> for Fuse<S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.back_done {
+                return None;
+            }
+            let m = self.inner.next_match_back();
+            self.back_done = m.is_none();
+            m
+        }
+
+        fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.back_done {
+                return None;
+            }
+            let r = self.inner.next_reject_back();
+            self.back_done = r.is_none();
+            r
+        }
+    }
+
+    impl<H, S> DoubleEndedSearcher<H> for Fuse<S>
+        where H: SearchPtrs,
+              S: DoubleEndedSearcher<H>,
+    {}
+
+    impl<H, S> FusedSearcher<H> for Fuse<S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {}
+
+    impl<H, S> TaggedSearcher<H> for Fuse<S>
+        where H: SearchPtrs,
+              S: TaggedSearcher<H>,
+    {
+        fn last_match_id(&self) -> usize {
+            self.inner.last_match_id()
+        }
+    }
+
+    // Yields an `a` on every other call, also after running out once.
+    #[cfg(test)]
+    struct Flickering<'a> {
+        haystack: &'a str,
+        calls: usize,
+    }
+
+    #[cfg(test)]
+    unsafe impl<'a> Searcher<&'a str> for Flickering<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            let range = self.haystack.as_bytes().as_ptr_range();
+            (range.start, range.end)
+        }
+
+        fn next(&mut self) -> SearchStep<*const u8> {
+            self.calls += 1;
+            match self.haystack.find('a') {
+                Some(i) if self.calls % 2 == 1 => {
+                    let range = self.haystack.as_bytes()[i..i + 1].as_ptr_range();
+                    SearchStep::Match(range.start, range.end)
+                }
+                _ => SearchStep::Done,
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuse() {
+        fn poll<H: SearchPtrs, S: FusedSearcher<H>>(searcher: &mut S) {
+            for _ in 0..3 {
+                assert!(searcher.next_match().is_none());
+                assert!(searcher.next_reject().is_none());
+                assert!(matches!(searcher.next(), SearchStep::Done));
+            }
+        }
+
+        let a = string::Ascii::new(b'a').unwrap();
+        let mut searcher = a.into_searcher("banana");
+        while searcher.next_match().is_some() {}
+        poll(&mut searcher);
+        let mut searcher = "an".into_searcher("banana");
+        while searcher.next_reject().is_some() {}
+        poll(&mut searcher);
+
+        let mut searcher = Flickering { haystack: "banana", calls: 0 };
+        assert!(searcher.next_match().is_some());
+        assert!(searcher.next_match().is_none());
+        assert!(searcher.next_match().is_some());
+
+        let mut searcher = Fuse::new(Flickering { haystack: "banana", calls: 0 });
+        assert!(searcher.next_match().is_some());
+        assert!(searcher.next_match().is_none());
+        poll(&mut searcher);
+        assert_eq!(searcher.match_bounds(), (0, Some(0)));
+    }
+
+    // Test support for the `DoubleEndedSearcher` contract: forwards to
+    // `inner` and panics as soon as a match yielded from either end
+    // differs from the one a forward-only search yields at that position.
+    // `reference` has to be an identical searcher, it's run forwards to
+    // completion to learn the expected matches.
+    #[cfg(test)]
+    pub struct CheckedDoubleEnded<H: SearchPtrs, S> {
+        inner: S,
+        expected: Vec<(H::Cursor, H::Cursor)>,
+        // The part of `expected` not yielded yet
+        front: usize,
+        back: usize,
+    }
+
+    #[cfg(test)]
+    impl<H: SearchPtrs, S: ReverseSearcher<H>> CheckedDoubleEnded<H, S> {
+        pub fn new(inner: S, mut reference: S) -> Self {
+            let mut expected = vec![];
+            while let Some(m) = reference.next_match() {
+                expected.push(m);
+            }
+            let back = expected.len();
+            CheckedDoubleEnded { inner, expected, front: 0, back }
+        }
+
+        fn check(&self,
+                 yielded: Option<(H::Cursor, H::Cursor)>,
+                 index: Option<usize>) {
+            let haystack = self.inner.haystack();
+            let same = match (yielded, index.map(|i| self.expected[i])) {
+                (None, None) => true,
+                (Some(a), Some(b)) => unsafe {
+                    H::cursor_cmp(haystack, a.0, b.0) == Ordering::Equal &&
+                        H::cursor_cmp(haystack, a.1, b.1) == Ordering::Equal
+                },
+                _ => false,
+            };
+            assert!(same, "searcher is not double ended");
+        }
+    }
+
+    // Rejects and the steps of `next` are passed through unchecked.
+    #[cfg(test)]
+    unsafe impl<H, S> Searcher<H> for CheckedDoubleEnded<H, S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.inner.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            self.inner.next()
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let m = self.inner.next_match();
+            let index = if self.front < self.back {
+                self.front += 1;
+                Some(self.front - 1)
+            } else {
+                None
+            };
+            self.check(m, index);
+            m
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.inner.next_reject()
+        }
+    }
+
+    #[cfg(test)]
+    unsafe impl<H, S> ReverseSearcher<H> for CheckedDoubleEnded<H, S>
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        fn next_match_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let m = self.inner.next_match_back();
+            let index = if self.front < self.back {
+                self.back -= 1;
+                Some(self.back)
+            } else {
+                None
+            };
+            self.check(m, index);
+            m
+        }
+
+        fn next_reject_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.inner.next_reject_back()
+        }
+    }
+
+    // Takes matches alternately from the back and the front.
+    #[cfg(test)]
+    fn check_double_ended<H, S>(inner: S, reference: S)
+        where H: SearchPtrs,
+              S: ReverseSearcher<H>,
+    {
+        let mut checked = CheckedDoubleEnded::new(CheckedSearcher::new(inner),
+                                                  CheckedSearcher::new(reference));
+        // Not `||`, both ends are asked in every round
+        while checked.next_match_back().is_some() | checked.next_match().is_some() {}
+    }
+
+    #[test]
+    fn test_double_ended() {
+        fn check<'a, P>(haystack: &'a str, pattern: P)
+            where P: Pattern<&'a str> + Clone,
+                  P::Searcher: DoubleEndedSearcher<&'a str>,
+        {
+            check_double_ended(pattern.clone().into_searcher(haystack),
+                               pattern.into_searcher(haystack));
+        }
+
+        let haystack = "a,b;c  dé,,ä;";
+        check(haystack, string::Ascii::new(b',').unwrap());
+        check(haystack, string::AsciiSet::new(b",;"));
+        check(haystack, string::Ascii2::new(b',', b';').unwrap());
+        check(haystack, &['é', ','][..]);
+        check(haystack, char::is_alphabetic);
+        check(haystack, string::Whitespace);
+        check("", string::Ascii::new(b',').unwrap());
+
+        let bytes = &b"a,b,,c"[..];
+        check_double_ended(slice::Ascii(b',').into_searcher(bytes),
+                           slice::Ascii(b',').into_searcher(bytes));
+        let numbers = &[1, 2, 1, 1][..];
+        check_double_ended(generic_slice::Elem(1).into_searcher(numbers),
+                           generic_slice::Elem(1).into_searcher(numbers));
+    }
+
+    // A substring searcher that can also search backwards, to show that
+    // substring patterns must not claim `DoubleEndedSearcher`. The needle
+    // must not be empty.
+    #[cfg(test)]
+    struct NaiveStrSearcher<'a> {
+        haystack: &'a str,
+        needle: &'a str,
+        start: usize,
+        end: usize,
+    }
+
+    #[cfg(test)]
+    impl<'a> NaiveStrSearcher<'a> {
+        fn new(haystack: &'a str, needle: &'a str) -> Self {
+            NaiveStrSearcher { haystack, needle, start: 0, end: haystack.len() }
+        }
+
+        fn cursors(&self, begin: usize) -> (*const u8, *const u8) {
+            self.range(begin, begin + self.needle.len())
+        }
+
+        fn range(&self, begin: usize, end: usize) -> (*const u8, *const u8) {
+            let range = self.haystack.as_bytes()[begin..end].as_ptr_range();
+            (range.start, range.end)
+        }
+    }
+
+    #[cfg(test)]
+    unsafe impl<'a> Searcher<&'a str> for NaiveStrSearcher<'a> {
+        fn haystack(&self) -> (*const u8, *const u8) {
+            let range = self.haystack.as_bytes().as_ptr_range();
+            (range.start, range.end)
+        }
+
+        // Rejects everything up to the next match at once
+        fn next(&mut self) -> SearchStep<*const u8> {
+            let begin = self.start;
+            let rest = &self.haystack[begin..self.end];
+            match rest.find(self.needle) {
+                _ if rest.is_empty() => SearchStep::Done,
+                Some(0) => {
+                    self.start += self.needle.len();
+                    let (begin, end) = self.cursors(begin);
+                    SearchStep::Match(begin, end)
+                }
+                found => {
+                    self.start += found.unwrap_or(rest.len());
+                    let (begin, end) = self.range(begin, self.start);
+                    SearchStep::Reject(begin, end)
+                }
+            }
+        }
+
+        fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+            let begin = self.start + self.haystack[self.start..self.end].find(self.needle)?;
+            self.start = begin + self.needle.len();
+            Some(self.cursors(begin))
+        }
+    }
+
+    #[cfg(test)]
+    unsafe impl<'a> ReverseSearcher<&'a str> for NaiveStrSearcher<'a> {
+        fn next_match_back(&mut self) -> Option<(*const u8, *const u8)> {
+            let begin = self.start + self.haystack[self.start..self.end].rfind(self.needle)?;
+            self.end = begin;
+            Some(self.cursors(begin))
+        }
+
+        fn next_reject_back(&mut self) -> Option<(*const u8, *const u8)> {
+            while self.haystack[self.start..self.end].ends_with(self.needle) {
+                self.end -= self.needle.len();
+            }
+            if self.start == self.end {
+                return None;
+            }
+            let end = self.end;
+            self.end = match self.haystack[self.start..end].rfind(self.needle) {
+                Some(i) => self.start + i + self.needle.len(),
+                None => self.start,
+            };
+            Some(self.range(self.end, end))
+        }
+    }
+
+    #[test]
+    fn test_double_ended_substring_without_overlap() {
+        // Without overlapping occurences both directions happen to agree
+        check_double_ended(NaiveStrSearcher::new("ab,ab", "ab"),
+                           NaiveStrSearcher::new("ab,ab", "ab"));
+    }
+
+    #[test]
+    #[should_panic(expected = "searcher is not double ended")]
+    fn test_double_ended_substring() {
+        check_double_ended(NaiveStrSearcher::new("aaa", "aa"),
+                           NaiveStrSearcher::new("aaa", "aa"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_naive_substring_steps() {
+        use self::SearchStep::{Match, Reject};
+
+        let haystack = "xaaab,";
+        let mut searcher = NaiveStrSearcher::new(haystack, "aa");
+        let hs = searcher.haystack();
+        let offset = |cursor| unsafe { <&str>::offset_from_start(hs, cursor) };
+        let mut steps = vec![];
+        loop {
+            match searcher.next().map(offset) {
+                SearchStep::Done => break,
+                step => steps.push(step),
+            }
+        }
+        assert_eq!(steps, vec![Reject(0, 1), Match(1, 3), Reject(3, 6)]);
+
+        // The rejects of both ends disagree just like the matches
+        let mut searcher = NaiveStrSearcher::new(haystack, "aa");
+        let offsets = |(begin, end)| (offset(begin), offset(end));
+        assert_eq!(searcher.next_reject().map(offsets), Some((0, 1)));
+        assert_eq!(searcher.next_reject_back().map(offsets), Some((4, 6)));
+        assert_eq!(searcher.next_reject_back().map(offsets), Some((1, 2)));
+        assert_eq!(searcher.next_reject().map(offsets), None);
+        assert_eq!(searcher.next_reject_back().map(offsets), None);
+    }
+
+    // Yields the cursors of the matches, or of the rejects, of any
+    // searcher, so the combinators of `Iterator` work on them. Going
+    // through `haystack` the cursors can still be turned into pieces.
+    pub struct MatchIter<H: SearchPtrs, S> {
+        searcher: S,
+        rejects: bool,
+        _marker: ::core::marker::PhantomData<H>,
+    }
+
+    pub fn matches_iter<H, S>(searcher: S) -> MatchIter<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        MatchIter { searcher, rejects: false, _marker: ::core::marker::PhantomData }
+    }
+
+    pub fn rejects_iter<H, S>(searcher: S) -> MatchIter<H, S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        MatchIter { searcher, rejects: true, _marker: ::core::marker::PhantomData }
+    }
+
+    impl<H: SearchPtrs, S: Searcher<H>> MatchIter<H, S> {
+        pub fn haystack(&self) -> H::Haystack {
+            self.searcher.haystack()
+        }
+
+        pub fn searcher(&self) -> &S {
+            &self.searcher
+        }
+    }
+
+    impl<H: SearchPtrs, S: Searcher<H>> Iterator for MatchIter<H, S> {
+        type Item = (H::Cursor, H::Cursor);
+
+        fn next(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.rejects {
+                self.searcher.next_reject()
+            } else {
+                self.searcher.next_match()
+            }
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            if self.rejects {
+                (0, None)
+            } else {
+                self.searcher.match_bounds()
+            }
+        }
+
+        // Keeps the tight loop of searchers overriding `try_fold_matches`.
+        fn fold<B, F>(mut self, init: B, mut f: F) -> B
+            where F: FnMut(B, (H::Cursor, H::Cursor)) -> B,
+        {
+            if self.rejects {
+                let mut acc = init;
+                while let Some(r) = self.searcher.next_reject() {
+                    acc = f(acc, r);
+                }
+                return acc;
+            }
+            match self.searcher.try_fold_matches(init, |acc, m| ControlFlow::Continue(f(acc, m))) {
+                ControlFlow::Continue(acc) | ControlFlow::Break(acc) => acc,
+            }
+        }
+    }
+
+    impl<H, S> DoubleEndedIterator for MatchIter<H, S>
+        where H: SearchPtrs,
+              S: DoubleEndedSearcher<H>,
+    {
+        fn next_back(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.rejects {
+                self.searcher.next_reject_back()
+            } else {
+                self.searcher.next_match_back()
+            }
+        }
+    }
+
+    impl<H: SearchPtrs, S: FusedSearcher<H>> ::core::iter::FusedIterator for MatchIter<H, S> {}
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_boxed_searcher() {
+        let haystack = "a,b;c--d,e";
+        let offsets = |searcher: BoxedSearcher<&str>| {
+            let hs = searcher.haystack();
+            matches_iter(searcher)
+                .map(|(begin, _)| unsafe { <&str>::offset_from_start(hs, begin) })
+                .collect::<Vec<_>>()
+        };
+
+        let searchers: Vec<BoxedSearcher<&str>> = vec![
+            string::Ascii::new(b',').unwrap().into_boxed_searcher(haystack),
+            "--".into_boxed_searcher(haystack),
+            (|c: char| c == ';').into_boxed_searcher(haystack),
+        ];
+        assert_eq!(searchers.into_iter().map(offsets).collect::<Vec<_>>(),
+                   vec![vec![1, 8], vec![5], vec![3]]);
+
+        let mut searchers: Vec<BoxedReverseSearcher<&str>> = vec![
+            string::Ascii::new(b',').unwrap().into_boxed_reverse_searcher(haystack),
+            string::CharRange('c'..='d').into_boxed_reverse_searcher(haystack),
+            (|c: char| c == ';').into_boxed_reverse_searcher(haystack),
+        ];
+        let last = searchers.iter_mut().map(|searcher| {
+            let hs = searcher.haystack();
+            searcher.next_match_back()
+                .map(|(begin, _)| unsafe { <&str>::offset_from_start(hs, begin) })
+        }).collect::<Vec<_>>();
+        assert_eq!(last, vec![Some(8), Some(7), Some(3)]);
+
+        // A boxed searcher is a searcher like any other, so it can be
+        // wrapped again
+        let fused = Fuse::new("-".into_boxed_searcher(haystack));
+        assert_eq!(matches_iter(fused).count(), 2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_into_searcher_at() {
+        // Pauses after `n` matches and resumes with a new searcher
+        fn resumed<'a, P>(haystack: &'a str, pattern: P, n: usize) -> Vec<(usize, &'a str)>
+            where P: Pattern<&'a str> + Copy,
+        {
+            let mut searcher = pattern.into_searcher(haystack);
+            for _ in 0..n {
+                searcher.next_match();
+            }
+            let position = searcher.position();
+            drop(searcher);
+
+            let searcher = unsafe { pattern.into_searcher_at(haystack, position) };
+            let hs = searcher.haystack();
+            matches_iter(searcher).map(|(begin, end)| unsafe {
+                (<&str>::offset_from_start(hs, begin), <&str>::range_to_self(hs, begin, end))
+            }).collect()
+        }
+
+        fn check<'a, P>(haystack: &'a str, pattern: P)
+            where P: Pattern<&'a str> + Copy,
+        {
+            let all = match_indices(haystack, pattern);
+            for n in 0..all.len() {
+                assert_eq!(resumed(haystack, pattern, n), all[n..]);
+            }
+        }
+
+        let long = "abcdefghij-abcdefghij-abcdefghij-abcdefghij";
+        for &haystack in &["banana", "", "aaaaaa", "éaéaé", "a-b", long] {
+            check(haystack, string::Ascii::new(b'a').unwrap());
+            for &needle in &["a", "an", "aa", "é", "", "abcdefghij"] {
+                check(haystack, needle);
+            }
+        }
+        assert_eq!(resumed(long, "abcdefghij", 3), vec![(33, "abcdefghij")]);
+        assert_eq!(resumed("banana", "a", 3), vec![]);
+
+        // Patterns that can't resume still start at the front
+        let haystack = "abc";
+        let searcher = unsafe {
+            string::CharRange('b'..='c').into_searcher_at(haystack, haystack.as_ptr())
+        };
+        assert_eq!(matches_iter(searcher).count(), 2);
+
+        let mut buf = *b"banana bandana";
+        let position = {
+            let mut searcher = b"an"[..].into_searcher(&mut buf[..]);
+            for _ in 0..3 {
+                searcher.next_match();
+            }
+            searcher.position()
+        };
+        buf[0] = b'B';
+        let searcher = unsafe { b"an"[..].into_searcher_at(&mut buf[..], position) };
+        let hs = searcher.haystack();
+        for (begin, end) in matches_iter(searcher).collect::<Vec<_>>() {
+            unsafe { <&mut [u8]>::range_to_self(hs, begin, end) }.copy_from_slice(b"AN");
+        }
+        assert_eq!(&buf, b"Banana bandANa");
+
+        let searcher = unsafe { slice::Ascii(b'a').into_searcher_at(&mut buf[..], position) };
+        assert_eq!(matches_iter(searcher).count(), 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_skip_to() {
+        // Splits at the delimiters outside of double quotes. Once a
+        // delimiter turns out to be quoted, the searcher skips behind
+        // the closing quote.
+        fn split_unquoted<'a, S: Searcher<&'a str>>(haystack: &'a str, mut searcher: S)
+            -> Vec<&'a str>
+        {
+            let hs = searcher.haystack();
+            let mut pieces = vec![];
+            let mut piece_start = 0;
+            while let Some((begin, _)) = searcher.next_match() {
+                let offset = unsafe { <&str>::offset_from_start(hs, begin) };
+                if haystack[piece_start..offset].matches('"').count() % 2 == 1 {
+                    match haystack[offset..].find('"') {
+                        Some(quote) => unsafe {
+                            let front = <&str>::cursor_at_front(hs);
+                            searcher.skip_to(<&str>::cursor_advance(hs, front, offset + quote + 1));
+                        },
+                        None => break,
+                    }
+                    continue;
+                }
+                pieces.push(&haystack[piece_start..offset]);
+                piece_start = offset + 1;
+            }
+            pieces.push(&haystack[piece_start..]);
+            pieces
+        }
+
+        // Only forwards the steps, so `skip_to` is left to the default
+        struct Steps<S>(S);
+
+        unsafe impl<H: SearchPtrs, S: Searcher<H>> Searcher<H> for Steps<S> {
+            fn haystack(&self) -> H::Haystack {
+                self.0.haystack()
+            }
+
+            fn next(&mut self) -> SearchStep<H::Cursor> {
+                self.0.next()
+            }
+        }
+
+        let cases: &[(&str, &[&str])] = &[
+            ("a,b,c", &["a", "b", "c"]),
+            (r#"a,"b,c",d"#, &["a", r#""b,c""#, "d"]),
+            (r#""a,,,b","",é,"c,""#, &[r#""a,,,b""#, r#""""#, "é", r#""c,""#]),
+            (r#"a,"b,c"#, &["a", r#""b,c"#]),
+            ("", &[""]),
+        ];
+        let comma = string::Ascii::new(b',').unwrap();
+        for &(haystack, pieces) in cases {
+            assert_eq!(split_unquoted(haystack, comma.into_searcher(haystack)), pieces);
+            assert_eq!(split_unquoted(haystack, Steps(comma.into_searcher(haystack))), pieces);
+            let searcher = Steps((|c| c == ',').into_searcher(haystack));
+            assert_eq!(split_unquoted(haystack, searcher), pieces);
+        }
+
+        // A match that reaches past the cursor is skipped with the steps
+        // in front of it
+        let haystack = "aaaa";
+        let hs = (haystack.as_ptr(), unsafe { haystack.as_ptr().add(4) });
+        let mut searcher = Steps("aa".into_searcher(haystack));
+        searcher.skip_to(unsafe { hs.0.add(1) });
+        assert_eq!(searcher.next_match(), Some(unsafe { (hs.0.add(2), hs.1) }));
+
+        let mut searcher = string::Ascii::new(b'a').unwrap().into_searcher(haystack);
+        searcher.skip_to(unsafe { hs.0.add(3) });
+        assert_eq!(searcher.position(), unsafe { hs.0.add(3) });
+        assert_eq!(matches_iter(searcher).count(), 1);
+    }
+
+    // Only uses what is built without `alloc`, so it also runs with
+    // `--no-default-features`.
+    #[test]
+    fn test_lazy_consumers() {
+        let mut pieces = [""; 4];
+        let mut n = 0;
+        for piece in Split::new("a,b,,c", b',') {
+            pieces[n] = piece;
+            n += 1;
+        }
+        assert_eq!(pieces[..n], ["a", "b", "", "c"]);
+
+        assert!(MatchIndices::new("banana", "an").eq([(1, "an"), (3, "an")]));
+        assert!(matches("banana", string::Ascii::new(b'a').unwrap()).eq(["a"; 3]));
+        assert_eq!(matches_iter(b'-'.into_searcher("a-b")).count(), 1);
+        assert!(lines("a\nb\r\n").eq(["a", "b"]));
+        assert_eq!(find("banana", "na"), Some(2));
+
+        let mut buf = *b"ab,cd";
+        for piece in Split::new(&mut buf[..], b',') {
+            piece[0] = piece[0].to_ascii_uppercase();
+        }
+        assert_eq!(&buf, b"Ab,Cd");
+    }
+
+    #[test]
+    fn test_match_iter() {
+        let a = string::Ascii::new(b'a').unwrap();
+        let it = matches_iter(a.into_searcher("banana"));
+        let hs = it.haystack();
+        let offsets = |(begin, end)| unsafe {
+            (<&str>::offset_from_start(hs, begin), <&str>::offset_from_start(hs, end))
+        };
+
+        assert_eq!(it.take(2).map(offsets).collect::<Vec<_>>(), vec![(1, 2), (3, 4)]);
+        assert_eq!(matches_iter(a.into_searcher("banana")).count(), 3);
+        assert_eq!(rejects_iter(a.into_searcher("banana")).count(), 3);
+        assert_eq!(matches_iter(a.into_searcher("banana")).rev().map(offsets).collect::<Vec<_>>(),
+                   vec![(5, 6), (3, 4), (1, 2)]);
+        assert_eq!(rejects_iter(a.into_searcher("banana")).rev().map(offsets).collect::<Vec<_>>(),
+                   vec![(4, 5), (2, 3), (0, 1)]);
+
+        // Both ends of one searcher meet in the middle
+        let mut it = matches_iter(a.into_searcher("banana"));
+        assert_eq!(it.next_back().map(offsets), Some((5, 6)));
+        assert_eq!(it.next().map(offsets), Some((1, 2)));
+        assert_eq!(it.map(offsets).collect::<Vec<_>>(), vec![(3, 4)]);
+
+        let it = matches_iter(a.into_searcher("banana"));
+        let hs = it.haystack();
+        let pieces = it.map(|(begin, end)| unsafe { <&str>::range_to_self(hs, begin, end) });
+        assert_eq!(pieces.collect::<Vec<_>>(), vec!["a"; 3]);
+    }
+
+    // Lazily yields the matches as pieces of the haystack.
+    //
+    // Unlike the consumers collecting into a `Vec`, this creates each piece
+    // while the searcher keeps going. That is fine for mutable haystacks
+    // since matches never overlap, as long as the searcher doesn't read
+    // what it already yielded, which `Then` for example does.
+    pub struct Matches<H: SearchPtrs, P: Pattern<H>> {
+        searcher: P::Searcher,
+        haystack: H::Haystack,
+        last: Option<(H::Cursor, H::Cursor)>,
+    }
+
+    pub fn matches<H, P>(haystack: H, pattern: P) -> Matches<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        Matches { searcher, haystack, last: None }
+    }
+
+    impl<H: SearchPtrs, P: Pattern<H>> Iterator for Matches<H, P> {
+        type Item = H;
+
+        fn next(&mut self) -> Option<H> {
+            let (begin, end) = self.searcher.next_match()?;
+            debug_assert_advances::<H>(self.haystack, self.last, (begin, end));
+            self.last = Some((begin, end));
+            Some(unsafe { H::range_to_self(self.haystack, begin, end) })
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            self.searcher.match_bounds()
+        }
+    }
+
+    impl<H, P> DoubleEndedIterator for Matches<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: DoubleEndedSearcher<H>,
+    {
+        fn next_back(&mut self) -> Option<H> {
+            let (begin, end) = self.searcher.next_match_back()?;
+            Some(unsafe { H::range_to_self(self.haystack, begin, end) })
+        }
+    }
+
+    impl<H, P> ::core::iter::FusedIterator for Matches<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: FusedSearcher<H>,
+    {}
+
+    #[test]
+    fn test_matches() {
+        let a = string::Ascii::new(b'a').unwrap();
+        assert_eq!(matches("banana", a).collect::<Vec<_>>(), vec!["a"; 3]);
+        assert_eq!(matches("banana", "an").take(1).count(), 1);
+        assert_eq!(matches("", a).next(), None);
+
+        let mut it = matches("a1b22c333", char::is_numeric);
+        assert_eq!(it.next(), Some("1"));
+        assert_eq!(it.next_back(), Some("3"));
+        assert_eq!(it.rev().collect::<Vec<_>>(), vec!["3", "3", "2", "2"]);
+
+        // Pieces from both ends stay usable while searching goes on
+        let mut bytes = *b"xaxaxax";
+        {
+            let mut it = matches(&mut bytes[..], slice::Ascii(b'x'));
+            let first = it.next().unwrap();
+            first[0] = b'1';
+            let last = it.next_back().unwrap();
+            last[0] = b'4';
+            for (i, piece) in it.enumerate() {
+                piece[0] = b'2' + i as u8;
+            }
+            first[0] = b'0';
+        }
+        assert_eq!(&bytes, b"0a2a3a4");
+    }
+
+    // Lazily yields the matches together with their offsets. The same
+    // caveat as for `Matches` applies to mutable haystacks.
+    pub struct MatchIndices<H: SearchPtrs, P: Pattern<H>> {
+        searcher: P::Searcher,
+        haystack: H::Haystack,
+        last: Option<(H::Cursor, H::Cursor)>,
+    }
+
+    impl<H: SearchPtrs, P: Pattern<H>> MatchIndices<H, P> {
+        pub fn new(haystack: H, pattern: P) -> Self {
+            let searcher = pattern.into_searcher(haystack);
+            let haystack = searcher.haystack();
+            MatchIndices { searcher, haystack, last: None }
+        }
+
+        fn next_range(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let (begin, end) = self.searcher.next_match()?;
+            debug_assert_advances::<H>(self.haystack, self.last, (begin, end));
+            self.last = Some((begin, end));
+            Some((begin, end))
+        }
+    }
+
+    impl<H: SearchPtrs, P: Pattern<H>> Iterator for MatchIndices<H, P> {
+        type Item = (usize, H);
+
+        fn next(&mut self) -> Option<(usize, H)> {
+            let (begin, end) = self.next_range()?;
+            unsafe {
+                Some((H::offset_from_start(self.haystack, begin),
+                      H::range_to_self(self.haystack, begin, end)))
+            }
+        }
+
+        // Every match begins behind the previous one, so there can't be
+        // more matches left than positions behind the last begin. Before
+        // the first match the front counts too, for the empty needle.
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            let back = unsafe { H::cursor_at_back(self.haystack) };
+            let remaining = unsafe {
+                match self.last {
+                    Some((begin, _)) => H::cursor_distance(self.haystack, begin, back),
+                    None => {
+                        let front = H::cursor_at_front(self.haystack);
+                        H::cursor_distance(self.haystack, front, back) + 1
+                    }
+                }
+            };
+            let (lower, upper) = self.searcher.match_bounds();
+            (lower, Some(upper.map_or(remaining, |upper| upper.min(remaining))))
+        }
+    }
+
+    impl<H, P> ::core::iter::FusedIterator for MatchIndices<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: FusedSearcher<H>,
+    {}
+
+    // Collects the matches like `MatchIndices`, but through
+    // `try_fold_matches`, creating the pieces once the searcher is done,
+    // see `ranges_to_pieces`.
+    #[cfg(feature = "alloc")]
+    pub fn match_indices<H, P>(haystack: H, pattern: P) -> Vec<(usize, H)>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let (lower, _) = searcher.match_bounds();
+        let mut offsets = Vec::with_capacity(lower);
+        let mut ranges = Vec::with_capacity(lower);
+
+        let _ = searcher.try_fold_matches(None, |last, (begin, end)| {
+            debug_assert_advances::<H>(haystack, last, (begin, end));
+            offsets.push(unsafe { H::offset_from_start(haystack, begin) });
+            ranges.push((begin, end));
+            ControlFlow::Continue(Some((begin, end)))
+        });
+
+        offsets.into_iter()
+            .zip(ranges_to_pieces::<H>(haystack, ranges))
+            .collect()
+    }
+
+    #[test]
+    fn test_match_indices_lazy() {
+        use std::cell::Cell;
+
+        let a = string::Ascii::new(b'a').unwrap();
+        assert_eq!(MatchIndices::new("banana", a).nth(1), Some((3, "a")));
+
+        // The search stops right behind the second match
+        let calls = Cell::new(0);
+        let mut it = MatchIndices::new("banana", |c: char| {
+            calls.set(calls.get() + 1);
+            c == 'a'
+        });
+        assert_eq!(it.nth(1), Some((3, "a")));
+        assert_eq!(calls.get(), 4);
+
+        // The remaining bytes bound the matches of `a` more tightly
+        let mut it = MatchIndices::new("banana", a);
+        assert_eq!(it.size_hint(), (0, Some(6)));
+        it.next();
+        assert_eq!(it.size_hint(), (0, Some(4)));
+        assert_eq!(it.by_ref().count(), 2);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+
+        // Bounds the empty needle, which matches once more than there are chars
+        let it = MatchIndices::new("ab", "");
+        let upper = it.size_hint().1.unwrap();
+        assert!(it.count() <= upper);
+
+        let mut bytes = *b"banana";
+        for (i, piece) in MatchIndices::new(&mut bytes[..], slice::Ascii(b'a')) {
+            piece[0] = b'0' + i as u8;
+        }
+        assert_eq!(&bytes, b"b1n3n5");
+    }
+
+    // The matches within `range` of the haystack with their offsets in
+    // the whole haystack. The window is searched as a haystack of its own,
+    // so matches reaching past its ends are not found. The pattern is
+    // cloned, as the bounds of the haystack are only known to a searcher.
+    //
+    // Panics like slicing if the range is out of bounds, or if it would
+    // split a char of a string haystack.
+    #[cfg(feature = "alloc")]
+    pub fn search_range<H, P>(haystack: H, range: Range<usize>, pattern: P) -> Vec<(usize, H)>
+        where H: SearchPtrs,
+              P: Pattern<H> + Clone,
+    {
+        let searcher = pattern.clone().into_searcher(haystack);
+        let haystack = searcher.haystack();
+
+        let window = unsafe {
+            let len = H::haystack_len(haystack);
+            assert!(range.start <= range.end,
+                    "range starts at {} but ends at {}", range.start, range.end);
+            assert!(range.end <= len,
+                    "range end {} out of range for haystack of length {}", range.end, len);
+
+            // Advancing by nothing checks that the start is a boundary,
+            // which `cursor_at_offset` leaves to its caller
+            let start = H::cursor_advance(haystack, H::cursor_at_offset(haystack, range.start), 0);
+            let end = H::cursor_advance(haystack, start, range.end - range.start);
+            H::range_to_self(haystack, start, end)
+        };
+
+        match_indices(window, pattern).into_iter()
+            .map(|(i, piece)| (range.start + i, piece))
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_search_range() {
+        use self::chain::Chain;
+        use self::generic::Indexed;
+
+        let haystack = "aa-aa-aa";
+        assert_eq!(search_range(haystack, 0..8, "aa"), match_indices(haystack, "aa"));
+        assert_eq!(search_range(haystack, 3..5, "aa"), vec![(3, "aa")]);
+        assert_eq!(search_range(haystack, 2..7, "aa"), vec![(3, "aa")]);
+        assert_eq!(search_range(haystack, 4..8, "aa"), vec![(6, "aa")]);
+        assert_eq!(search_range(haystack, 1..5, "aa"), vec![(3, "aa")]);
+        assert_eq!(search_range(haystack, 4..5, "aa"), vec![]);
+        assert_eq!(search_range(haystack, 8..8, "aa"), vec![]);
+        assert_eq!(search_range(haystack, 2..2, ""), vec![(2, "")]);
+
+        let l = string::Ascii::new(b'l').unwrap();
+        assert_eq!(search_range("héllo wörld", 4..12, l), vec![(4, "l"), (11, "l")]);
+        assert_eq!(search_range("héllo wörld", 3..10, "o"), vec![(5, "o")]);
+
+        let bytes = b"aa-aa-aa";
+        assert_eq!(search_range(&bytes[..], 2..7, slice::Ascii(b'a')),
+                   vec![(3, &b"a"[..]), (4, &b"a"[..]), (6, &b"a"[..])]);
+        assert_eq!(search_range(Indexed(haystack), 2..7, "aa"),
+                   vec![(3, Indexed("aa"))]);
+
+        // The window may cross the seam of a chain
+        assert_eq!(search_range(Chain("xa", "ay"), 1..3, "aa"), vec![(1, Chain("a", "a"))]);
+        assert_eq!(search_range(Chain("xa", "ay"), 2..4, "aa"), vec![]);
+
+        let mut bytes = *b"aa-aa-aa";
+        for (_, piece) in search_range(&mut bytes[..], 1..7, slice::Ascii(b'a')) {
+            piece[0] = b'b';
+        }
+        assert_eq!(&bytes, b"ab-bb-ba");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "char boundary")]
+    fn test_search_range_char_boundary() {
+        search_range("héllo", 2..4, "l");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_search_range_out_of_bounds() {
+        search_range("hello", 2..6, "l");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "cursor doesn't belong to this chain")]
+    fn test_chain_foreign_cursor() {
+        use self::chain::Chain;
+
+        let s = "xxab";
+        let haystack = Chain(&s[..2], &s[2..]);
+        let sub = unsafe {
+            Chain::range_to_self(haystack, Chain::cursor_at_offset(haystack, 3),
+                                 Chain::cursor_at_back(haystack))
+        };
+        unsafe { Chain::offset_from_start(haystack, Chain::cursor_at_front(sub)) };
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_haystack_len() {
+        use self::c_string::CStrSlice;
+        use self::chain::Chain;
+        use self::generic::Indexed;
+        use self::segments::Segments;
+        use std::ffi::{CStr, OsStr};
+
+        // The length of the haystack and the distance between
+        // the begins of the first two matches
+        fn measure<H, P>(haystack: H, pattern: P) -> (usize, Option<usize>)
+            where H: SearchPtrs,
+                  P: Pattern<H>,
+        {
+            let mut searcher = pattern.into_searcher(haystack);
+            let hs = searcher.haystack();
+            let first = searcher.next_match();
+            let second = searcher.next_match();
+            unsafe {
+                let len = H::haystack_len(hs);
+                assert_eq!(len, H::cursor_distance(hs, H::cursor_at_front(hs),
+                                                   H::cursor_at_back(hs)));
+                (len, first.and_then(|a| second.map(|b| H::cursor_distance(hs, a.0, b.0))))
+            }
+        }
+
+        // String-like haystacks count bytes
+        let comma = string::Ascii::new(b',').unwrap();
+        assert_eq!(measure("é,ü,", ","), (6, Some(3)));
+        assert_eq!(measure(Indexed("é,ü,"), ","), (6, Some(3)));
+        let mut owned = String::from("é,ü,");
+        assert_eq!(measure(&mut owned[..], comma), (6, Some(3)));
+        assert_eq!(measure(OsStr::new("é,ü,"), OsStr::new(",")), (6, Some(3)));
+        let c = CStr::from_bytes_with_nul("é,ü,\0".as_bytes()).unwrap();
+        assert_eq!(measure(CStrSlice::new(c), slice::Ascii(b',')), (6, Some(3)));
+        assert_eq!(measure(Chain("é,", "ü,"), ","), (6, Some(3)));
+        let list = ["é", ",ü", ","];
+        assert_eq!(measure(Segments::new(&list), ","), (6, Some(3)));
+        assert_eq!(measure("", ","), (0, None));
+
+        // Slices count elements, whatever their size
+        let chars: Vec<char> = "é,ü,".chars().collect();
+        assert_eq!(measure(&chars[..], ','), (4, Some(2)));
+        let units: Vec<u16> = "é,ü,".encode_utf16().collect();
+        assert_eq!(measure(&units[..], ','), (4, Some(2)));
+        assert_eq!(measure(&[1u32, 0, 0, 1][..], generic_slice::Elem(1)), (4, Some(3)));
+        assert_eq!(measure(&[(), (), ()][..], generic_slice::Elem(())), (3, Some(1)));
+        let mut bytes = *b",a,";
+        assert_eq!(measure(&mut bytes[..], slice::Ascii(b',')), (3, Some(2)));
+        assert_eq!(measure(Indexed(&b",a,"[..]), slice::Ascii(b',')), (3, Some(2)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cursor_at_offset() {
+        use self::c_string::CStrSlice;
+        use self::chain::Chain;
+        use self::generic::Indexed;
+        use self::segments::Segments;
+        use std::ffi::{CStr, OsStr};
+
+        // Re-creates the cursors of all steps from their offsets
+        fn round_trip<H, P>(haystack: H, pattern: P)
+            where H: SearchPtrs,
+                  P: Pattern<H>,
+        {
+            use self::SearchStep::{Match, Reject};
+
+            let mut searcher = pattern.into_searcher(haystack);
+            let hs = searcher.haystack();
+            let mut cursors = unsafe { vec![H::cursor_at_front(hs), H::cursor_at_back(hs)] };
+            while let Match(begin, end) | Reject(begin, end) = searcher.next() {
+                cursors.push(begin);
+                cursors.push(end);
+            }
+            for &c in &cursors {
+                unsafe {
+                    let offset = H::offset_from_start(hs, c);
+                    let anchored = H::cursor_at_offset(hs, offset);
+                    assert_eq!(H::cursor_cmp(hs, anchored, c), Ordering::Equal);
+                    assert_eq!(offset + H::offset_from_end(hs, c), H::haystack_len(hs));
+                }
+            }
+
+            // Stepping with `cursor_next` passes every step boundary
+            let mut stops = vec![];
+            unsafe {
+                let back = H::cursor_at_back(hs);
+                let mut c = H::cursor_at_front(hs);
+                while H::cursor_cmp(hs, c, back) == Ordering::Less {
+                    stops.push(H::offset_from_start(hs, c));
+                    c = H::cursor_next(hs, c);
+                }
+                stops.push(H::offset_from_start(hs, c));
+            }
+            for &c in &cursors {
+                assert!(stops.contains(&unsafe { H::offset_from_start(hs, c) }));
+            }
+        }
+
+        let comma = string::Ascii::new(b',').unwrap();
+        round_trip("é,ü,", ",");
+        round_trip("é,ü,", comma);
+        round_trip("", comma);
+        round_trip(Indexed("é,ü,"), ",");
+        let mut owned = String::from("é,ü,");
+        round_trip(&mut owned[..], comma);
+        round_trip(OsStr::new("é,ü,"), OsStr::new(","));
+        let c = CStr::from_bytes_with_nul("é,ü,\0".as_bytes()).unwrap();
+        round_trip(CStrSlice::new(c), slice::Ascii(b','));
+        round_trip(Chain("é,", "ü,"), ",");
+        let list = ["é", ",ü", ","];
+        round_trip(Segments::new(&list), ",");
+
+        let chars: Vec<char> = "é,ü,".chars().collect();
+        round_trip(&chars[..], ',');
+        round_trip(&[(), (), ()][..], generic_slice::Elem(()));
+        let mut bytes = *b",a,";
+        round_trip(&mut bytes[..], slice::Ascii(b','));
+        round_trip(Indexed(&b",a,"[..]), slice::Ascii(b','));
+
+        // Back-side cursors give their offsets from the back directly
+        let haystack = "ab,cd";
+        let mut searcher = comma.into_searcher(haystack);
+        let hs = searcher.haystack();
+        let (begin, end) = searcher.next_match_back().unwrap();
+        unsafe {
+            assert_eq!(<&str>::offset_from_end(hs, begin), 3);
+            assert_eq!(<&str>::offset_from_end(hs, end), 2);
+            assert_eq!(<&str>::cursor_at_offset(hs, 3), end);
+        }
+    }
+
+    // A match together with its offsets, which count elements of the
+    // haystack like those of `match_ranges`.
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct Match<H> {
+        start: usize,
+        end: usize,
+        value: H,
+    }
+
+    impl<H> Match<H> {
+        pub fn start(&self) -> usize {
+            self.start
+        }
+
+        pub fn end(&self) -> usize {
+            self.end
+        }
+
+        pub fn range(&self) -> Range<usize> {
+            self.start..self.end
+        }
+
+        pub fn value(&self) -> &H {
+            &self.value
+        }
+
+        pub fn into_value(self) -> H {
+            self.value
+        }
+    }
+
+    // Like `match_indices`, but with the end offset of every match.
+    #[cfg(feature = "alloc")]
+    pub fn match_details<H, P>(haystack: H, pattern: P) -> Vec<Match<H>>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut match_indices = MatchIndices::new(haystack, pattern);
+        let haystack = match_indices.haystack;
+        let (lower, _) = match_indices.searcher.match_bounds();
+        let mut offsets = Vec::with_capacity(lower);
+        let mut ranges = Vec::with_capacity(lower);
+
+        while let Some((begin, end)) = match_indices.next_range() {
+            unsafe {
+                offsets.push((H::offset_from_start(haystack, begin),
+                              H::offset_from_start(haystack, end)));
+            }
+            ranges.push((begin, end));
+        }
+
+        offsets.into_iter()
+            .zip(ranges_to_pieces::<H>(haystack, ranges))
+            .map(|((start, end), value)| Match { start, end, value })
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_match_details() {
+        use self::segments::Segments;
+
+        let haystack = "grüne Äpfel, grüne Birnen";
+        let matches = match_details(haystack, "grüne");
+        assert_eq!(matches.iter().map(Match::range).collect::<Vec<_>>(),
+                   match_ranges(haystack, "grüne"));
+        for m in &matches {
+            assert_eq!(m.end() - m.start(), m.value().len());
+            assert_eq!(&haystack[m.range()], *m.value());
+        }
+        for m in match_details(haystack, char::is_uppercase) {
+            assert_eq!(m.end() - m.start(), m.value().len());
+        }
+
+        let units: Vec<u16> = "aüb€€".encode_utf16().collect();
+        for m in match_details(&units[..], '€') {
+            assert_eq!(m.end() - m.start(), m.value().len());
+        }
+
+        let list = ["gr", "ün", "e"];
+        let m = match_details(Segments::new(&list), "üne");
+        assert_eq!(m.len(), 1);
+        assert_eq!((m[0].start(), m[0].end()), (2, 6));
+        assert_eq!(m[0].value().len(), 4);
+
+        let mut bytes = *b"a,,b";
+        for m in match_details(&mut bytes[..], b',') {
+            assert_eq!(m.end() - m.start(), m.value().len());
+            m.into_value()[0] = b';';
+        }
+        assert_eq!(&bytes, b"a;;b");
+        assert_eq!(match_details("", "x"), vec![]);
+    }
+
+    // Like `match_indices`, with the id of the alternative that matched
+    // as the second field, see `TaggedSearcher`.
+    #[cfg(feature = "alloc")]
+    pub fn match_indices_tagged<H, P>(haystack: H, pattern: P) -> Vec<(usize, usize, H)>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: TaggedSearcher<H>,
+    {
+        let mut matches = matches_iter(pattern.into_searcher(haystack));
+        let haystack = matches.haystack();
+        let (lower, _) = matches.size_hint();
+        let mut tags = Vec::with_capacity(lower);
+        let mut ranges = Vec::with_capacity(lower);
+        let mut last = None;
+
+        while let Some((begin, end)) = matches.next() {
+            debug_assert_advances::<H>(haystack, last, (begin, end));
+            last = Some((begin, end));
+            let offset = unsafe { H::offset_from_start(haystack, begin) };
+            tags.push((offset, matches.searcher().last_match_id()));
+            ranges.push((begin, end));
+        }
+
+        tags.into_iter()
+            .zip(ranges_to_pieces::<H>(haystack, ranges))
+            .map(|((offset, id), piece)| (offset, id, piece))
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_match_indices_tagged() {
+        use self::generic::Or;
+        use self::string::MultiStr;
+
+        assert_eq!(match_indices_tagged("abb", MultiStr(&["ab", "b"])),
+                   vec![(0, 0, "ab"), (2, 1, "b")]);
+        assert_eq!(match_indices_tagged("a+b-c", MultiStr(&["-", "+"])),
+                   vec![(1, 1, "+"), (3, 0, "-")]);
+        assert_eq!(match_indices_tagged("abb", Or("ab", "b")),
+                   vec![(0, 0, "ab"), (2, 1, "b")]);
+        assert_eq!(match_indices_tagged("xbax", Or(b'a', b'b')),
+                   vec![(1, 1, "b"), (2, 0, "a")]);
+        assert_eq!(match_indices_tagged("", Or("a", "b")), vec![]);
+    }
+
+    // The matches as plain ranges of offsets, which are counted in
+    // elements of the haystack, eg. bytes for `&str` and `T`s for `&[T]`.
+    #[cfg(feature = "alloc")]
+    pub fn match_ranges<H, P>(haystack: H, pattern: P) -> Vec<Range<usize>>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let matches = matches_iter(pattern.into_searcher(haystack));
+        let haystack = matches.haystack();
+        let mut last = None;
+
+        matches.map(|(begin, end)| {
+            debug_assert_advances::<H>(haystack, last, (begin, end));
+            last = Some((begin, end));
+            unsafe {
+                H::offset_from_start(haystack, begin)..H::offset_from_start(haystack, end)
+            }
+        }).collect()
+    }
+
+    // Walks the haystack as offsets, calling `f` with each gap and the
+    // match behind it, and finally with the gap behind the last match.
+    // Doesn't depend on the haystack type, so the replacing consumers
+    // can share it.
+    #[cfg(feature = "alloc")]
+    fn walk_offsets<H, P, F>(haystack: H, pattern: P, mut f: F)
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              F: FnMut(Range<usize>, Option<Range<usize>>),
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+
+        let walked = searcher.try_fold_matches((0, None), |(last_end, last), (begin, end)| {
+            debug_assert_advances::<H>(haystack, last, (begin, end));
+            let (begin_offset, end_offset) = unsafe {
+                (H::offset_from_start(haystack, begin),
+                 H::offset_from_start(haystack, end))
+            };
+            f(last_end..begin_offset, Some(begin_offset..end_offset));
+            ControlFlow::Continue((end_offset, Some((begin, end))))
+        });
+        let last_end = match walked {
+            ControlFlow::Continue((last_end, _)) |
+            ControlFlow::Break((last_end, _)) => last_end,
+        };
+
+        let back = unsafe { H::haystack_len(haystack) };
+        f(last_end..back, None);
+    }
+
+    // Replaces every match, copying the gaps in between.
+    #[cfg(feature = "alloc")]
+    pub fn replace<'a, P>(haystack: &'a str, pattern: P, with: &str) -> String
+        where P: Pattern<&'a str>,
+    {
+        let mut ret = String::with_capacity(haystack.len());
+        walk_offsets(haystack, pattern, |gap, m| {
+            ret.push_str(&haystack[gap]);
+            if m.is_some() {
+                ret.push_str(with);
+            }
+        });
+        ret
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_replace() {
+        let haystacks = ["", "a", "banana", "aaa", "é,ü,", "xéx"];
+        for &haystack in &haystacks {
+            for &with in &["", "-", "<long>"] {
+                for &needle in &["a", "an", "aa", "é", ",", "", "x"] {
+                    assert_eq!(replace(haystack, needle, with),
+                               haystack.replace(needle, with));
+                }
+                assert_eq!(replace(haystack, b',', with), haystack.replace(',', with));
+                assert_eq!(replace(haystack, char::is_alphabetic, with),
+                           haystack.replace(char::is_alphabetic, with));
+            }
+        }
+        assert_eq!(replace("a b\t c", string::Whitespace, "_"), "a_b_c");
+    }
+
+    // Overwrites every match with `replacement`, through the mutable
+    // pieces of the haystack. Matches with a different length than the
+    // replacement are skipped and left as they are. Returns the number
+    // of replaced matches.
+    #[cfg(feature = "alloc")]
+    pub fn replace_in_place<H, P, T>(haystack: H, pattern: P, replacement: &[T]) -> usize
+        where H: SearchPtrs + ::core::ops::DerefMut<Target = [T]>,
+              P: Pattern<H>,
+              T: Copy,
+    {
+        let matches = matches_iter(pattern.into_searcher(haystack));
+        let haystack = matches.haystack();
+        let ranges = matches.collect();
+
+        let mut replaced = 0;
+        for mut piece in ranges_to_pieces::<H>(haystack, ranges) {
+            if piece.len() == replacement.len() {
+                piece.copy_from_slice(replacement);
+                replaced += 1;
+            }
+        }
+        replaced
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_replace_in_place() {
+        let mut bytes = *b"banana";
+        assert_eq!(replace_in_place(&mut bytes[..], &b"na"[..], b"NA"), 2);
+        assert_eq!(&bytes, b"baNANA");
+
+        // The run of two spaces has the wrong length
+        let mut bytes = *b"a  b c";
+        assert_eq!(replace_in_place(&mut bytes[..], slice::AsciiWhitespace, b"_"), 1);
+        assert_eq!(&bytes, b"a  b_c");
+
+        assert_eq!(replace_in_place(&mut [][..], b'a', b"b"), 0);
+
+        let mut numbers = [1, 2, 3, 2];
+        assert_eq!(replace_in_place(&mut numbers[..], generic_slice::Elem(2), &[0]), 2);
+        assert_eq!(numbers, [1, 0, 3, 0]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_match_ranges() {
+        let haystack = "grüne Äpfel, grüne Birnen";
+        let ranges = match_ranges(haystack, "grüne");
+        assert_eq!(ranges, vec![0..6, 15..21]);
+        for range in ranges {
+            assert_eq!(&haystack[range], "grüne");
+        }
+        for range in match_ranges(haystack, char::is_uppercase) {
+            assert!(haystack[range].chars().all(char::is_uppercase));
+        }
+
+        // Offsets count elements, not bytes
+        let units: Vec<u16> = "aüb€".encode_utf16().collect();
+        assert_eq!(match_ranges(&units[..], '€'), vec![3..4]);
+        let numbers = [10u64, 20, 30, 20];
+        let ranges = match_ranges(&numbers[..], generic_slice::Elem(20));
+        assert_eq!(ranges, vec![1..2, 3..4]);
+        assert_eq!(&numbers[ranges[1].clone()], &[20]);
+
+        let mut bytes = *b"a,b";
+        assert_eq!(match_ranges(&mut bytes[..], b','), vec![1..2]);
+        assert_eq!(match_ranges("", "x"), Vec::<Range<usize>>::new());
+    }
+
+    // Counts the matches without creating pieces for them.
+    pub fn count<H, P>(haystack: H, pattern: P) -> usize
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        pattern.count_in(haystack)
+    }
+
+    #[test]
+    fn test_count() {
+        for &haystack in &["", "a", "banana", "aaaa", "éaé"] {
+            for &needle in &["a", "an", "aa", "", "é"] {
+                assert_eq!(count(haystack, needle), matches(haystack, needle).count());
+            }
+            let a = string::Ascii::new(b'a').unwrap();
+            assert_eq!(count(haystack, a), matches(haystack, a).count());
+            assert_eq!(count(haystack, char::is_alphabetic),
+                       matches(haystack, char::is_alphabetic).count());
+
+            let mut bytes = haystack.as_bytes().to_vec();
+            assert_eq!(count(&mut bytes[..], slice::Ascii(b'a')),
+                       matches(&mut bytes[..], slice::Ascii(b'a')).count());
+            assert_eq!(count(&mut bytes[..], &b""[..]),
+                       matches(&mut bytes[..], &b""[..]).count());
+        }
+        assert_eq!(count("banana", "a"), 3);
+        assert_eq!(count("ab", ""), 3);
+        assert_eq!(count(&mut [][..], b'a'), 0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_try_fold_matches() {
+        // Breaks after the second match and returns the offsets folded so
+        // far, followed by the offset of the match found next.
+        fn break_after_second<H, P>(haystack: H, pattern: P) -> (Vec<usize>, Option<usize>)
+            where H: SearchPtrs,
+                  P: Pattern<H>,
+        {
+            let mut searcher = pattern.into_searcher(haystack);
+            let hs = searcher.haystack();
+            let folded = searcher.try_fold_matches(vec![], |mut offsets, (begin, _)| {
+                offsets.push(unsafe { H::offset_from_start(hs, begin) });
+                if offsets.len() == 2 {
+                    ControlFlow::Break(offsets)
+                } else {
+                    ControlFlow::Continue(offsets)
+                }
+            });
+            let offsets = match folded {
+                ControlFlow::Break(offsets) => offsets,
+                ControlFlow::Continue(offsets) => {
+                    assert!(offsets.len() < 2);
+                    offsets
+                }
+            };
+            let next = searcher.next_match()
+                .map(|(begin, _)| unsafe { H::offset_from_start(hs, begin) });
+            (offsets, next)
+        }
+
+        let a = string::Ascii::new(b'a').unwrap();
+        assert_eq!(break_after_second("banana", a), (vec![1, 3], Some(5)));
+        assert_eq!(break_after_second("bAnana", string::AsciiNoCase::new(b'a').unwrap()),
+                   (vec![1, 3], Some(5)));
+        assert_eq!(break_after_second("banana", "a"), (vec![1, 3], Some(5)));
+        assert_eq!(break_after_second("aa", a), (vec![0, 1], None));
+        assert_eq!(break_after_second("ba", a), (vec![1], None));
+        assert_eq!(break_after_second(&b"banana"[..], slice::Ascii(b'a')),
+                   (vec![1, 3], Some(5)));
+        let mut bytes = *b"banana";
+        assert_eq!(break_after_second(&mut bytes[..], slice::Ascii(b'a')),
+                   (vec![1, 3], Some(5)));
+        assert_eq!(break_after_second(&mut bytes[..], slice::AsciiNoCase(b'A')),
+                   (vec![1, 3], Some(5)));
+
+        // The overrides find the same matches as `next_match`
+        for &haystack in &["", "a", "banana", "AaBa", "éaé"] {
+            let ranges = match_ranges(haystack, a);
+            assert_eq!(match_indices(haystack, a).into_iter().map(|m| m.0).collect::<Vec<_>>(),
+                       ranges.iter().map(|r| r.start).collect::<Vec<_>>());
+            assert_eq!(count(haystack, string::AsciiNoCase::new(b'a').unwrap()),
+                       match_ranges(haystack, string::AsciiNoCase::new(b'a').unwrap()).len());
+            assert_eq!(replace(haystack, a, "<>"), haystack.replace('a', "<>"));
+            assert_eq!(match_indices(haystack.as_bytes(), slice::Ascii(b'a')).len(),
+                       ranges.len());
+        }
+    }
+
+    // Deletes every match by moving the following bytes to the front,
+    // and returns the length of what remains. The bytes behind that
+    // length are unspecified.
+    //
+    // Moving bytes would invalidate the cursors of the searcher,
+    // so all matches are found before anything is moved.
+    #[cfg(feature = "alloc")]
+    pub fn remove_matches<P>(haystack: &mut [u8], pattern: P) -> usize
+        where P: for<'b> Pattern<&'b mut [u8]>,
+    {
+        let ranges = match_ranges(&mut *haystack, pattern);
+
+        let mut len = 0;
+        let mut last_end = 0;
+        for range in ranges.into_iter().chain(Some(haystack.len()..haystack.len())) {
+            haystack.copy_within(last_end..range.start, len);
+            len += range.start - last_end;
+            last_end = range.end;
+        }
+        len
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_remove_matches() {
+        let mut bytes = *b"banana";
+        let len = remove_matches(&mut bytes, slice::Ascii(b'a'));
+        assert_eq!(len, 3);
+        assert_eq!(&bytes[..len], b"bnn");
+
+        let mut bytes = *b"one, two,,three, ";
+        let len = remove_matches(&mut bytes, &b", "[..]);
+        assert_eq!(&bytes[..len], b"onetwo,,three");
+
+        let mut bytes = *b"aaa";
+        assert_eq!(remove_matches(&mut bytes, b'a'), 0);
+        let mut bytes = *b"xyz";
+        assert_eq!(remove_matches(&mut bytes, b'a'), 3);
+        assert_eq!(&bytes, b"xyz");
+        assert_eq!(remove_matches(&mut [], b'a'), 0);
+    }
+
+    // Overwrites every match with `fill` while searching, by writing
+    // through the cursors of each match as soon as it's found.
+    // Returns the number of matches.
+    //
+    // Like with `Matches` the searcher must not look at what it already
+    // yielded, it would see the fill bytes.
+    pub fn fill_matches<'a, P>(haystack: &'a mut [u8], pattern: P, fill: u8) -> usize
+        where P: Pattern<&'a mut [u8]>,
+    {
+        matches_iter(pattern.into_searcher(haystack)).fold(0, |count, (begin, end)| {
+            unsafe {
+                ::core::ptr::write_bytes(begin, fill, end.offset_from(begin) as usize);
+            }
+            count + 1
+        })
+    }
+
+    #[test]
+    fn test_fill_matches() {
+        let mut bytes = *b"card 1234-5678, pin 42";
+        assert_eq!(fill_matches(&mut bytes, |b: u8| b.is_ascii_digit(), b'#'), 10);
+        assert_eq!(&bytes, b"card ####-####, pin ##");
+
+        // Occurrences of "aa" could overlap, the matches don't
+        let mut bytes = *b"aaaaa";
+        assert_eq!(fill_matches(&mut bytes, &b"aa"[..], b'-'), 2);
+        assert_eq!(&bytes, b"----a");
+
+        let mut bytes = *b"a, b,  c";
+        assert_eq!(fill_matches(&mut bytes, slice::AsciiWhitespace, b'_'), 2);
+        assert_eq!(&bytes, b"a,_b,__c");
+        assert_eq!(fill_matches(&mut [], b'a', b'-'), 0);
+    }
+
+    // Hands every match to `f` as soon as it's found, together with its
+    // offset, so it can be rewritten in place. `f` only ever gets one
+    // match at a time, and can't keep it. The same caveat as for
+    // `fill_matches` applies.
+    pub fn replace_with<'a, P, F>(haystack: &'a mut [u8], pattern: P, mut f: F)
+        where P: Pattern<&'a mut [u8]>,
+              F: FnMut(usize, &mut [u8]),
+    {
+        for (offset, piece) in MatchIndices::new(haystack, pattern) {
+            f(offset, piece);
+        }
+    }
+
+    #[test]
+    fn test_replace_with() {
+        let mut bytes = *b"banana";
+        replace_with(&mut bytes, &b"na"[..], |_, m| m.make_ascii_uppercase());
+        assert_eq!(&bytes, b"baNANA");
+
+        let mut bytes = *b"a-b-c-d";
+        let mut offsets = vec![];
+        replace_with(&mut bytes, slice::Ascii(b'-'), |offset, m| {
+            offsets.push(offset);
+            m[0] = if offset < 3 { b'<' } else { b'>' };
+        });
+        assert_eq!(offsets, vec![1, 3, 5]);
+        assert_eq!(&bytes, b"a<b>c>d");
+    }
+
+    // The offset of the first match, without searching any further.
+    pub fn find<H, P>(haystack: H, pattern: P) -> Option<usize>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        pattern.first_match_offset(haystack)
+    }
+
+    pub fn contains<H, P>(haystack: H, pattern: P) -> bool
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        pattern.is_contained_in(haystack)
+    }
+
+    // The offset of the last match, searching from the back.
+    pub fn rfind<H, P>(haystack: H, pattern: P) -> Option<usize>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        searcher.next_match_back().map(|(begin, _)| unsafe {
+            H::offset_from_start(searcher.haystack(), begin)
+        })
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_find() {
+        let a = string::Ascii::new(b'a').unwrap();
+        assert_eq!(find("banana", a), Some(1));
+        assert_eq!(rfind("banana", a), Some(5));
+        assert_eq!(find("bnn", a), None);
+        assert_eq!(rfind("", a), None);
+        assert_eq!(find("éa", "a"), Some(2));
+
+        let slice = &mut {*b"banana"}[..];
+        assert_eq!(find(&mut*slice, slice::Ascii(b'n')), Some(2));
+        assert_eq!(rfind(&mut*slice, slice::Ascii(b'n')), Some(4));
+        assert_eq!(rfind(&mut*slice, slice::Ascii(b'x')), None);
+
+        let numbers = [1, 2, 3, 2, 1];
+        assert_eq!(find(&numbers[..], generic_slice::Elem(2)), Some(1));
+        assert_eq!(rfind(&numbers[..], generic_slice::Elem(2)), Some(3));
+
+        let list = ["ban", "a", "na"];
+        assert_eq!(find(segments::Segments::new(&list), "ana"), Some(1));
+    }
+
+    // `first_match_offset` as provided by `Pattern`, to compare
+    // the specialized versions against.
+    #[cfg(test)]
+    fn first_match_offset_default<H, P>(haystack: H, pattern: P) -> Option<usize>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        searcher.next_match().map(|(begin, _)| unsafe {
+            H::offset_from_start(searcher.haystack(), begin)
+        })
+    }
+
+    #[test]
+    fn test_first_match_offset() {
+        // Long enough for both the naive and the two-way path
+        let long = "ab".repeat(40) + "aé,aab";
+        let haystacks = ["", "a", "é", "baab", "xé,é", "abababac", &long];
+        for &haystack in &haystacks {
+            for &needle in &["", "a", "ab", "aab", "é,", "ac", "x"] {
+                assert_eq!(needle.first_match_offset(haystack),
+                           first_match_offset_default(haystack, needle));
+                assert_eq!(contains(haystack, needle), haystack.contains(needle));
+            }
+            for &b in b"ab,x" {
+                let ascii = string::Ascii::new(b).unwrap();
+                assert_eq!(ascii.first_match_offset(haystack),
+                           first_match_offset_default(haystack, ascii));
+
+                let mut bytes = haystack.as_bytes().to_vec();
+                assert_eq!(slice::Ascii(b).first_match_offset(&mut bytes[..]),
+                           first_match_offset_default(&mut bytes[..], slice::Ascii(b)));
+            }
+        }
+        assert_eq!(find(&*long, "aab"), long.find("aab"));
+        // Forwarded through the impl for references
+        let pat = &slice::Ascii(b'c');
+        assert!(contains(&b"abc"[..], pat));
+    }
+
+    // The offset of the first reject. For patterns matching more than one
+    // element at once that's where the first gap between matches begins.
+    pub fn find_not<H, P>(haystack: H, pattern: P) -> Option<usize>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut rejects = rejects_iter(pattern.into_searcher(haystack));
+        rejects.next().map(|(begin, _)| unsafe {
+            H::offset_from_start(rejects.haystack(), begin)
+        })
+    }
+
+    // The offset of the begin of the last reject, searching from the back.
+    pub fn rfind_not<H, P>(haystack: H, pattern: P) -> Option<usize>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        searcher.next_reject_back().map(|(begin, _)| unsafe {
+            H::offset_from_start(searcher.haystack(), begin)
+        })
+    }
+
+    #[test]
+    fn test_find_not() {
+        let digit = |c: char| c.is_ascii_digit();
+        for &text in &["", "123", "12ab3", "a", "1é2", "é12"] {
+            assert_eq!(find_not(text, digit), text.find(|c: char| !digit(c)));
+            assert_eq!(rfind_not(text, digit), text.rfind(|c: char| !digit(c)));
+        }
+        assert_eq!(find_not("  x", b' '), Some(2));
+        assert_eq!(rfind_not("x  ", b' '), Some(0));
+        assert_eq!(find_not("   ", b' '), None);
+
+        // Rejects of a substring are the gaps between its matches
+        assert_eq!(find_not("ababxab", "ab"), Some(4));
+        assert_eq!(find_not("abab", "ab"), None);
+
+        let mut bytes = *b"--ab-";
+        assert_eq!(find_not(&mut bytes[..], b'-'), Some(2));
+        assert_eq!(rfind_not(&mut bytes[..], b'-'), Some(3));
+    }
+
+    // The `n`th match (counting from 0) with its offset. Stops searching
+    // once it's found.
+    pub fn nth_match<H, P>(haystack: H, n: usize, pattern: P) -> Option<(usize, H)>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut match_indices = MatchIndices::new(haystack, pattern);
+        for _ in 0..n {
+            match_indices.next_range()?;
+        }
+        match_indices.next()
+    }
+
+    // The `n`th piece `split` would yield, without searching further.
+    pub fn nth_field<H, P>(haystack: H, n: usize, pattern: P) -> Option<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut split = Split::new(haystack, pattern);
+        for _ in 0..n {
+            split.next_range()?;
+        }
+        split.next()
+    }
+
+    // Test support: counts how often `next_match` is called.
+    #[cfg(test)]
+    struct Counting<'c, P>(P, &'c ::std::cell::Cell<usize>);
+
+    #[cfg(test)]
+    struct CountingSearcher<'c, S>(S, &'c ::std::cell::Cell<usize>);
+
+    #[cfg(test)]
+    unsafe impl<'c, H, S> Searcher<H> for CountingSearcher<'c, S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.0.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            self.0.next()
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.1.set(self.1.get() + 1);
+            self.0.next_match()
+        }
+
+        fn next_reject(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            self.0.next_reject()
+        }
+    }
+
+    #[cfg(test)]
+    impl<'c, H, P> Pattern<H> for Counting<'c, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        type Searcher = CountingSearcher<'c, P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            CountingSearcher(self.0.into_searcher(haystack), self.1)
+        }
+    }
+
+    #[test]
+    fn test_nth_match() {
+        use std::cell::Cell;
+
+        let calls = Cell::new(0);
+        let record = "root:x:0:0:root:/root:/bin/sh";
+        assert_eq!(nth_match(record, 1, Counting(b':', &calls)), Some((6, ":")));
+        assert_eq!(calls.get(), 2);
+
+        calls.set(0);
+        assert_eq!(nth_field(record, 2, Counting(b':', &calls)), Some("0"));
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        assert_eq!(nth_field(record, 6, Counting(b':', &calls)), Some("/bin/sh"));
+        // The last field is only known once no match is left
+        assert_eq!(calls.get(), 7);
+        assert_eq!(nth_field(record, 7, b':'), None);
+        assert_eq!(nth_match(record, 6, b':'), None);
+        assert_eq!(nth_match("banana", 2, "a"), Some((5, "a")));
+        for n in 0..4 {
+            assert_eq!(nth_field("a,,b", n, b','), "a,,b".split(',').nth(n));
+        }
+
+        let mut bytes = *b"a:b:c";
+        nth_field(&mut bytes[..], 1, slice::Ascii(b':')).unwrap()[0] = b'B';
+        assert_eq!(&bytes, b"a:B:c");
+    }
+
+    // Stops at the first match, so an infinite searcher still terminates.
+    #[test]
+    fn test_find_stops_early() {
+        struct Endless<'a>(&'a str);
+
+        unsafe impl<'a> Searcher<&'a str> for Endless<'a> {
+            fn haystack(&self) -> (*const u8, *const u8) {
+                let hs = self.0.as_bytes().as_ptr_range();
+                (hs.start, hs.end)
+            }
+            fn next(&mut self) -> SearchStep<*const u8> {
+                let p = self.0.as_ptr();
+                SearchStep::Match(p, p)
+            }
+            fn next_match(&mut self) -> Option<(*const u8, *const u8)> {
+                let p = self.0.as_ptr();
+                Some((p, p))
+            }
+            fn next_reject(&mut self) -> Option<(*const u8, *const u8)> {
+                None
+            }
+        }
+
+        struct Forever;
+
+        impl<'a> Pattern<&'a str> for Forever {
+            type Searcher = Endless<'a>;
+
+            fn into_searcher(self, haystack: &'a str) -> Endless<'a> {
+                Endless(haystack)
+            }
+            fn is_prefix_of(self, _: &'a str) -> bool {
+                true
+            }
+            fn is_suffix_of(self, _: &'a str) -> bool
+                where Self::Searcher: ReverseSearcher<&'a str> {
+                true
+            }
+        }
+
+        assert_eq!(find("abc", Forever), Some(0));
+    }
+
+    // If the first match begins at the front, returns the rest of the
+    // haystack behind it. The stripped prefix is the match the searcher
+    // finds there, so for patterns with several possible matches at one
+    // position it depends on how they choose: `MultiStr` for example
+    // strips the longest needle.
+    pub fn strip_prefix<H, P>(haystack: H, pattern: P) -> Option<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let (begin, end) = searcher.next_match()?;
+        unsafe {
+            let front = H::cursor_at_front(haystack);
+            if H::cursor_cmp(haystack, begin, front) != Ordering::Equal {
+                return None;
+            }
+            Some(H::range_to_self(haystack, end, H::cursor_at_back(haystack)))
+        }
+    }
+
+    // If the last match ends at the back, returns the rest of the
+    // haystack in front of it.
+    pub fn strip_suffix<H, P>(haystack: H, pattern: P) -> Option<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let (begin, end) = searcher.next_match_back()?;
+        unsafe {
+            if H::offset_from_end(haystack, end) != 0 {
+                return None;
+            }
+            Some(H::range_to_self(haystack, H::cursor_at_front(haystack), begin))
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_strip_prefix_suffix() {
+        assert_eq!(strip_prefix("key=value", "key="), Some("value"));
+        assert_eq!(strip_prefix("a key=", "key="), None);
+        assert_eq!(strip_prefix("", "key="), None);
+        assert_eq!(strip_prefix("abc", ""), Some("abc"));
+        assert_eq!(strip_prefix("  a", string::Whitespace), Some("a"));
+
+        // The longest of the needles matching at the front is stripped
+        let needles = ["ab", "abc"];
+        assert_eq!(strip_prefix("abcd", string::MultiStr(&needles)), Some("d"));
+        assert_eq!(strip_prefix("abd", string::MultiStr(&needles)), Some("d"));
+
+        let a = string::Ascii::new(b'a').unwrap();
+        assert_eq!(strip_suffix("banana", a), Some("banan"));
+        assert_eq!(strip_suffix("banan", a), None);
+        assert_eq!(strip_suffix("", a), None);
+        assert_eq!(strip_suffix("a1 ", char::is_whitespace), Some("a1"));
+
+        let mut bytes = *b"--ab--";
+        {
+            let rest = strip_prefix(&mut bytes[..], &b"--"[..]).unwrap();
+            rest[0] = b'A';
+            let rest = strip_suffix(rest, slice::Ascii(b'-')).unwrap();
+            assert_eq!(rest, b"Ab-");
+            rest[2] = b'!';
+        }
+        assert_eq!(&bytes, b"--Ab!-");
+        assert!(strip_suffix(&mut [][..], b'-').is_none());
+    }
+
+    // Strips all matches from the front: keeps everything from the first
+    // reject on.
+    pub fn trim_start<H, P>(haystack: H, pattern: P) -> H
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let end = unsafe { H::cursor_at_back(haystack) };
+        let begin = match searcher.next_reject() {
+            Some((begin, _)) => begin,
+            None => end,
+        };
+        unsafe { H::range_to_self(haystack, begin, end) }
+    }
+
+    #[test]
+    fn test_trim_start() {
+        let space = string::Ascii::new(b' ').unwrap();
+        assert_eq!(trim_start("  a b ", space), "a b ");
+        assert_eq!(trim_start("   ", space), "");
+        assert_eq!(trim_start("ab", space), "ab");
+        assert_eq!(trim_start("", space), "");
+        assert_eq!(trim_start(" é", space), "é");
+        assert_eq!(trim_start("12ab3", |c: char| c.is_ascii_digit()), "ab3");
+
+        let slice = &mut {*b"--ab"}[..];
+        {
+            let trimmed = trim_start(&mut*slice, slice::Ascii(b'-'));
+            assert_eq!(trimmed, b"ab");
+            trimmed[0] = b'c';
+        }
+        assert_eq!(slice, b"--cb");
+        assert_eq!(trim_start(&mut {*b"--"}[..], b'-'), b"");
+        assert_eq!(trim_start(&mut {*b"ab"}[..], b'-'), b"ab");
+        assert_eq!(trim_start(&mut [][..], b'-'), b"");
+    }
+
+    // Strips all matches from the back: keeps everything up to and
+    // including the last reject.
+    pub fn trim_end<H, P>(haystack: H, pattern: P) -> H
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let begin = unsafe { H::cursor_at_front(haystack) };
+        let end = match searcher.next_reject_back() {
+            Some((_, end)) => end,
+            None => begin,
+        };
+        unsafe { H::range_to_self(haystack, begin, end) }
+    }
+
+    #[test]
+    fn test_trim_end() {
+        use self::string::Whitespace;
+
+        let x = string::Ascii::new(b'x').unwrap();
+        assert_eq!(trim_end("axbxx", x), "axb");
+        assert_eq!(trim_end("xxx", x), "");
+        assert_eq!(trim_end("", x), "");
+        assert_eq!(trim_end("aé", x), "aé");
+        assert_eq!(trim_end(" a b \t\u{a0}", Whitespace), " a b");
+        assert_eq!(trim_end("ab12", |c: char| c.is_ascii_digit()), "ab");
+
+        let slice = &mut {*b"ab--"}[..];
+        {
+            let trimmed = trim_end(&mut*slice, slice::Ascii(b'-'));
+            assert_eq!(trimmed, b"ab");
+            trimmed[1] = b'c';
+        }
+        assert_eq!(slice, b"ac--");
+        assert_eq!(trim_end(&mut {*b"--"}[..], b'-'), b"");
+        assert_eq!(trim_end(&mut [][..], b'-'), b"");
+    }
+
+    // Strips all matches from both ends. The searcher has to be double
+    // ended, so that both ends agree on where the matches are.
+    // If everything matches the result is empty, at the front.
+    pub fn trim<H, P>(haystack: H, pattern: P) -> H
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: DoubleEndedSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let (begin, end) = match searcher.next_reject() {
+            Some((begin, end)) => {
+                let end = match searcher.next_reject_back() {
+                    Some((_, end)) => end,
+                    // The first reject was the only one
+                    None => end,
+                };
+                (begin, end)
+            }
+            None => {
+                let front = unsafe { H::cursor_at_front(haystack) };
+                (front, front)
+            }
+        };
+        unsafe { H::range_to_self(haystack, begin, end) }
+    }
+
+    #[test]
+    fn test_trim() {
+        use self::string::Whitespace;
+
+        assert_eq!(trim(" \t a b\u{a0}\n", Whitespace), "a b");
+        assert_eq!(trim("   ", Whitespace), "");
+        assert_eq!(trim("", Whitespace), "");
+        assert_eq!(trim(" a", Whitespace), "a");
+        assert_eq!(trim("xax", b'x'), "a");
+
+        // The empty result lies at the front
+        let haystack = "xx";
+        assert_eq!(trim(haystack, b'x').as_ptr(), haystack.as_ptr());
+
+        for &text in &["", "x", "a", "xax", "axa", "xxaxbxx", "é x é", "xéx"] {
+            let x = string::Ascii::new(b'x').unwrap();
+            assert_eq!(trim(text, x), trim_end(trim_start(text, x), x));
+            assert_eq!(trim(text, x), text.trim_matches('x'));
+            let pred = |c: char| c == 'x' || c == ' ';
+            assert_eq!(trim(text, pred), trim_end(trim_start(text, pred), pred));
+            assert_eq!(trim(text, Whitespace), text.trim());
+        }
+
+        let mut bytes = *b"--ab--";
+        {
+            let trimmed = trim(&mut bytes[..], slice::Ascii(b'-'));
+            assert_eq!(trimmed, b"ab");
+            trimmed.copy_from_slice(b"AB");
+        }
+        assert_eq!(&bytes, b"--AB--");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_match_indices() {
+        assert_eq!(match_indices("banana", b'a'),
+                   vec![(1, "a"), (3, "a"), (5, "a")]);
+
+        let mut slice = &mut {*b"banana"}[..];
+
+        {
+            let match_indices = match_indices(&mut*slice, slice::Ascii(b'a'));
+
+            assert_eq!(match_indices.iter().map(|x| x.0).collect::<Vec<_>>(),
+                       vec![1, 3, 5]);
+
+            for m in match_indices {
+                m.1[0] = b'i';
+            }
+        }
+
+        assert_eq!(slice, b"binini");
+    }
+
+    // Lazily yields the pieces between the matches. The same caveat as
+    // for `Matches` applies to mutable haystacks.
+    //
+    // When searching from both ends, the piece between the last matches
+    // found from the front and from the back is yielded by whichever
+    // end gets there first, like with `str::split`.
+    pub struct Split<H: SearchPtrs, P: Pattern<H>> {
+        searcher: P::Searcher,
+        haystack: H::Haystack,
+        // The begin of the next piece from the front
+        // and the end of the next piece from the back
+        start: H::Cursor,
+        end: H::Cursor,
+        last: Option<(H::Cursor, H::Cursor)>,
+        finished: bool,
+    }
+
+    impl<H: SearchPtrs, P: Pattern<H>> Split<H, P> {
+        pub fn new(haystack: H, pattern: P) -> Self {
+            let searcher = pattern.into_searcher(haystack);
+            let haystack = searcher.haystack();
+            let (start, end) = unsafe {
+                (H::cursor_at_front(haystack), H::cursor_at_back(haystack))
+            };
+            Split { searcher, haystack, start, end, last: None, finished: false }
+        }
+
+        fn next_range(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            if self.finished {
+                return None;
+            }
+            match self.searcher.next_match() {
+                Some((begin, end)) => {
+                    debug_assert_advances::<H>(self.haystack, self.last, (begin, end));
+                    debug_assert!(unsafe {
+                        H::cursor_cmp(self.haystack, begin, self.start)
+                    } != Ordering::Less, "split needs non-overlapping matches");
+                    self.last = Some((begin, end));
+                    let piece = (self.start, begin);
+                    self.start = end;
+                    Some(piece)
+                }
+                None => {
+                    self.finished = true;
+                    Some((self.start, self.end))
+                }
+            }
+        }
+
+        fn next_range_back(&mut self) -> Option<(H::Cursor, H::Cursor)>
+            where P::Searcher: ReverseSearcher<H>
+        {
+            if self.finished {
+                return None;
+            }
+            match self.searcher.next_match_back() {
+                Some((begin, end)) => {
+                    let piece = (end, self.end);
+                    self.end = begin;
+                    Some(piece)
+                }
+                None => {
+                    self.finished = true;
+                    Some((self.start, self.end))
+                }
+            }
+        }
+    }
+
+    impl<H: SearchPtrs, P: Pattern<H>> Iterator for Split<H, P> {
+        type Item = H;
+
+        fn next(&mut self) -> Option<H> {
+            let (begin, end) = self.next_range()?;
+            Some(unsafe { H::range_to_self(self.haystack, begin, end) })
+        }
+
+        // One more piece than matches, until the last one is yielded
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            if self.finished {
+                return (0, Some(0));
+            }
+            let (lower, upper) = self.searcher.match_bounds();
+            (lower.saturating_add(1), upper.and_then(|upper| upper.checked_add(1)))
+        }
+    }
+
+    impl<H, P> DoubleEndedIterator for Split<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: DoubleEndedSearcher<H>,
+    {
+        fn next_back(&mut self) -> Option<H> {
+            let (begin, end) = self.next_range_back()?;
+            Some(unsafe { H::range_to_self(self.haystack, begin, end) })
+        }
+    }
+
+    // Fused by `finished` whatever the searcher does, as the searcher
+    // isn't asked again once it ran out.
+    impl<H: SearchPtrs, P: Pattern<H>> ::core::iter::FusedIterator for Split<H, P> {}
+
+    // Collects the pieces of `Split`, but only creates them once the
+    // searcher is done, see `ranges_to_pieces`.
+    #[cfg(feature = "alloc")]
+    pub fn split<H, P>(haystack: H, pattern: P) -> Vec<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut split = Split::new(haystack, pattern);
+        let mut ranges = Vec::with_capacity(split.searcher.match_bounds().0 + 1);
+        while let Some(range) = split.next_range() {
+            ranges.push(range);
+        }
+        ranges_to_pieces::<H>(split.haystack, ranges)
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_split_lazy() {
+        use std::cell::Cell;
+
+        let calls = Cell::new(0);
+        let comma = |c: char| {
+            calls.set(calls.get() + 1);
+            c == ','
+        };
+        let mut it = Split::new("a,b,c,d", comma);
+        assert_eq!(it.next(), Some("a"));
+        // Only 'a' and the first ',' were looked at
+        assert_eq!(calls.get(), 2);
+        assert_eq!(it.nth(1), Some("c"));
+
+        let comma = string::Ascii::new(b',').unwrap();
+        assert_eq!(Split::new(",a,,b,", comma).collect::<Vec<_>>(),
+                   split(",a,,b,", comma));
+        assert_eq!(Split::new(",a,,b,", comma).rev().collect::<Vec<_>>(),
+                   ",a,,b,".rsplit(',').collect::<Vec<_>>());
+        assert_eq!(Split::new("", comma).rev().collect::<Vec<_>>(), vec![""]);
+
+        // Both ends meet in the middle
+        let mut it = Split::new("a,b,c", comma);
+        assert_eq!(it.next_back(), Some("c"));
+        assert_eq!(it.next(), Some("a"));
+        assert_eq!(it.next_back(), Some("b"));
+        assert_eq!(it.next(), None);
+        assert_eq!(it.next_back(), None);
+
+        let mut bytes = *b"ab,cd,ef";
+        {
+            let mut it = Split::new(&mut bytes[..], slice::Ascii(b','));
+            let first = it.next().unwrap();
+            let last = it.next_back().unwrap();
+            for piece in it {
+                piece.copy_from_slice(b"--");
+            }
+            first[0] = b'A';
+            last[1] = b'F';
+        }
+        assert_eq!(&bytes, b"Ab,--,eF");
+    }
+
+    // Test support: reports exact match bounds, counting down from the
+    // given number of matches of the inner pattern.
+    #[cfg(all(test, feature = "alloc"))]
+    struct Exact<P>(P, usize);
+
+    #[cfg(all(test, feature = "alloc"))]
+    struct ExactSearcher<S>(S, usize);
+
+    #[cfg(all(test, feature = "alloc"))]
+    unsafe impl<H, S> Searcher<H> for ExactSearcher<S>
+        where H: SearchPtrs,
+              S: Searcher<H>,
+    {
+        fn haystack(&self) -> H::Haystack {
+            self.0.haystack()
+        }
+
+        fn next(&mut self) -> SearchStep<H::Cursor> {
+            let step = self.0.next();
+            if let SearchStep::Match(..) = step {
+                self.1 -= 1;
+            }
+            step
+        }
+
+        fn next_match(&mut self) -> Option<(H::Cursor, H::Cursor)> {
+            let m = self.0.next_match();
+            if m.is_some() {
+                self.1 -= 1;
+            }
+            m
+        }
+
+        fn match_bounds(&self) -> (usize, Option<usize>) {
+            (self.1, Some(self.1))
+        }
+    }
+
+    #[cfg(all(test, feature = "alloc"))]
+    impl<H, P> Pattern<H> for Exact<P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        type Searcher = ExactSearcher<P::Searcher>;
+
+        fn into_searcher(self, haystack: H) -> Self::Searcher {
+            ExactSearcher(self.0.into_searcher(haystack), self.1)
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_match_bounds() {
+        let comma = string::Ascii::new(b',').unwrap();
+        let mut it = matches("a,b,c", Exact(comma, 2));
+        assert_eq!(it.size_hint(), (2, Some(2)));
+        it.next();
+        assert_eq!(it.size_hint(), (1, Some(1)));
+        assert_eq!(MatchIndices::new("a,b,c", Exact(comma, 2)).size_hint(), (2, Some(2)));
+
+        let mut it = Split::new("a,b,c", Exact(comma, 2));
+        assert_eq!(it.size_hint(), (3, Some(3)));
+        it.next();
+        assert_eq!(it.size_hint(), (2, Some(2)));
+        assert_eq!(it.by_ref().count(), 2);
+        assert_eq!(it.size_hint(), (0, Some(0)));
+
+        assert_eq!(split("a,b,c", Exact(comma, 2)), vec!["a", "b", "c"]);
+        assert_eq!(match_indices("a,b,c", Exact(comma, 2)), vec![(1, ","), (3, ",")]);
+
+        // Single-element patterns are bounded by the remaining elements
+        let mut it = matches("a,b,c", comma);
+        assert_eq!(it.size_hint(), (0, Some(5)));
+        it.next();
+        assert_eq!(it.size_hint(), (0, Some(3)));
+        assert_eq!(matches("éa", &['a'][..]).size_hint(), (0, Some(3)));
+        assert_eq!(matches(&[1, 2, 1][..], generic_slice::Elem(1)).size_hint(), (0, Some(3)));
+        let mut bytes = *b"a,b";
+        assert_eq!(matches(&mut bytes[..], slice::Ascii(b',')).size_hint(), (0, Some(3)));
+        assert_eq!(matches("a,b", ",").size_hint(), (0, None));
+        assert_eq!(Split::new("a,b", ",").size_hint(), (1, None));
+        // The offsets still bound the matches of `MatchIndices`
+        assert_eq!(MatchIndices::new("a,b", ",").size_hint(), (0, Some(4)));
+    }
+
+    // Like `split`, but with at most `n` pieces: after `n - 1` matches
+    // the rest of the haystack is left unsearched and becomes the last piece.
+    #[cfg(feature = "alloc")]
+    pub fn splitn<H, P>(haystack: H, n: usize, pattern: P) -> Vec<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        match n {
+            0 => return vec![],
+            1 => return vec![haystack],
+            _ => {}
+        }
+
+        let mut split = Split::new(haystack, pattern);
+        let mut ranges = vec![];
+        while ranges.len() + 1 < n {
+            match split.next_range() {
+                Some(range) => ranges.push(range),
+                None => break,
+            }
+        }
+        if !split.finished {
+            ranges.push((split.start, split.end));
+        }
+        ranges_to_pieces::<H>(split.haystack, ranges)
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_splitn() {
+        for &text in &["key=value=with=equals", "key", "", "=", "a==b=", "=x="] {
+            for n in 0..6 {
+                assert_eq!(splitn(text, n, b'='),
+                           text.splitn(n, '=').collect::<Vec<_>>());
+            }
+        }
+
+        let mut bytes = *b"a,b,c,d";
+        {
+            let pieces = splitn(&mut bytes[..], 3, slice::Ascii(b','));
+            assert_eq!(pieces.len(), 3);
+
+            for (i, piece) in pieces.into_iter().enumerate() {
+                for byte in piece {
+                    *byte = b'0' + i as u8;
+                }
+            }
+        }
+        // The tail keeps its delimiter
+        assert_eq!(&bytes, b"0,1,222");
+    }
+
+    // Splits around the first match into the pieces in front of and
+    // behind it.
+    pub fn split_once<H, P>(haystack: H, pattern: P) -> Option<(H, H)>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let (begin, end) = searcher.next_match()?;
+        unsafe {
+            Some((H::range_to_self(haystack, H::cursor_at_front(haystack), begin),
+                  H::range_to_self(haystack, end, H::cursor_at_back(haystack))))
+        }
+    }
+
+    // Splits around the last match, found by searching from the back.
+    pub fn rsplit_once<H, P>(haystack: H, pattern: P) -> Option<(H, H)>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let (begin, end) = searcher.next_match_back()?;
+        unsafe {
+            Some((H::range_to_self(haystack, H::cursor_at_front(haystack), begin),
+                  H::range_to_self(haystack, end, H::cursor_at_back(haystack))))
+        }
+    }
+
+    #[test]
+    fn test_split_once() {
+        assert_eq!(split_once("key=value=more", "="), Some(("key", "value=more")));
+        assert_eq!(split_once("key", "="), None);
+        assert_eq!(split_once("", "="), None);
+        assert_eq!(split_once("=value", "="), Some(("", "value")));
+        assert_eq!(split_once("key=", "="), Some(("key", "")));
+        assert_eq!(split_once("a::b", "::"), Some(("a", "b")));
+
+        let eq = string::Ascii::new(b'=').unwrap();
+        assert_eq!(rsplit_once("key=value=more", eq), Some(("key=value", "more")));
+        assert_eq!(rsplit_once("key", eq), None);
+        assert_eq!(rsplit_once("=value", eq), Some(("", "value")));
+        assert_eq!(rsplit_once("key=", eq), Some(("key", "")));
+        for &text in &["a=b=c", "=", "==", "abc", ""] {
+            assert_eq!(split_once(text, eq), text.split_once('='));
+            assert_eq!(rsplit_once(text, eq), text.rsplit_once('='));
+        }
+
+        // Both halves are mutable at the same time
+        let mut bytes = *b"ab=cd=ef";
+        {
+            let (front, back) = split_once(&mut bytes[..], slice::Ascii(b'=')).unwrap();
+            front[0] = b'A';
+            back[0] = b'C';
+            front[1] = b'B';
+        }
+        {
+            let (front, back) = rsplit_once(&mut bytes[..], slice::Ascii(b'=')).unwrap();
+            assert_eq!(front, b"AB=Cd");
+            back.copy_from_slice(b"EF");
+            front[4] = b'D';
+        }
+        assert_eq!(&bytes, b"AB=CD=EF");
+    }
+
+    // Splits around the first match into the piece in front of it,
+    // the match itself and the piece behind it.
+    //
+    // The four cursors front <= begin <= end <= back come from one
+    // searcher, so the pieces are adjacent and don't overlap, which
+    // keeps them usable at the same time for mutable haystacks.
+    pub fn partition<H, P>(haystack: H, pattern: P) -> Option<(H, H, H)>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        let (begin, end) = searcher.next_match()?;
+        unsafe {
+            let front = H::cursor_at_front(haystack);
+            let back = H::cursor_at_back(haystack);
+            Some((H::range_to_self(haystack, front, begin),
+                  H::range_to_self(haystack, begin, end),
+                  H::range_to_self(haystack, end, back)))
+        }
+    }
+
+    #[test]
+    fn test_partition() {
+        assert_eq!(partition("key=value=more", "="), Some(("key", "=", "value=more")));
+        assert_eq!(partition("=value", "="), Some(("", "=", "value")));
+        assert_eq!(partition("key=", "="), Some(("key", "=", "")));
+        assert_eq!(partition("key", "="), None);
+        assert_eq!(partition("", "="), None);
+        assert_eq!(partition("a::b", "::"), Some(("a", "::", "b")));
+        assert_eq!(partition("ab", ""), Some(("", "", "ab")));
+        assert_eq!(partition("x1y22", char::is_numeric), Some(("x", "1", "y22")));
+
+        // All three pieces are mutable at the same time
+        let mut bytes = *b"ab=cd";
+        {
+            let (front, m, back) = partition(&mut bytes[..], slice::Ascii(b'=')).unwrap();
+            front[0] = b'A';
+            m[0] = b':';
+            back[1] = b'D';
+            front[1] = b'B';
+        }
+        assert_eq!(&bytes, b"AB:cD");
+        let mut bytes = *b"=ab";
+        {
+            let (front, m, back) = partition(&mut bytes[..], slice::Ascii(b'=')).unwrap();
+            assert!(front.is_empty());
+            m[0] = b'-';
+            back.make_ascii_uppercase();
+        }
+        assert_eq!(&bytes, b"-AB");
+        let mut bytes = *b"ab=";
+        {
+            let (front, m, back) = partition(&mut bytes[..], slice::Ascii(b'=')).unwrap();
+            assert!(back.is_empty());
+            front.make_ascii_uppercase();
+            m[0] = b'!';
+        }
+        assert_eq!(&bytes, b"AB!");
+        assert!(partition(&mut bytes[..], slice::Ascii(b'=')).is_none());
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Token<H> {
+        // A match
+        Sep(H),
+        // A gap between matches
+        Field(H),
+    }
+
+    // Lazily cuts the whole haystack into matches and the gaps between
+    // them, in order, so that the pieces put together are the haystack
+    // again. Gaps are never empty, so adjacent matches yield adjacent
+    // `Sep`s. The same caveat as for `Matches` applies to mutable
+    // haystacks.
+    //
+    // The tokens are the steps of `Searcher::next`, with adjacent rejects
+    // merged into a single field.
+    pub struct Tokenize<H: SearchPtrs, P: Pattern<H>> {
+        searcher: P::Searcher,
+        haystack: H::Haystack,
+        // The step found behind the field yielded last
+        pending: Option<SearchStep<H::Cursor>>,
+    }
+
+    pub fn tokenize<H, P>(haystack: H, pattern: P) -> Tokenize<H, P>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let searcher = pattern.into_searcher(haystack);
+        let haystack = searcher.haystack();
+        Tokenize { searcher, haystack, pending: None }
+    }
+
+    impl<H: SearchPtrs, P: Pattern<H>> Iterator for Tokenize<H, P> {
+        type Item = Token<H>;
+
+        fn next(&mut self) -> Option<Token<H>> {
+            let haystack = self.haystack;
+            let step = match self.pending.take() {
+                Some(step) => step,
+                None => self.searcher.next(),
+            };
+
+            match step {
+                SearchStep::Match(begin, end) => {
+                    Some(Token::Sep(unsafe { H::range_to_self(haystack, begin, end) }))
+                }
+                SearchStep::Reject(begin, mut end) => {
+                    loop {
+                        match self.searcher.next() {
+                            SearchStep::Reject(_, next_end) => end = next_end,
+                            step => {
+                                self.pending = Some(step);
+                                break;
+                            }
+                        }
+                    }
+                    Some(Token::Field(unsafe { H::range_to_self(haystack, begin, end) }))
+                }
+                SearchStep::Done => {
+                    self.pending = Some(SearchStep::Done);
+                    None
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use self::Token::*;
+
+        let comma = string::Ascii::new(b',').unwrap();
+        let line = "a,b,,c d,";
+        let tokens: Vec<_> = tokenize(line, comma).collect();
+        assert_eq!(tokens, vec![Field("a"), Sep(","), Field("b"), Sep(","), Sep(","),
+                                Field("c d"), Sep(",")]);
+
+        for &line in &["", ",", ",,a", "a", "a,,", ",a,b,", "é,,ü"] {
+            let pieces: String = tokenize(line, comma)
+                .map(|token| match token {
+                    Sep(piece) => {
+                        assert_eq!(piece, ",");
+                        piece
+                    }
+                    Field(piece) => {
+                        assert!(!piece.is_empty());
+                        piece
+                    }
+                })
+                .collect();
+            assert_eq!(pieces, line);
+        }
+
+        assert_eq!(tokenize("ab", "").collect::<Vec<_>>(),
+                   vec![Sep(""), Field("a"), Sep(""), Field("b"), Sep("")]);
+        assert_eq!(tokenize("a  b", string::Whitespace).collect::<Vec<_>>(),
+                   vec![Field("a"), Sep("  "), Field("b")]);
+
+        let mut bytes = *b"ab,,cd";
+        for token in tokenize(&mut bytes[..], slice::Ascii(b',')) {
+            match token {
+                Sep(piece) => piece[0] = b';',
+                Field(piece) => piece.make_ascii_uppercase(),
+            }
+        }
+        assert_eq!(&bytes, b"AB;;CD");
+    }
+
+    // Like `split`, but every piece keeps the match ending it. So unlike
+    // with `split`, a match at the very back doesn't leave an empty
+    // last piece.
+    #[cfg(feature = "alloc")]
+    pub fn split_inclusive<H, P>(haystack: H, pattern: P) -> Vec<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+    {
+        let matches = matches_iter(pattern.into_searcher(haystack));
+        let mut ranges = vec![];
+
+        let haystack = matches.haystack();
+
+        let mut last_end = unsafe {
+            H::cursor_at_front(haystack)
+        };
+
+        let mut last = None;
+
+        for (begin, end) in matches {
+            debug_assert_advances::<H>(haystack, last, (begin, end));
+            last = Some((begin, end));
+            ranges.push((last_end, end));
+            last_end = end;
+        }
+
+        if unsafe { H::offset_from_end(haystack, last_end) } != 0 {
+            ranges.push((last_end, unsafe { H::cursor_at_back(haystack) }));
+        }
+
+        ranges_to_pieces::<H>(haystack, ranges)
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_split_inclusive() {
+        use self::string::LineTerminator;
+
+        assert_eq!(split_inclusive("a\nb\r\nc", LineTerminator),
+                   vec!["a\n", "b\r\n", "c"]);
+        assert_eq!(split_inclusive("a\nb\r\n", LineTerminator),
+                   vec!["a\n", "b\r\n"]);
+        assert_eq!(split_inclusive("\n\n", LineTerminator), vec!["\n", "\n"]);
+        assert_eq!(split_inclusive("", LineTerminator), Vec::<&str>::new());
+        for &text in &["a\nb\n\nc", "a\n", "\n", "abc", ""] {
+            assert_eq!(split_inclusive(text, b'\n'),
+                       text.split_inclusive('\n').collect::<Vec<_>>());
+        }
+
+        let mut bytes = *b"ab\ncd\n";
+        {
+            let lines = split_inclusive(&mut bytes[..], slice::Ascii(b'\n'));
+            assert_eq!(lines.len(), 2);
+            for line in lines {
+                line[0] = line[0].to_ascii_uppercase();
+            }
+        }
+        assert_eq!(&bytes, b"Ab\nCd\n");
+    }
+
+    // The lines of a string like `str::lines`: the pieces between
+    // `\n` and `\r\n` terminators, without the empty piece `Split`
+    // yields behind a final terminator.
+    pub struct Lines<'a>(Split<&'a str, string::LineTerminator>);
+
+    impl<'a> Iterator for Lines<'a> {
+        type Item = &'a str;
+
+        fn next(&mut self) -> Option<&'a str> {
+            let line = self.0.next()?;
+            if self.0.finished && line.is_empty() {
+                None
+            } else {
+                Some(line)
+            }
+        }
+    }
+
+    impl<'a> ::core::iter::FusedIterator for Lines<'a> {}
+
+    pub fn lines<'a>(haystack: &'a str) -> Lines<'a> {
+        Lines(Split::new(haystack, string::LineTerminator))
+    }
+
+    #[test]
+    fn test_lines() {
+        let corpus = ["", "\n", "\n\n", "a", "a\n", "a\n\nb", "a\r\nb\r\n",
+                      "\r", "a\r", "\r\r\n", "a\rb\n", "a\n\r\n", "\r\n\n",
+                      "é\r\nü\nx"];
+        for &text in &corpus {
+            assert_eq!(lines(text).collect::<Vec<_>>(),
+                       text.lines().collect::<Vec<_>>(), "{:?}", text);
+        }
+    }
+
+    // Like `split`, but walking the matches from the back, so the
+    // pieces come out in back-to-front order like with `str::rsplit`.
+    #[cfg(feature = "alloc")]
+    pub fn rsplit<H, P>(haystack: H, pattern: P) -> Vec<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        let mut searcher = pattern.into_searcher(haystack);
+        let mut ranges = vec![];
+
+        let haystack = searcher.haystack();
+
+        let mut last_begin = unsafe {
+            H::cursor_at_back(haystack)
+        };
+
+        while let Some((begin, end)) = searcher.next_match_back() {
+            debug_assert!(unsafe {
+                H::cursor_cmp(haystack, end, last_begin)
+            } != Ordering::Greater, "rsplit needs non-overlapping matches");
+            ranges.push((end, last_begin));
+            last_begin = begin;
+        }
+
+        let begin = unsafe { H::cursor_at_front(haystack) };
+        ranges.push((begin, last_begin));
+
+        ranges_to_pieces::<H>(haystack, ranges)
+    }
+
+    // Like `rsplit`, but with at most `n` pieces: after `n - 1` matches
+    // the rest of the front is left unsearched and becomes the last piece.
+    #[cfg(feature = "alloc")]
+    pub fn rsplitn<H, P>(haystack: H, n: usize, pattern: P) -> Vec<H>
+        where H: SearchPtrs,
+              P: Pattern<H>,
+              P::Searcher: ReverseSearcher<H>,
+    {
+        if n == 0 {
+            return vec![];
+        }
+
+        let mut searcher = pattern.into_searcher(haystack);
+        let mut ranges = vec![];
+
+        let haystack = searcher.haystack();
+
+        let mut last_begin = unsafe {
+            H::cursor_at_back(haystack)
+        };
+
+        while ranges.len() + 1 < n {
+            let (begin, end) = match searcher.next_match_back() {
+                Some(range) => range,
+                None => break,
+            };
+            debug_assert!(unsafe {
+                H::cursor_cmp(haystack, end, last_begin)
+            } != Ordering::Greater, "rsplitn needs non-overlapping matches");
+            ranges.push((end, last_begin));
+            last_begin = begin;
+        }
+
+        let begin = unsafe { H::cursor_at_front(haystack) };
+        ranges.push((begin, last_begin));
+
+        ranges_to_pieces::<H>(haystack, ranges)
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_rsplitn() {
+        for &text in &["key=value=more", "key", "", "=", "a==b=", "=x="] {
+            for n in 0..5 {
+                assert_eq!(rsplitn(text, n, b'='),
+                           text.rsplitn(n, '=').collect::<Vec<_>>());
+            }
+        }
+
+        let slice = &mut {*b"a,b,c,d"}[..];
+
+        {
+            let rsplitn = rsplitn(&mut*slice, 3, slice::Ascii(b','));
+            assert_eq!(rsplitn.len(), 3);
+
+            for (i, m) in rsplitn.into_iter().enumerate() {
+                for byte in m {
+                    *byte = b'0' + i as u8;
+                }
+            }
+        }
+
+        // The front remainder keeps its delimiter
+        assert_eq!(slice, b"222,1,0");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_rsplit() {
+        assert_eq!(rsplit("hangman", string::Ascii::new(b'a').unwrap()),
+                   vec!["n", "ngm", "h"]);
+        assert_eq!(rsplit(",a,,b,", b','), vec!["", "b", "", "a", ""]);
+        assert_eq!(rsplit("", b','), vec![""]);
+
+        let slice = &mut {*b"hangman"}[..];
+
+        {
+            let rsplit = rsplit(&mut*slice, slice::Ascii(b'a'));
+            assert_eq!(rsplit.len(), 3);
+
+            for (i, m) in rsplit.into_iter().enumerate() {
+                for byte in m {
+                    *byte = b'0' + i as u8;
+                }
+            }
+        }
+
+        assert_eq!(slice, b"2a111a0");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_split() {
+        assert_eq!(split("hangman", string::Ascii::new(b'a').unwrap()),
+                   vec!["h", "ngm", "n"]);
+
+        let mut slice = &mut {*b"hangman"}[..];
+
+        {
+            let split = split(&mut*slice, slice::Ascii(b'a'));
+
+            for m in split {
+                for byte in m {
+                    *byte = b'-';
+                }
+            }
+        }
+
+        assert_eq!(slice, b"-a---a-");
+    }
+
+    // Mostly of interest under Miri: every piece has to stay usable
+    // while the others are written to.
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_mut_pieces_interleaved() {
+        let mut bytes = *b"ab,cd,ef";
+        {
+            let mut pieces = split(&mut bytes[..], slice::Ascii(b','));
+            let (first, rest) = pieces.split_at_mut(1);
+            let (second, third) = rest.split_at_mut(1);
+            for i in 0..2 {
+                third[0][i] = first[0][i].to_ascii_uppercase();
+                first[0][i] = second[0][i];
+                second[0][i] = third[0][i] ^ 0x20;
+            }
+            assert_eq!(first[0], b"cd");
+        }
+        assert_eq!(&bytes, b"cd,ab,AB");
+
+        let mut numbers = [1u32, 0, 2, 3, 0, 4];
+        {
+            let mut pieces = split(&mut numbers[..], generic_slice::Elem(0));
+            let last = pieces.pop().unwrap();
+            for piece in pieces.iter_mut().rev() {
+                for n in piece.iter_mut() {
+                    *n += last[0];
+                }
+                last[0] += 1;
+            }
+        }
+        assert_eq!(numbers, [6, 0, 6, 7, 0, 6]);
+
+        let mut text = String::from("one two three");
+        {
+            let mut pieces = split(&mut text[..], ' ');
+            let first = pieces.remove(0);
+            first.make_ascii_uppercase();
+            pieces[1].make_ascii_uppercase();
+            assert_eq!(&*first, "ONE");
+            pieces[0].make_ascii_uppercase();
+        }
+        assert_eq!(text, "ONE TWO THREE");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_ascii_no_case() {
+        assert_eq!(match_indices("LOG ENTRY: ERROR", string::AsciiNoCase::new(b'e').unwrap()),
+                   vec![(4, "E"), (11, "E")]);
+        assert_eq!(split("a-b_c", string::AsciiNoCase::new(b'-').unwrap()), vec!["a", "b_c"]);
+        assert!(string::AsciiNoCase::new(b'e').unwrap().is_prefix_of("Entry"));
+        assert!(!string::AsciiNoCase::new(b'e').unwrap().is_prefix_of(""));
+
+        // Bytes of multi-byte chars could split them, so they are refused
+        assert!(string::AsciiNoCase::new(0xA9).is_none());
+        let a = string::AsciiNoCase::new(b'a').unwrap();
+        assert_eq!(split("éaÉAé", a), vec!["é", "É", "é"]);
+        assert_eq!(rsplit("éaÉ", a), vec!["É", "é"]);
+
+        let slice = &mut {*b"EXECUTE"}[..];
+
+        {
+            let match_indices = match_indices(&mut*slice, slice::AsciiNoCase(b'e'));
+
+            assert_eq!(match_indices.iter().map(|x| x.0).collect::<Vec<_>>(),
+                       vec![0, 2, 6]);
+
+            for m in match_indices {
+                m.1[0] = b'3';
+            }
+        }
+
+        assert_eq!(slice, b"3X3CUT3");
+        assert!(slice::AsciiNoCase(b'x').is_prefix_of(&mut {*b"Xy"}[..]));
+        assert!(!slice::AsciiNoCase(b'x').is_prefix_of(&mut [][..]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_ascii_set() {
+        let whitespace = string::AsciiSet::new(b" \t\r\n");
+        for &haystack in &["a b\tc\r\nd", " é\nx ", "", "none"] {
+            assert_eq!(split(haystack, whitespace),
+                       haystack.split(&[' ', '\t', '\r', '\n'][..])
+                               .collect::<Vec<_>>());
+        }
+
+        let mut searcher = whitespace.into_searcher("é a");
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "é");
+        }
+
+        let whitespace: slice::AsciiSet = b" \t\r\n".iter().cloned().collect();
+        let slice = &mut {*b"ab\tc\r\nd\xff"}[..];
+
+        {
+            let split = split(&mut*slice, whitespace);
+
+            assert_eq!(split.len(), 4);
+            for m in split {
+                for byte in m {
+                    *byte = b'-';
+                }
+            }
+        }
+
+        assert_eq!(slice, b"--\t-\r\n--");
+        assert!(slice::AsciiSet::new(&[0xff]).is_prefix_of(&mut {[0xff]}[..]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ascii_set_rejects_non_ascii() {
+        string::AsciiSet::new(&[b'a', 0xc3]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_bytes_pattern() {
+        let slice = &mut {*b"GET / HTTP/1.1\r\nHost: x\r\n\r\nbody"}[..];
+
+        {
+            let split = split(&mut*slice, &b"\r\n\r\n"[..]);
+
+            assert_eq!(split.len(), 2);
+            for m in split {
+                for byte in m {
+                    *byte = b'-';
+                }
+            }
+        }
+
+        assert_eq!(slice, &b"-----------------------\r\n\r\n----"[..]);
+
+        let slice = &mut {*b"aaaaa"}[..];
+        assert_eq!(match_indices(&mut*slice, &b"aa"[..])
+                       .iter().map(|x| x.0).collect::<Vec<_>>(),
+                   vec![0, 2]);
+        assert_eq!(match_indices(&mut*slice, &b"aaaaaa"[..]).len(), 0);
+        assert_eq!(match_indices(&mut [][..], &b"a"[..]).len(), 0);
+
+        assert!((&b"aa"[..]).is_prefix_of(&mut*slice));
+        assert!(!(&b"ab"[..]).is_prefix_of(&mut*slice));
+        assert!(!(&b"aaaaaa"[..]).is_prefix_of(&mut*slice));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_char_bytes_pattern() {
+        let mut buf = "café, résumé, e".as_bytes().to_vec();
+
+        {
+            let matches = match_indices(&mut buf[..], 'é');
+
+            assert_eq!(matches.iter().map(|m| m.0).collect::<Vec<_>>(),
+                       vec![3, 8, 13]);
+            for m in matches {
+                m.1.copy_from_slice("è".as_bytes());
+            }
+        }
+
+        assert_eq!(buf, "cafè, rèsumè, e".as_bytes());
+        assert!('c'.is_prefix_of(&mut buf[..]));
+        assert_eq!(split(&mut buf[..], 'è').len(), 4);
+        assert_eq!(match_indices(&mut buf[..], 'e').len(), 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_byte_predicate_pattern() {
+        use std::collections::HashSet;
+
+        let slice = &mut {*b"a\r\nb\nc"}[..];
+        assert_eq!(split(&mut*slice, |b| b == b'\n' || b == b'\r').len(), 4);
+
+        let separators = b",;".iter().cloned().collect::<HashSet<u8>>();
+        let slice = &mut {*b"ab,c;;d"}[..];
+
+        {
+            let split = split(&mut*slice, |b| separators.contains(&b));
+
+            assert_eq!(split.iter().map(|x| x.len()).collect::<Vec<_>>(),
+                       vec![2, 1, 0, 1]);
+            for m in split {
+                for byte in m {
+                    *byte = b'-';
+                }
+            }
+        }
+
+        assert_eq!(slice, b"--,-;;-");
+        assert!((|b| b == b'-').is_prefix_of(&mut*slice));
+
+        let mut searcher = (|b: u8| b == b';').into_searcher(&mut*slice);
+        let hs = searcher.haystack();
+        let (begin, _) = searcher.next_match_back().unwrap();
+        unsafe {
+            assert_eq!(<&mut [u8]>::offset_from_start(hs, begin), 5);
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_shared_slice() {
+        let haystack = &b"banana"[..];
+
+        let matches = match_indices(haystack, slice::Ascii(b'a'));
+        assert_eq!(matches, vec![(1, &b"a"[..]), (3, b"a"), (5, b"a")]);
+        assert_eq!(split(haystack, slice::Ascii(b'n')),
+                   vec![&b"ba"[..], b"a", b"a"]);
+        assert_eq!(split(haystack, |b| b == b'b' || b == b'n'),
+                   vec![&b""[..], b"a", b"a", b"a"]);
+        // The haystack is still usable, as it is only borrowed
+        assert_eq!(haystack, b"banana");
+
+        let mut searcher = slice::Ascii(b'a').into_searcher(haystack);
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_match_back().unwrap();
+            assert_eq!(<&[u8]>::offset_from_start(hs, m.0), 5);
+            let m = searcher.next_reject_back().unwrap();
+            assert_eq!(<&[u8]>::range_to_self(hs, m.0, m.1), b"n");
+            let m = searcher.next_match().unwrap();
+            assert_eq!(<&[u8]>::offset_from_start(hs, m.0), 1);
+        }
+
+        let mut searcher = (|b| b == b'n').into_searcher(haystack);
+        assert!(searcher.next_match_back().is_some());
+        assert!(searcher.next_match_back().is_some());
+        assert!(searcher.next_match_back().is_none());
+
+        assert!(slice::Ascii(b'b').is_prefix_of(haystack));
+        assert!(slice::Ascii(b'a').is_suffix_of(haystack));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_generic_slice() {
+        use self::generic_slice::Elem;
+
+        let haystack = &[1u32, 0, 2, 3, 0, 0, 4][..];
+        assert_eq!(match_indices(haystack, Elem(0)),
+                   vec![(1, &[0u32][..]), (4, &[0][..]), (5, &[0][..])]);
+        assert_eq!(split(haystack, Elem(0)),
+                   vec![&[1u32][..], &[2, 3], &[], &[4]]);
+        assert!(Elem(1).is_prefix_of(haystack));
+        assert!(Elem(4).is_suffix_of(haystack));
+
+        let mut searcher = Elem(0).into_searcher(haystack);
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_match_back().unwrap();
+            assert_eq!(<&[u32]>::offset_from_start(hs, m.0), 5);
+            let m = searcher.next_reject_back().unwrap();
+            assert_eq!(<&[u32]>::range_to_self(hs, m.0, m.1), &[3]);
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&[u32]>::offset_from_start(hs, m.0), 0);
+        }
+
+        let words: Vec<String> = "a b - c - - d".split(' ').map(String::from).collect();
+        let dash = String::from("-");
+        let pat = &Elem(dash.clone());
+        let pieces = split(&words[..], pat);
+        assert_eq!(pieces, vec![&words[..2], &words[3..4], &[], &words[6..]]);
+        assert_eq!(match_indices(&words[..], Elem(dash)).iter()
+                       .map(|m| m.0).collect::<Vec<_>>(),
+                   vec![2, 4, 5]);
+
+        // Zero-sized elements still get distinct positions
+        let units = &[(), (), ()][..];
+        assert_eq!(match_indices(units, Elem(())).iter()
+                       .map(|m| m.0).collect::<Vec<_>>(),
+                   vec![0, 1, 2]);
+        assert_eq!(split(units, Elem(())).len(), 4);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_generic_mut_slice() {
+        use self::generic_slice::Elem;
+
+        let mut buffer = vec![5u32, 3, 0, 9, 7, 8, 0, 0, 2, 1];
+
+        {
+            let pieces = split(&mut buffer[..], Elem(0));
+            assert_eq!(pieces.len(), 4);
+
+            for piece in pieces {
+                piece.sort();
+                for elem in piece.iter_mut() {
+                    *elem *= 10;
+                }
+            }
+        }
+
+        assert_eq!(buffer, [30, 50, 0, 70, 80, 90, 0, 0, 10, 20]);
+
+        let mut searcher = Elem(0).into_searcher(&mut buffer[..]);
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_match_back().unwrap();
+            assert_eq!(<&mut [u32]>::offset_from_start(hs, m.0), 7);
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&mut [u32]>::range_to_self(hs, m.0, m.1), &[30]);
+        }
+
+        let mut units = [(), ()];
+        assert_eq!(split(&mut units[..], Elem(())).len(), 3);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_utf16() {
+        use self::utf16::Unit;
+
+        // "a😀b-c😀", the emoji is the surrogate pair D83D DE00
+        let wide: Vec<u16> = "a\u{1F600}b-c\u{1F600}".encode_utf16().collect();
+        let haystack = &wide[..];
+        assert_eq!(haystack.len(), 8);
+
+        assert_eq!(split(haystack, Unit(b'-' as u16)),
+                   vec![&wide[..4], &wide[5..]]);
+        assert_eq!(match_indices(haystack, Unit(0xDE00)).iter()
+                       .map(|m| m.0).collect::<Vec<_>>(),
+                   vec![2, 7]);
+
+        // The char pattern matches the whole surrogate pair
+        assert_eq!(match_indices(haystack, '\u{1F600}'),
+                   vec![(1, &wide[1..3]), (6, &wide[6..8])]);
+        assert_eq!(split(haystack, '\u{1F600}'),
+                   vec![&wide[..1], &wide[3..6], &[]]);
+        assert_eq!(match_indices(haystack, 'c'), vec![(5, &wide[5..6])]);
+        assert!('a'.is_prefix_of(haystack));
+        assert!(!'\u{1F600}'.is_prefix_of(haystack));
+
+        // BMP chars never match inside a surrogate pair, unlike units
+        assert_eq!(match_indices(haystack, Unit(0xD83D)).len(), 2);
+        for &c in &['\u{D7FF}', '\u{E000}', '\u{FFFD}', '\u{F600}'] {
+            assert!(match_indices(haystack, c).is_empty());
+        }
+
+        let needle = &wide[3..6];
+        assert_eq!(match_indices(haystack, needle), vec![(3, needle)]);
+        assert_eq!(split(haystack, &[][..]).len(), 10);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_c_string() {
+        use std::ffi::CStr;
+        use self::c_string::CStrSlice;
+
+        let path = CStr::from_bytes_with_nul(b"/usr/bin:/bin\0").unwrap();
+        let haystack = CStrSlice::new(path);
+
+        let pieces: Vec<_> = split(haystack, slice::Ascii(b':')).iter()
+            .map(|p| p.as_bytes())
+            .collect();
+        assert_eq!(pieces, vec![&b"/usr/bin"[..], b"/bin"]);
+        assert_eq!(split(haystack, slice::Ascii(b'/')).len(), 4);
+        assert_eq!(match_indices(haystack, slice::Ascii(b':'))[0].0, 8);
+        assert!(match_indices(haystack, slice::Ascii(0)).is_empty());
+        assert!(slice::Ascii(b'/').is_prefix_of(haystack));
+        assert!(slice::Ascii(b'n').is_suffix_of(haystack));
+
+        let mut searcher = slice::Ascii(b':').into_searcher(haystack);
+        let hs = searcher.haystack();
+        let (begin, _) = searcher.next_match_back().unwrap();
+        unsafe {
+            let back = CStrSlice::cursor_at_back(hs);
+            assert_eq!(CStrSlice::range_to_self(hs, begin, back).as_bytes(), b":/bin");
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_indexed() {
+        use self::generic::{Indexed, Not, Or};
+        use self::string::{Ascii, CharRange, Glob, Whitespace};
+
+        let haystack = "Fünf Äpfel, zwei Birnen und 12 Kiwis\r\n";
+        let indexed = Indexed(haystack);
+
+        macro_rules! assert_same {
+            ($($pattern:expr),*) => {$(
+                assert_eq!(match_ranges(indexed, $pattern),
+                           match_ranges(haystack, $pattern));
+                assert_eq!(split(indexed, $pattern).iter()
+                               .map(|piece| piece.0).collect::<Vec<_>>(),
+                           split(haystack, $pattern));
+            )*}
+        }
+
+        assert_same!(Ascii::new(b' ').unwrap(), "zwei", "", |c: char| c == 'ü',
+                     CharRange('a'..='f'), Whitespace, Glob("K*s"),
+                     Or(Ascii::new(b',').unwrap(), string::classes::Digit),
+                     Not(string::AsciiSet::new(b"aeiou")));
+        assert_eq!(match_ranges(indexed, "Birnen"), vec![19..25]);
+        assert_eq!(match_indices(indexed, "e").iter()
+                       .map(|m| m.0).collect::<Vec<_>>(),
+                   vec![10, 16, 23]);
+        assert!("Fünf".is_prefix_of(indexed));
+        assert!(string::LineTerminator.is_suffix_of(indexed));
+
+        let numbers = &[3u32, 1, 4, 1, 5][..];
+        assert_eq!(match_ranges(Indexed(numbers), generic_slice::Elem(1)),
+                   match_ranges(numbers, generic_slice::Elem(1)));
+        assert_eq!(match_ranges(Indexed(&b"a,b,,c"[..]), slice::Ascii(b',')),
+                   vec![1..2, 3..4, 4..5]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_chain() {
+        use self::chain::Chain;
+        use self::generic::{Or, Then};
+
+        let haystack = Chain("one two thr", "ee four");
+        let space = string::Ascii::new(b' ').unwrap();
+
+        let pieces = split(haystack, space);
+        assert_eq!(pieces, vec![Chain("one", ""), Chain("two", ""),
+                                Chain("thr", "ee"), Chain("four", "")]);
+        assert_eq!(pieces[2].concat(), "three");
+
+        // Needles straddling the seam, with every possible split
+        for &needle in &["r", "re", "hre", "three", "ee f", "o three fo"] {
+            let expected = match_ranges("one two three four", needle);
+            assert_eq!(match_ranges(haystack, needle), expected);
+        }
+        assert_eq!(match_indices(haystack, "three"), vec![(8, Chain("thr", "ee"))]);
+        assert_eq!(split(haystack, "e"),
+                   vec![Chain("on", ""), Chain(" two thr", ""), Chain("", ""),
+                        Chain(" four", "")]);
+
+        // Matches can't extend past either end of the chain
+        assert!(match_indices(Chain("ab", "c"), "abcd").is_empty());
+
+        // Combinators restart their parts before, across and after the seam
+        for mid in 0..7 {
+            let (front, back) = "abcbcb".split_at(mid);
+            assert_eq!(match_ranges(Chain(front, back), Or("ab", "bcb")), vec![0..2, 3..6]);
+            assert_eq!(match_ranges(Chain(front, back), Then("c", "b")), vec![2..4, 4..6]);
+            assert_eq!(match_ranges(Chain(front, back), Then("bc", "b")), vec![1..4]);
+        }
+        assert_eq!(match_ranges(Chain("", "abc"), "bc"), vec![1..3]);
+        assert_eq!(match_ranges(Chain("abc", ""), "bc"), vec![1..3]);
+
+        let chars = Chain("aé", "ü");
+        assert_eq!(match_ranges(chars, ""), vec![0..0, 1..1, 3..3, 5..5]);
+        assert_eq!(split(chars, "").len(), 5);
+
+        assert!("one two".is_prefix_of(haystack));
+        assert!(!"one three".is_prefix_of(haystack));
+        assert!(string::Ascii::new(b'e').unwrap().is_prefix_of(Chain("", "ee")));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_char_slice() {
+        let text = "grüße, welt, ,ünd mehr";
+        let chars: Vec<char> = text.chars().collect();
+        let haystack = &chars[..];
+
+        let pieces: Vec<String> = split(haystack, ',').iter()
+            .map(|piece| piece.iter().collect())
+            .collect();
+        assert_eq!(pieces, split(text, |c| c == ','));
+
+        // Offsets are char indices, unlike the byte offsets of `&str`
+        assert_eq!(match_ranges(haystack, 'ü'), vec![2..3, 14..15]);
+        assert_eq!(match_ranges(text, |c| c == 'ü'), vec![2..4, 16..18]);
+
+        let needle = &['e', 'l', 't'][..];
+        assert_eq!(match_indices(haystack, needle), vec![(8, needle)]);
+        assert_eq!(split(haystack, &[','][..]).len(), 4);
+        assert_eq!(match_ranges(haystack, |c: char| !c.is_alphabetic()).len(), 6);
+
+        assert!('g'.is_prefix_of(haystack));
+        assert!('r'.is_suffix_of(haystack));
+        assert!((|c: char| c == 'r').is_suffix_of(haystack));
+        assert!((&['g', 'r'][..]).is_prefix_of(haystack));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_segments() {
+        use std::borrow::Cow;
+        use self::generic::{Or, Then};
+        use self::segments::Segments;
+
+        let list = ["a:", ":b::c", ":", ":d"];
+        let haystack = Segments::new(&list);
+
+        // The first and the last delimiter span two segments
+        assert_eq!(split(haystack.clone(), "::"),
+                   vec![Segments::Borrowed("a"), Segments::Borrowed("b"),
+                        Segments::Borrowed("c"), Segments::Borrowed("d")]);
+        assert_eq!(match_ranges(haystack.clone(), "::"), vec![1..3, 4..6, 7..9]);
+
+        // Pieces spanning segments list their parts
+        let list = ["ab", "cd::e", "f"];
+        assert_eq!(split(Segments::new(&list), "::"),
+                   vec![Segments::List(Cow::Owned(vec!["ab", "cd"])),
+                        Segments::List(Cow::Owned(vec!["e", "f"]))]);
+        let list = ["x", "", "y", "z"];
+        assert_eq!(match_indices(Segments::new(&list), "xyz"),
+                   vec![(0, Segments::List(Cow::Owned(vec!["x", "y", "z"])))]);
+
+        // Every way to cut the text into three segments behaves like the `&str`
+        let text = "one::two:::three";
+        let step = if cfg!(miri) { 4 } else { 1 };
+        for i in (0..text.len() + 1).step_by(step) {
+            for j in (i..text.len() + 1).step_by(step) {
+                let list = [&text[..i], &text[i..j], &text[j..]];
+                let haystack = Segments::new(&list);
+                for &needle in &["::", ":::", "o", "wo::", "one::two:::three", "x", ""] {
+                    let pieces: Vec<String> = split(haystack.clone(), needle).iter()
+                        .map(|piece| piece.concat())
+                        .collect();
+                    assert_eq!(pieces, text.split(needle).collect::<Vec<_>>());
+                    assert_eq!(match_ranges(haystack.clone(), needle),
+                               match_ranges(text, needle));
+                }
+                // Combinators restart their parts on sub-haystacks
+                assert_eq!(match_ranges(haystack.clone(), Or("o::", ":t")),
+                           match_ranges(text, Or("o::", ":t")));
+                assert_eq!(match_ranges(haystack.clone(), Then(":", "t")),
+                           match_ranges(text, Then(":", "t")));
+                let colon = string::Ascii::new(b':').unwrap();
+                assert_eq!(match_ranges(haystack, colon), match_ranges(text, colon));
+            }
+        }
+
+        let list = ["é", "ü"];
+        assert_eq!(match_ranges(Segments::new(&list), ""), vec![0..0, 2..2, 4..4]);
+        assert!(split(Segments::new(&[]), "x") == vec![Segments::Borrowed("")]);
+        assert!("a::".is_prefix_of(Segments::new(&["a:", ":b"])));
+        assert!(string::Ascii::new(b'b').unwrap().is_prefix_of(Segments::Borrowed("b")));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_os_string() {
+        use std::ffi::OsStr;
+        use self::os_string::Ascii;
+
+        let path = OsStr::new("/usr/lib/libfoo.so.1");
+        let slash = Ascii::new(b'/').unwrap();
+        let dot = Ascii::new(b'.').unwrap();
+
+        assert_eq!(split(path, slash), vec!["", "usr", "lib", "libfoo.so.1"]);
+        assert_eq!(split(path, dot), vec!["/usr/lib/libfoo", "so", "1"]);
+        assert_eq!(match_indices(path, dot).iter().map(|m| m.0).collect::<Vec<_>>(),
+                   vec![15, 18]);
+        assert!(slash.is_prefix_of(path));
+        assert!(!dot.is_suffix_of(path));
+        assert!(Ascii::new(0x80).is_none());
+
+        let mut searcher = slash.into_searcher(path);
+        let hs = searcher.haystack();
+        let (begin, end) = searcher.next_reject_back().unwrap();
+        unsafe {
+            assert_eq!(<&OsStr>::range_to_self(hs, begin, end), "1");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_os_str_pattern() {
+        use std::ffi::OsStr;
+
+        let name = OsStr::new("report.tmp.old.tmp");
+        let tmp = OsStr::new(".tmp");
+
+        assert_eq!(match_indices(name, tmp),
+                   vec![(6, tmp), (14, tmp)]);
+        assert_eq!(split(name, tmp), vec!["report", ".old", ""]);
+        assert!(OsStr::new("report").is_prefix_of(name));
+        assert!(!OsStr::new("tmp").is_prefix_of(name));
+        assert_eq!(split(OsStr::new("ab"), OsStr::new("")), vec!["", "a", "b", ""]);
+    }
+
+    #[cfg(all(unix, feature = "std"))]
+    #[test]
+    fn test_os_str_pattern_unix() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        // Not valid UTF-8, but still split at any byte
+        let name = OsStr::from_bytes(b"caf\xE9.tmp\xFF.tmp");
+        let pieces = split(name, OsStr::new(".tmp"));
+        assert_eq!(pieces, vec![OsStr::from_bytes(b"caf\xE9"),
+                                OsStr::from_bytes(b"\xFF"),
+                                OsStr::new("")]);
+        assert_eq!(match_indices(name, OsStr::from_bytes(b"\xE9")).len(), 1);
+        assert_eq!(split(OsStr::from_bytes(b"\xC3\xA9"), OsStr::new("")).len(), 4);
+    }
+
+    #[cfg(all(windows, feature = "std"))]
+    #[test]
+    fn test_os_str_pattern_windows() {
+        use std::ffi::{OsStr, OsString};
+        use std::os::windows::ffi::OsStringExt;
+
+        // "a", an unpaired lead surrogate, ".tmp", a paired surrogate
+        let name = OsString::from_wide(&[0x61, 0xD83D, 0x2E, 0x74, 0x6D, 0x70,
+                                         0xD83D, 0xDE00]);
+        let lone = OsString::from_wide(&[0xD83D]);
+
+        let pieces = split(&*name, OsStr::new(".tmp"));
+        assert_eq!(pieces, vec![&*OsString::from_wide(&[0x61, 0xD83D]),
+                                &*OsString::from_wide(&[0xD83D, 0xDE00])]);
+        // The lone surrogate doesn't match inside the pair
+        assert_eq!(match_indices(&*name, &*lone).len(), 1);
+        // Empty matches only fall on code point boundaries
+        assert_eq!(split(&*name, OsStr::new("")).len(), 9);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_bytes_pattern_empty_needle() {
+        let slice = &mut {*b"abc"}[..];
+        assert_eq!(match_indices(&mut*slice, &b""[..])
+                       .iter().map(|x| x.0).collect::<Vec<_>>(),
+                   vec![0, 1, 2, 3]);
+
+        {
+            let split = split(&mut*slice, &b""[..]);
+
+            assert_eq!(split.iter().map(|x| x.len()).collect::<Vec<_>>(),
+                       vec![0, 1, 1, 1, 0]);
+            for m in split {
+                for byte in m {
+                    *byte = b'-';
+                }
+            }
+        }
+
+        assert_eq!(slice, b"---");
+        assert_eq!(split(&mut [][..], &b""[..]).len(), 2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_char_classes() {
+        use self::string::classes::{Alpha, AlphaNum, Digit, HexDigit};
+
+        assert_eq!(split("abc123def", Digit), vec!["abc", "", "", "def"]);
+        assert_eq!(match_indices("a1é½", Alpha), vec![(0, "a"), (2, "é")]);
+        assert_eq!(match_indices("a1é½-", AlphaNum).len(), 4);
+        assert_eq!(split("0xfF_9g", HexDigit), vec!["", "x", "", "_", "g"]);
+
+        assert!(Digit.is_prefix_of("1a"));
+        assert!(Alpha.is_suffix_of("1é"));
+        assert!(!Alpha.is_suffix_of("é1"));
+
+        let mut searcher = Digit.into_searcher("ab12cd3");
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_match_back().unwrap();
+            assert_eq!(<&str>::offset_from_start(hs, m.0), 6);
+            let m = searcher.next_reject_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "d");
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_char_range() {
+        use self::string::CharRange;
+
+        let haystack = "abγδe ωΩ";
+        assert_eq!(match_indices(haystack, CharRange('α'..='ω')),
+                   vec![(2, "γ"), (4, "δ"), (8, "ω")]);
+        assert_eq!(split(haystack, CharRange('a'..='z')),
+                   haystack.split(|c: char| c.is_ascii_lowercase())
+                           .collect::<Vec<_>>());
+
+        let mut searcher = CharRange('α'..='ω').into_searcher(haystack);
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_match_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "ω");
+            let m = searcher.next_reject_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), " ");
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "a");
+        }
+
+        assert!(CharRange('α'..='ω').is_prefix_of("γa"));
+        assert!(CharRange('α'..='ω').is_suffix_of("aω"));
+        assert!(!CharRange('α'..='ω').is_suffix_of("aΩ"));
+        assert!(!CharRange('α'..='ω').is_suffix_of(""));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_whitespace() {
+        use self::string::Whitespace;
+
+        assert_eq!(split("  a \t b\u{a0}c ", Whitespace),
+                   vec!["", "a", "b", "c", ""]);
+        assert_eq!(split("a", Whitespace), vec!["a"]);
+        assert_eq!(split("", Whitespace), vec![""]);
+        assert_eq!(match_indices("a \n b", Whitespace), vec![(1, " \n ")]);
+
+        let haystack = "ab  cd\te";
+        let mut searcher = Whitespace.into_searcher(haystack);
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "ab");
+            let m = searcher.next_reject_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "e");
+            let m = searcher.next_match_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "\t");
+            let m = searcher.next_match().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "  ");
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "cd");
+        }
+        assert_eq!(searcher.next_match(), None);
+        assert_eq!(searcher.next_reject_back(), None);
+
+        assert!(Whitespace.is_prefix_of("\u{a0}x"));
+        assert!(Whitespace.is_suffix_of("x\n"));
+        assert!(!Whitespace.is_suffix_of(""));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_ascii_whitespace() {
+        use self::slice::AsciiWhitespace;
+
+        let slice = &mut {*b" \tab \x0b\x0ccd\r\ne\n"}[..];
+
+        {
+            let split = split(&mut*slice, AsciiWhitespace);
+
+            assert_eq!(split.iter().map(|x| &x[..]).collect::<Vec<_>>(),
+                       vec![&b""[..], b"ab", b"cd", b"e", b""]);
+            for (i, m) in split.into_iter().enumerate() {
+                for byte in m {
+                    *byte = b'0' + i as u8;
+                }
+            }
+        }
+
+        assert_eq!(slice, b" \t11 \x0b\x0c22\r\n3\n");
+        assert_eq!(split(&mut [][..], AsciiWhitespace).len(), 1);
+
+        let mut searcher = AsciiWhitespace.into_searcher(&mut*slice);
+        let hs = searcher.haystack();
+        let mut runs = vec![];
+        while let Some((begin, end)) = searcher.next_match_back() {
+            unsafe {
+                runs.push((<&mut [u8]>::offset_from_start(hs, begin),
+                           <&mut [u8]>::offset_from_start(hs, end)));
+            }
+        }
+        assert_eq!(runs, vec![(12, 13), (9, 11), (4, 7), (0, 2)]);
+
+        assert!(AsciiWhitespace.is_prefix_of(&mut*slice));
+        assert!(AsciiWhitespace.is_suffix_of(&mut*slice));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_digit_run() {
+        use self::string::DigitRun;
+
+        assert_eq!(match_indices("12 apples, 345 pears: 6", DigitRun),
+                   vec![(0, "12"), (11, "345"), (22, "6")]);
+        assert_eq!(split("12é3", DigitRun), vec!["", "é", ""]);
+        assert_eq!(match_indices("2024", DigitRun), vec![(0, "2024")]);
+        assert_eq!(split("2024", DigitRun), vec!["", ""]);
+        assert_eq!(match_indices("none", DigitRun), vec![]);
+
+        let mut searcher = DigitRun.into_searcher("1a22bb333");
+        let hs = searcher.haystack();
+        let mut runs = vec![];
+        while let Some((begin, end)) = searcher.next_match_back() {
+            unsafe {
+                runs.push(<&str>::range_to_self(hs, begin, end));
+            }
+        }
+        assert_eq!(runs, vec!["333", "22", "1"]);
+
+        let mut searcher = DigitRun.into_searcher("1a22bb333");
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_reject_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "bb");
+        }
+
+        assert!(DigitRun.is_prefix_of("1a"));
+        assert!(DigitRun.is_suffix_of("a1"));
+        assert!(!DigitRun.is_suffix_of("1a"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_line_terminator() {
+        use self::string::LineTerminator;
+
+        assert_eq!(split("a\r\nb\nc\r\n", LineTerminator), vec!["a", "b", "c", ""]);
+        assert_eq!(split("a\rb\r\r\n", LineTerminator), vec!["a\rb\r", ""]);
+        assert_eq!(match_indices("\r\n\n", LineTerminator),
+                   vec![(0, "\r\n"), (2, "\n")]);
+
+        let haystack = "a\r\nb\nc\r\n";
+        let mut searcher = LineTerminator.into_searcher(haystack);
+        let hs = searcher.haystack();
+        let mut back = vec![];
+        while let Some((begin, end)) = searcher.next_match_back() {
+            unsafe {
+                back.push((<&str>::offset_from_start(hs, begin),
+                           <&str>::range_to_self(hs, begin, end)));
+            }
+        }
+        assert_eq!(back, vec![(6, "\r\n"), (4, "\n"), (1, "\r\n")]);
+
+        let mut searcher = LineTerminator.into_searcher(haystack);
+        let mut rejects = vec![];
+        while let Some((begin, end)) = searcher.next_reject_back() {
+            unsafe {
+                rejects.push(<&str>::range_to_self(hs, begin, end));
+            }
+        }
+        assert_eq!(rejects, vec!["c", "b", "a"]);
+
+        assert!(LineTerminator.is_prefix_of("\r\nx"));
+        assert!(!LineTerminator.is_prefix_of("\rx"));
+        assert!(LineTerminator.is_suffix_of("x\n"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_not() {
+        use self::generic::Not;
+
+        assert_eq!(split("a,b,,c", Not(string::Ascii::new(b',').unwrap())),
+                   vec!["", ",", ",,", ""]);
+        assert_eq!(match_indices("aé,", Not(string::Ascii::new(b',').unwrap())),
+                   vec![(0, "a"), (1, "é")]);
+
+        // For a substring needle the gaps become the matches
+        assert_eq!(match_indices("xxabyab", Not("ab")),
+                   vec![(0, "xx"), (4, "y")]);
+        assert_eq!(split("xxabyab", Not("ab")), vec!["", "ab", "ab"]);
+
+        let mut searcher = Not("ab").into_searcher("xxabyab");
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "ab");
+        }
+
+        assert!(Not(string::Ascii::new(b',').unwrap()).is_prefix_of("a,"));
+        assert!(!Not(string::Ascii::new(b',').unwrap()).is_prefix_of(",a"));
+        assert!(!Not(string::Ascii::new(b',').unwrap()).is_prefix_of(""));
+        assert!(Not(|c: char| c == ',').is_suffix_of(",a"));
+        assert!(!Not(|c: char| c == ',').is_suffix_of("a,"));
+
+        let slice = &mut {*b"a,b,,c"}[..];
+
+        {
+            for m in split(&mut*slice, Not(slice::Ascii(b','))) {
+                for byte in m {
+                    *byte = b'-';
+                }
+            }
+        }
+
+        assert_eq!(slice, b"a-b--c");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_coalesce() {
+        use self::generic::{Coalesce, Not};
+
+        // Collects the rejects as offsets
+        fn rejects<H, S>(mut searcher: S) -> Vec<(usize, usize)>
+            where H: SearchPtrs,
+                  S: Searcher<H>,
+        {
+            let hs = searcher.haystack();
+            let mut ret = vec![];
+            while let Some((begin, end)) = searcher.next_reject() {
+                unsafe {
+                    ret.push((H::offset_from_start(hs, begin), H::offset_from_start(hs, end)));
+                }
+            }
+            ret
+        }
+
+        let a = string::Ascii::new(b'A').unwrap();
+        assert_eq!(rejects(Coalesce(a).into_searcher("xxAxx")), vec![(0, 2), (3, 5)]);
+        assert_eq!(rejects(a.into_searcher("xxAxx")).len(), 4);
+        assert_eq!(rejects(Coalesce(slice::Ascii(b'A')).into_searcher(&b"xxAxx"[..])),
+                   vec![(0, 2), (3, 5)]);
+        assert_eq!(rejects(Coalesce(a).into_searcher("AAxA")), vec![(2, 3)]);
+        assert_eq!(rejects(Coalesce(a).into_searcher("éé,ü")), vec![(0, 7)]);
+        assert_eq!(rejects(Coalesce(a).into_searcher("")), vec![]);
+
+        // Matches are unchanged, and inverting gives the maximal runs
+        assert_eq!(match_indices("xxAxx", Coalesce(a)), vec![(2, "A")]);
+        assert_eq!(match_indices("xxAxxAAy", Not(Coalesce(a))),
+                   vec![(0, "xx"), (3, "xx"), (7, "y")]);
+        assert!(Coalesce(a).is_prefix_of("Ax"));
+
+        // `next_match` discards the buffered reject, skipping the
+        // match in front of it
+        let mut searcher = Coalesce(a).into_searcher("xxAyAzz");
+        let hs = searcher.haystack();
+        let offsets = |(begin, end)| unsafe {
+            (<&str>::offset_from_start(hs, begin), <&str>::offset_from_start(hs, end))
+        };
+        assert_eq!(searcher.next_reject().map(offsets), Some((0, 2)));
+        assert_eq!(searcher.next_match().map(offsets), Some((4, 5)));
+        assert_eq!(searcher.next_reject().map(offsets), Some((5, 7)));
+        assert_eq!(searcher.next_reject(), None);
+    }
+
+    // Collects the steps of `next` as offsets, checking that they tile
+    // the haystack and that the searcher stays done afterwards. The
+    // searcher runs through `CheckedSearcher`.
+    #[cfg(all(test, feature = "std"))]
+    fn search_steps<H, S>(searcher: S) -> Vec<SearchStep<usize>>
+        where H: SearchPtrs,
+              S: FusedSearcher<H>,
     {
-        let mut searcher = pattern.into_searcher(haystack);
-        let mut ret = vec![];
+        let mut searcher = CheckedSearcher::new(searcher);
+        let hs = searcher.haystack();
+        let back = unsafe { H::haystack_len(hs) };
+        let mut steps = vec![];
+        let mut position = 0;
+        loop {
+            let step = searcher.next().map(|c| unsafe { H::offset_from_start(hs, c) });
+            match step {
+                SearchStep::Match(begin, end) | SearchStep::Reject(begin, end) => {
+                    assert_eq!(begin, position, "steps are not contiguous");
+                    assert!(begin <= end);
+                    position = end;
+                }
+                SearchStep::Done => break,
+            }
+            steps.push(step);
+        }
+        assert_eq!(position, back, "steps end in front of the back");
+        // Once done, the searcher stays done
+        for _ in 0..3 {
+            assert!(matches!(searcher.next(), SearchStep::Done));
+            assert!(searcher.next_match().is_none());
+            assert!(searcher.next_reject().is_none());
+        }
+        steps
+    }
+
+    // Checks that the matches among the steps of `searcher` are those
+    // `next_match` yields for an identical searcher.
+    #[cfg(all(test, feature = "std"))]
+    fn check_steps<H, S>(searcher: S, reference: S) -> Vec<SearchStep<usize>>
+        where H: SearchPtrs,
+              S: FusedSearcher<H>,
+    {
+        let steps = search_steps(searcher);
+        let mut reference = CheckedSearcher::new(reference);
+        let matches: Vec<_> = steps.iter()
+            .filter_map(|step| match *step {
+                SearchStep::Match(begin, end) => Some((begin, end)),
+                _ => None,
+            })
+            .collect();
+
+        let hs = reference.haystack();
+        let mut expected = vec![];
+        while let Some((begin, end)) = reference.next_match() {
+            unsafe {
+                expected.push((H::offset_from_start(hs, begin), H::offset_from_start(hs, end)));
+            }
+        }
+        assert_eq!(matches, expected);
+        steps
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_search_steps() {
+        use self::SearchStep::{Match, Reject};
+        use self::chain::Chain;
+        use self::generic::{Anchored, AnchoredEnd, Coalesce, Indexed, Not, Or, Repeat, Then};
+        use self::segments::Segments;
+        use self::string::{Glob, MultiStr};
+        use std::ffi::{CStr, OsStr};
+
+        fn check<H, P>(haystack: H, pattern: P) -> Vec<SearchStep<usize>>
+            where H: SearchPtrs + Clone,
+                  P: Pattern<H> + Clone,
+                  P::Searcher: FusedSearcher<H>,
+        {
+            check_steps(pattern.clone().into_searcher(haystack.clone()),
+                        pattern.into_searcher(haystack))
+        }
+
+        let a = string::Ascii::new(b'a').unwrap();
+        assert_eq!(check("xaay", a), vec![Reject(0, 1), Match(1, 2), Match(2, 3), Reject(3, 4)]);
+        assert_eq!(check("éab", "ab"), vec![Reject(0, 2), Match(2, 4)]);
+        assert_eq!(check("éa", a), vec![Reject(0, 2), Match(2, 3)]);
+        assert_eq!(check("ab", ""),
+                   vec![Match(0, 0), Reject(0, 1), Match(1, 1), Reject(1, 2), Match(2, 2)]);
+        assert_eq!(check("", a), vec![]);
+        assert_eq!(check("a  b", string::Whitespace), vec![Reject(0, 1), Match(1, 3), Reject(3, 4)]);
+        assert_eq!(check("xxaxx", Coalesce(a)), vec![Reject(0, 2), Match(2, 3), Reject(3, 5)]);
+        assert_eq!(check("xax", Not(a)), vec![Match(0, 1), Reject(1, 2), Match(2, 3)]);
+        assert_eq!(check("abab", Anchored("ab")), vec![Match(0, 2), Reject(2, 4)]);
+        assert_eq!(check("abxa", AnchoredEnd(a)), vec![Reject(0, 3), Match(3, 4)]);
+        assert_eq!(check(&b"xa"[..], slice::Ascii(b'a')), vec![Reject(0, 1), Match(1, 2)]);
+
+        for &haystack in &["", "ab", "aab\r\nb 12 éa", "  ab  a", "ababab", "éaé\n"] {
+            check(haystack, a);
+            check(haystack, string::AsciiSet::new(b"ab"));
+            check(haystack, "ab");
+            check(haystack, "");
+            check(haystack, "é");
+            check(haystack, Glob("a*b"));
+            check(haystack, Glob("*"));
+            check(haystack, &['b', 'é'][..]);
+            check(haystack, char::is_alphabetic);
+            check(haystack, string::Whitespace);
+            check(haystack, string::DigitRun);
+            check(haystack, string::LineTerminator);
+            check(haystack, MultiStr(&["a", "ab", "é"]));
+            check(haystack, Or("a", "b"));
+            check(haystack, Or("", "b"));
+            check(haystack, Not("ab"));
+            check(haystack, Coalesce(a));
+            check(haystack, Coalesce(Not(a)));
+            check(haystack, Anchored("a"));
+            check(haystack, AnchoredEnd(a));
+            check(haystack, Repeat(a));
+            check(haystack, Then("a", "b"));
+            check(Indexed(haystack), "ab");
+            assert_eq!(check(Indexed(haystack), Or("ab", "ba")), check(haystack, Or("ab", "ba")));
+            assert_eq!(check(Indexed(haystack), Then("a", "b")), check(haystack, Then("a", "b")));
+
+            let bytes = haystack.as_bytes();
+            check(bytes, slice::Ascii(b'a'));
+            check(bytes, |b: u8| b == b'a');
+            check(OsStr::new(haystack), os_string::Ascii::new(b'a').unwrap());
+            check(OsStr::new(haystack), OsStr::new("ab"));
+            check(OsStr::new(haystack), OsStr::new(""));
+
+            let chars: Vec<char> = haystack.chars().collect();
+            check(&chars[..], 'a');
+            check(&chars[..], &['a', 'b'][..]);
+            check(&chars[..], |c: char| c == 'a');
+
+            let mid = haystack.char_indices().nth(2).map_or(haystack.len(), |(i, _)| i);
+            let (front, back) = haystack.split_at(mid);
+            assert_eq!(check(Chain(front, back), "ab"), check(haystack, "ab"));
+            assert_eq!(check(Chain(front, back), ""), check(haystack, ""));
+            assert_eq!(check(Chain(front, back), Or("ab", "ba")), check(haystack, Or("ab", "ba")));
+            assert_eq!(check(Chain(front, back), Then("a", "b")), check(haystack, Then("a", "b")));
+            let list = [front, "", back];
+            assert_eq!(check(Segments::new(&list), "ab"), check(haystack, "ab"));
+            assert_eq!(check(Segments::new(&list), ""), check(haystack, ""));
+            assert_eq!(check(Segments::new(&list), Or("ab", "ba")),
+                       check(haystack, Or("ab", "ba")));
+            assert_eq!(check(Segments::new(&list), Then("a", "b")),
+                       check(haystack, Then("a", "b")));
+
+            // Mutable haystacks yield the steps of their shared views
+            let mut owned = haystack.to_string();
+            assert_eq!(search_steps(a.into_searcher(&mut owned[..])), check(haystack, a));
+            assert_eq!(search_steps('é'.into_searcher(&mut owned[..])), check(haystack, "é"));
+            let mut owned = haystack.as_bytes().to_vec();
+            assert_eq!(search_steps(slice::Ascii(b'a').into_searcher(&mut owned[..])),
+                       check(bytes, slice::Ascii(b'a')));
+            assert_eq!(search_steps((&b"ab"[..]).into_searcher(&mut owned[..])),
+                       check(haystack, "ab"));
+        }
+
+        let path = CStr::from_bytes_with_nul(b"/usr/bin:/bin\0").unwrap();
+        assert_eq!(check(c_string::CStrSlice::new(path), slice::Ascii(b':')),
+                   check(&b"/usr/bin:/bin"[..], slice::Ascii(b':')));
+        check(&[1, 2, 1, 1][..], generic_slice::Elem(1));
+        check(&[1u16, 2, 1, 2][..], &[1u16, 2][..]);
+    }
 
-        let haystack = searcher.haystack();
+    #[test]
+    fn test_clone_searcher() {
+        use self::generic::Or;
 
-        let mut last_end = Some(unsafe {
-            H::cursor_at_front(haystack)
-        });
+        fn rest<'a, S: Searcher<&'a str>>(mut searcher: S) -> Vec<(usize, usize)> {
+            let hs = searcher.haystack();
+            let mut v = vec![];
+            while let Some((a, b)) = searcher.next_match() {
+                unsafe { v.push((<&str>::offset_from_start(hs, a), <&str>::offset_from_start(hs, b))) }
+            }
+            v
+        }
 
-        while let Some((begin, end)) = searcher.next_match() {
-            if let Some(last_end) = last_end {
-                unsafe {
-                    let slice = H::range_to_self(haystack, last_end, begin);
-                    ret.push(slice);
+        // Clones continue from where the original stopped, independently
+        let mut searcher = string::Ascii::new(b'a').unwrap().into_searcher("banana");
+        searcher.next_match();
+        searcher.next_match();
+        assert_eq!(format!("{:?}", searcher),
+                   "AsciiSearcher { remaining: 4..6, ascii: 'a', ignore_case: false }");
+        let clone = searcher.clone();
+        assert_eq!(rest(searcher), vec![(5, 6)]);
+        assert_eq!(rest(clone), vec![(5, 6)]);
+
+        let mut searcher = Or("an", "b").into_searcher("banana");
+        searcher.next_match();
+        searcher.next_match();
+        assert!(format!("{:?}", searcher)
+                    .starts_with("OrSearcher { a: StrSearcher { remaining: 3..6, needle: \"an\""));
+        let clone = searcher.clone();
+        assert_eq!(rest(searcher), vec![(3, 5)]);
+        assert_eq!(rest(clone), vec![(3, 5)]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_or() {
+        use self::generic::Or;
+        use self::string::Ascii;
+
+        assert_eq!(match_indices("a1b2", Or(Ascii::new(b'1').unwrap(), Ascii::new(b'b').unwrap())),
+                   vec![(1, "1"), (2, "b")]);
+        assert_eq!(split("a,b;c", Or(Ascii::new(b';').unwrap(), Ascii::new(b',').unwrap())),
+                   vec!["a", "b", "c"]);
+
+        // Ties prefer the longer match, overlapping matches are dropped
+        assert_eq!(match_indices("abcab", Or("a", "ab")),
+                   vec![(0, "ab"), (3, "ab")]);
+        assert_eq!(match_indices("abcbc", Or("ab", "bc")),
+                   vec![(0, "ab"), (3, "bc")]);
+
+        // A buffered match of the losing branch is not skipped
+        assert_eq!(match_indices("xaxb", Or("b", "a")),
+                   vec![(1, "a"), (3, "b")]);
+
+        // The losing branch restarts behind the yielded match, so matches
+        // overlapping its dropped one are still found
+        assert_eq!(match_indices("abcbcb", Or("ab", "bcb")),
+                   vec![(0, "ab"), (3, "bcb")]);
+        assert_eq!(match_indices("abcbcb", Or("bcb", "ab")),
+                   vec![(0, "ab"), (3, "bcb")]);
+        assert_eq!(match_indices("aaaa", Or("aaa", "aa")),
+                   vec![(0, "aaa")]);
+        assert_eq!(match_ranges(generic::Indexed("abcbcb"), Or("ab", "bcb")), vec![0..2, 3..6]);
+        assert_eq!(match_ranges(generic::Indexed("abcbcb"), Or("bcb", "ab")), vec![0..2, 3..6]);
+        assert_eq!(match_ranges(chain::Chain("ab", "cbcb"), Or("ab", "bcb")), vec![0..2, 3..6]);
+        assert_eq!(match_ranges(chain::Chain("", "abcbcb"), Or("ab", "bcb")), vec![0..2, 3..6]);
+
+        let mut searcher = Or("ab", Ascii::new(b',').unwrap()).into_searcher("x,abyy");
+        let hs = searcher.haystack();
+        let mut steps = vec![];
+        unsafe {
+            let m = searcher.next_reject().unwrap();
+            steps.push(<&str>::range_to_self(hs, m.0, m.1));
+            let m = searcher.next_match().unwrap();
+            steps.push(<&str>::range_to_self(hs, m.0, m.1));
+            let m = searcher.next_reject().unwrap();
+            steps.push(<&str>::range_to_self(hs, m.0, m.1));
+        }
+        assert_eq!(steps, vec!["x", ",", "yy"]);
+        assert_eq!(searcher.next_reject(), None);
+
+        assert!(Or(Ascii::new(b'x').unwrap(), Ascii::new(b'y').unwrap()).is_prefix_of("yx"));
+        assert!(!Or(Ascii::new(b'x').unwrap(), Ascii::new(b'y').unwrap()).is_prefix_of("zx"));
+
+        let slice = &mut {*b"a1b2"}[..];
+
+        {
+            for m in split(&mut*slice, Or(slice::Ascii(b'1'), slice::Ascii(b'2'))) {
+                for byte in m {
+                    *byte = b'-';
                 }
             }
-            last_end = Some(end);
         }
 
-        if let Some(last_end) = last_end {
-            unsafe {
-                let end = H::cursor_at_back(haystack);
-                let slice = H::range_to_self(haystack, last_end, end);
-                ret.push(slice);
+        assert_eq!(slice, b"-1-2");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_then() {
+        use self::generic::Then;
+        use self::string::Ascii;
+
+        let digit = |c: char| c.is_ascii_digit();
+
+        assert_eq!(match_indices("v1.2 x3. 4.", Then(digit, Ascii::new(b'.').unwrap())),
+                   vec![(1, "1."), (6, "3."), (9, "4.")]);
+        assert_eq!(split("1.2.3", Then(digit, Ascii::new(b'.').unwrap())),
+                   vec!["", "", "3"]);
+
+        // `B` failing at one candidate backtracks to the next `A` match
+        assert_eq!(match_indices("1x2.", Then(digit, Ascii::new(b'.').unwrap())),
+                   vec![(2, "2.")]);
+        assert_eq!(match_indices("ab", Then("a", "c")), vec![]);
+
+        // Or to an `A` match one char further on, overlapping the failed one
+        assert_eq!(match_indices("aaab", Then("aa", "b")), vec![(1, "aab")]);
+        assert_eq!(match_indices("ééék", Then("éé", "k")), vec![(2, "éék")]);
+        assert_eq!(match_indices("aaabaab", Then("aa", "b")),
+                   vec![(1, "aab"), (4, "aab")]);
+
+        // Haystacks whose cursors count from the front of a sub-haystack
+        let indexed = generic::Indexed;
+        assert_eq!(match_ranges(indexed("xxab"), Then("a", "b")), vec![2..4]);
+        assert_eq!(match_ranges(indexed("aaab"), Then("aa", "b")), vec![1..4]);
+        assert_eq!(match_ranges(indexed("éaabaab"), Then("aa", "b")), vec![2..5, 5..8]);
+        assert_eq!(match_ranges(chain::Chain("xx", "ab"), Then("a", "b")), vec![2..4]);
+        assert_eq!(match_ranges(chain::Chain("xa", "ab"), Then("a", "b")), vec![2..4]);
+        assert_eq!(match_ranges(chain::Chain("aa", "ab"), Then("aa", "b")), vec![1..4]);
+
+        // `A` matching at the very end of the haystack
+        assert_eq!(match_indices("ab1", Then(digit, "")), vec![(2, "1")]);
+        assert_eq!(match_indices("ab1", Then(digit, Ascii::new(b'.').unwrap())), vec![]);
+
+        let mut searcher = Then("a", "b").into_searcher("xabyab");
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "x");
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "y");
+            let m = searcher.next_match().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "ab");
+        }
+        assert_eq!(searcher.next_reject(), None);
+
+        assert!(Then("a", "b").is_prefix_of("abc"));
+        assert!(!Then("a", "b").is_prefix_of("acb"));
+
+        let slice = &mut {*b"1.2x3."}[..];
+
+        {
+            let pattern = Then(slice::Ascii(b'2'), slice::Ascii(b'x'));
+            for m in match_indices(&mut*slice, pattern) {
+                for byte in m.1 {
+                    *byte = b'-';
+                }
             }
         }
 
-        ret
+        assert_eq!(slice, b"1.--3.");
     }
 
+    #[cfg(feature = "alloc")]
     #[test]
-    fn test_split() {
-        assert_eq!(split("hangman", string::Ascii(b'a')),
-                   vec!["h", "ngm", "n"]);
+    fn test_repeat() {
+        use self::generic::Repeat;
+        use self::string::Ascii;
 
-        let mut slice = &mut {*b"hangman"}[..];
+        assert_eq!(match_indices("   if  x", Repeat(Ascii::new(b' ').unwrap())),
+                   vec![(0, "   "), (5, "  ")]);
+        assert_eq!(split("a  b c   ", Repeat(Ascii::new(b' ').unwrap())),
+                   vec!["a", "b", "c", ""]);
+        assert_eq!(match_indices("", Repeat(Ascii::new(b' ').unwrap())), vec![]);
+        assert_eq!(split("", Repeat(Ascii::new(b' ').unwrap())), vec![""]);
+        assert_eq!(match_indices("abab", Repeat("ab")), vec![(0, "abab")]);
+
+        let mut searcher = Repeat(Ascii::new(b'-').unwrap()).into_searcher("ab--c-de");
+        let hs = searcher.haystack();
+        let mut rejects = vec![];
+        while let Some((begin, end)) = searcher.next_reject() {
+            unsafe {
+                rejects.push(<&str>::range_to_self(hs, begin, end));
+            }
+        }
+        assert_eq!(rejects, vec!["ab", "c", "de"]);
+
+        assert!(Repeat(Ascii::new(b' ').unwrap()).is_prefix_of("  x"));
+        assert!(!Repeat(Ascii::new(b' ').unwrap()).is_prefix_of("x "));
+
+        let slice = &mut {*b"\t\tfoo\tbar\t\t"}[..];
 
         {
-            let split = split(&mut*slice, slice::Ascii(b'a'));
+            let split = split(&mut*slice, Repeat(slice::Ascii(b'\t')));
 
+            assert_eq!(split.len(), 4);
             for m in split {
                 for byte in m {
                     *byte = b'-';
@@ -361,7 +13951,736 @@ pub mod api_consumer {
             }
         }
 
-        assert_eq!(slice, b"-a---a-");
+        assert_eq!(slice, b"\t\t---\t---\t\t");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_ascii_new() {
+        use self::string::Ascii;
+
+        assert!(Ascii::new(b'a').is_some());
+        assert!(Ascii::new(0x7F).is_some());
+        // 0xA9 is the continuation byte of 'é' (0xC3 0xA9)
+        assert!(Ascii::new(0xA9).is_none());
+        assert!(Ascii::new(0xFF).is_none());
+
+        let a = unsafe { Ascii::new_unchecked(b'a') };
+        assert_eq!(match_indices("éa", a), vec![(2, "a")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_ascii_reverse() {
+        let a = string::Ascii::new(b'a').unwrap();
+        let haystack = "banana";
+        let mut searcher = a.into_searcher(haystack);
+        let hs = searcher.haystack();
+        let offset = |(begin, _)| unsafe { <&str>::offset_from_start(hs, begin) };
+
+        // Alternating between both ends yields every match exactly once
+        let mut offsets = vec![];
+        loop {
+            let front = searcher.next_match().map(offset);
+            let back = searcher.next_match_back().map(offset);
+            offsets.extend(front);
+            offsets.extend(back);
+            if front.is_none() && back.is_none() {
+                break;
+            }
+        }
+        offsets.sort();
+        let forward: Vec<_> = match_indices(haystack, a).into_iter()
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(offsets, forward);
+        assert_eq!(offsets, vec![1, 3, 5]);
+
+        let mut searcher = a.into_searcher("éaxa");
+        let hs = searcher.haystack();
+        let range = |(begin, end)| unsafe {
+            <&str>::range_to_self(hs, begin, end)
+        };
+        assert_eq!(searcher.next_reject_back().map(range), Some("x"));
+        assert_eq!(searcher.next_reject_back().map(range), Some("é"));
+        assert_eq!(searcher.next_reject_back().map(range), None);
+        assert!(a.is_suffix_of("banana"));
+    }
+
+    #[test]
+    fn test_mut_ascii_reverse() {
+        let mut haystack = *b"a,b,,c,d,";
+        let comma = slice::Ascii(b',');
+        let mut searcher = comma.into_searcher(&mut haystack[..]);
+        let hs = searcher.haystack();
+        let offset = |(begin, _)| unsafe { <&mut [u8]>::offset_from_start(hs, begin) };
+
+        let (mut front, mut back) = (vec![], vec![]);
+        loop {
+            let f = searcher.next_match().map(offset);
+            let b = searcher.next_match_back().map(offset);
+            front.extend(f);
+            back.extend(b);
+            if f.is_none() && b.is_none() {
+                break;
+            }
+        }
+        assert_eq!(front, vec![1, 3, 4]);
+        assert_eq!(back, vec![8, 6]);
+        assert!(front.iter().all(|i| !back.contains(i)));
+
+        // Rejects from both ends meet without sharing a byte
+        let mut searcher = comma.into_searcher(&mut haystack[..]);
+        let hs = searcher.haystack();
+        let offset = |(begin, _)| unsafe { <&mut [u8]>::offset_from_start(hs, begin) };
+        let (mut front, mut back) = (vec![], vec![]);
+        while let Some(i) = searcher.next_reject_back().map(offset) {
+            back.push(i);
+            front.extend(searcher.next_reject().map(offset));
+        }
+        assert_eq!(front, vec![0, 2]);
+        assert_eq!(back, vec![7, 5]);
+        assert!(comma.is_suffix_of(&mut haystack[..]));
+    }
+
+    #[test]
+    fn test_default_prefix_suffix() {
+        let a = string::Ascii::new(b'a').unwrap();
+        assert!(!a.is_prefix_of(""));
+        assert!(!a.is_suffix_of(""));
+        assert!(a.is_prefix_of("ab"));
+        assert!(!a.is_suffix_of("ab"));
+        assert!(a.is_suffix_of("ba"));
+        assert!(a.is_prefix_of("a") && a.is_suffix_of("a"));
+
+        let a = slice::Ascii(b'a');
+        assert!(!a.is_prefix_of(&mut [][..]));
+        assert!(!a.is_suffix_of(&mut [][..]));
+        assert!(a.is_suffix_of(&mut {*b"ba"}[..]));
+        assert!(!a.is_prefix_of(&mut {*b"ba"}[..]));
+
+        // Only the first and the last match are looked at
+        let e = generic_slice::Elem(1);
+        assert!(e.is_prefix_of(&[1, 2, 1][..]));
+        assert!(!e.is_suffix_of(&[1, 2][..]));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_byte_pattern() {
+        assert_eq!(split("a,b,,c", b','), vec!["a", "b", "", "c"]);
+        assert!(b'a'.is_prefix_of("abc"));
+        assert!(!b'b'.is_prefix_of("abc"));
+
+        // 0xA9 is the continuation byte of 'é' (0xC3 0xA9)
+        assert_eq!(match_indices("é", 0xA9), vec![]);
+        assert_eq!(split("é", 0xA9), vec!["é"]);
+        assert!(!0xC3.is_prefix_of("é"));
+
+        let slice = &mut {*b"\xC3\xA9"}[..];
+        assert_eq!(match_indices(&mut*slice, 0xA9).len(), 1);
+        assert!(0xC3.is_prefix_of(slice));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_pattern_by_ref() {
+        use self::generic::{Not, Repeat};
+        use self::string::{Ascii, CharRange};
+
+        let pat = &Ascii::new(b'a').unwrap();
+        assert!(pat.is_prefix_of("abca"));
+        assert_eq!(split("abca", pat), vec!["", "bc", ""]);
+        assert_eq!(match_indices("abca", pat), vec![(0, "a"), (3, "a")]);
+
+        let pat = CharRange('a'..='a');
+        assert!((&pat).is_prefix_of("abca"));
+        assert_eq!(split("abca", &pat), vec!["", "bc", ""]);
+        assert_eq!(match_indices("abca", &pat), vec![(0, "a"), (3, "a")]);
+        assert!(pat.is_contained_in("bab"));
+
+        let pat = Repeat(Not(CharRange(' '..=' ')));
+        assert_eq!(split("ab  c", &pat), vec!["", "  ", ""]);
+        assert!(pat.is_prefix_of("ab"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_anchored_end() {
+        use self::generic::AnchoredEnd;
+        use self::string::Whitespace;
+        use self::string::classes::Digit;
+
+        assert_eq!(match_indices("ab1", AnchoredEnd(Digit)), vec![(2, "1")]);
+        // Ends one char before the back
+        assert_eq!(match_indices("a1b", AnchoredEnd(Digit)), vec![]);
+        assert_eq!(match_indices("1a2", AnchoredEnd(Digit)), vec![(2, "2")]);
+        assert_eq!(match_indices("a b  ", AnchoredEnd(Whitespace)),
+                   vec![(3, "  ")]);
+        assert_eq!(split("a b  ", AnchoredEnd(Whitespace)), vec!["a b", ""]);
+        assert_eq!(split("a b", AnchoredEnd(Whitespace)), vec!["a b"]);
+
+        assert!(AnchoredEnd(Digit).is_suffix_of("a1"));
+        assert!(!AnchoredEnd(Digit).is_suffix_of("1a"));
+        assert!(AnchoredEnd(Digit).is_prefix_of("1"));
+        assert!(!AnchoredEnd(Digit).is_prefix_of("11"));
+
+        let rejects = |haystack, back: bool| {
+            let mut searcher = AnchoredEnd(Digit).into_searcher(haystack);
+            let hs = searcher.haystack();
+            let mut rejects = vec![];
+            loop {
+                let reject = if back {
+                    searcher.next_reject_back()
+                } else {
+                    searcher.next_reject()
+                };
+                match reject {
+                    Some((begin, end)) => unsafe {
+                        rejects.push(<&str>::range_to_self(hs, begin, end));
+                    },
+                    None => return rejects,
+                }
+            }
+        };
+        assert_eq!(rejects("a1b2", false), vec!["a1b"]);
+        assert_eq!(rejects("a1b2", true), vec!["a1b"]);
+        assert_eq!(rejects("a1b", false), vec!["a1b"]);
+        assert_eq!(rejects("2", true), Vec::<&str>::new());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_anchored() {
+        use self::generic::Anchored;
+        use self::string::Ascii;
+
+        assert_eq!(match_indices("abc", Anchored("ab")), vec![(0, "ab")]);
+        assert_eq!(match_indices("xab", Anchored("ab")), vec![]);
+        assert_eq!(match_indices("abab", Anchored("ab")), vec![(0, "ab")]);
+        assert_eq!(split("abc", Anchored("ab")), vec!["", "c"]);
+        assert_eq!(split("xab", Anchored("ab")), vec!["xab"]);
+
+        assert!(Anchored("ab").is_contained_in("abc"));
+        assert!(!Anchored("ab").is_contained_in("xab"));
+        assert!(Anchored(Ascii::new(b'x').unwrap()).is_prefix_of("xab"));
+
+        let rejects = |haystack| {
+            let mut searcher = Anchored("ab").into_searcher(haystack);
+            let hs = searcher.haystack();
+            let mut rejects = vec![];
+            while let Some((begin, end)) = searcher.next_reject() {
+                unsafe {
+                    rejects.push(<&str>::range_to_self(hs, begin, end));
+                }
+            }
+            rejects
+        };
+        assert_eq!(rejects("abcab"), vec!["cab"]);
+        assert_eq!(rejects("xab"), vec!["xab"]);
+        assert_eq!(rejects("ab"), Vec::<&str>::new());
+
+        // Only the front is looked at, also when the anchor fails
+        let calls = ::core::cell::Cell::new(0);
+        let digit = |c: char| {
+            calls.set(calls.get() + 1);
+            c.is_ascii_digit()
+        };
+        assert_eq!(match_indices("x123456789", Anchored(digit)), vec![]);
+        assert_eq!(calls.get(), 1);
+        calls.set(0);
+        assert_eq!(match_indices("123456789", Anchored(digit)), vec![(0, "1")]);
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    #[should_panic(expected = "no match has been found yet")]
+    fn test_multi_str_id_before_match() {
+        let searcher = string::MultiStr(&["a", "b"]).into_searcher("ab");
+        searcher.last_match_id();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_multi_str() {
+        use self::string::MultiStr;
+
+        let needles = ["error", "warn", "fatal"];
+        let log = "warn: x\nerror: y\nfatal error";
+        assert_eq!(match_indices(log, MultiStr(&needles)),
+                   vec![(0, "warn"), (8, "error"), (17, "fatal"), (23, "error")]);
+
+        let mut searcher = MultiStr(&needles).into_searcher(log);
+        let mut ids = vec![];
+        while searcher.next_match().is_some() {
+            ids.push(searcher.matched_needle().unwrap());
+        }
+        assert_eq!(ids, vec![1, 0, 2, 0]);
+
+        // Leftmost-longest, regardless of needle order
+        assert_eq!(match_indices("abcab", MultiStr(&["ab", "abc"])),
+                   vec![(0, "abc"), (3, "ab")]);
+        assert_eq!(match_indices("abcab", MultiStr(&["abc", "ab"])),
+                   vec![(0, "abc"), (3, "ab")]);
+        // Leftmost wins over longest
+        assert_eq!(match_indices("xabc", MultiStr(&["abc", "xa"])),
+                   vec![(0, "xa")]);
+        assert_eq!(match_indices("aéb", MultiStr(&["é", ""])), vec![(1, "é")]);
+
+        assert_eq!(split("a, b;c", MultiStr(&[", ", ";"])), vec!["a", "b", "c"]);
+
+        let mut searcher = MultiStr(&["ab", "c"]).into_searcher("xabcyy");
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "x");
+            let m = searcher.next_reject().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "yy");
+        }
+
+        assert!(MultiStr(&["x", "ab"]).is_prefix_of("abc"));
+        assert!(!MultiStr(&["x", "bc"]).is_prefix_of("abc"));
+        assert!(!MultiStr(&[""]).is_prefix_of("abc"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_ascii_large_haystack() {
+        // Interpreting megabytes takes too long under Miri
+        let len = if cfg!(miri) { 4 << 10 } else { 4 << 20 };
+        let mut haystack = String::with_capacity(len);
+        for _ in 0..len - 1 {
+            haystack.push('a');
+        }
+        haystack.push('b');
+
+        assert_eq!(match_indices(&haystack[..], string::Ascii::new(b'b').unwrap()),
+                   vec![(len - 1, "b")]);
+        assert_eq!(match_indices(&haystack[1..], string::Ascii::new(b'b').unwrap()),
+                   vec![(len - 2, "b")]);
+        assert_eq!(split(&haystack[..], string::Ascii::new(b'c').unwrap()).len(), 1);
+
+        // The reverse search skips whole words as well
+        haystack.replace_range(..1, "b");
+        haystack.pop();
+        let mut searcher = string::Ascii::new(b'b').unwrap().into_searcher(&haystack[..]);
+        let front = haystack.as_ptr();
+        assert_eq!(searcher.next_match_back(), Some((front, front.wrapping_add(1))));
+        assert_eq!(searcher.next_match_back(), None);
+        assert_eq!(rsplit(&haystack[..], string::Ascii::new(b'c').unwrap()).len(), 1);
+    }
+
+    // A small xorshift generator, good enough for randomized tests
+    #[cfg(all(test, feature = "alloc"))]
+    fn random_bytes(seed: u64, len: usize, alphabet: &[u8]) -> Vec<u8> {
+        let mut state = seed | 1;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            alphabet[(state % alphabet.len() as u64) as usize]
+        }).collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_ascii2_ascii3() {
+        let alphabet = b"abc\r\n \t";
+        let newline = string::Ascii2::new(b'\r', b'\n').unwrap();
+        let blank_or_a = string::Ascii3::new(b' ', b'\t', b'a').unwrap();
+
+        for seed in 0..200 {
+            let bytes = random_bytes(seed, seed as usize % 67, alphabet);
+            let haystack = ::std::str::from_utf8(&bytes).unwrap();
+
+            let reference = |set: &[u8]| -> Vec<(usize, &str)> {
+                haystack.match_indices(|c: char| set.contains(&(c as u8)))
+                        .collect()
+            };
+
+            assert_eq!(match_indices(haystack, newline), reference(b"\r\n"));
+            assert_eq!(match_indices(haystack, blank_or_a), reference(b" \ta"));
+            assert_eq!(split(haystack, newline),
+                       haystack.split(&['\r', '\n'][..]).collect::<Vec<_>>());
+
+            let mut buf = bytes.clone();
+            let found = match_indices(&mut buf[..], slice::Ascii3(b'a', b'b', b'\n'))
+                .into_iter().map(|m| m.0).collect::<Vec<_>>();
+            let expected = bytes.iter().enumerate()
+                .filter(|&(_, b)| b"ab\n".contains(b))
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected);
+        }
+
+        let slice = &mut {*b"a\r\nb\nc"}[..];
+
+        {
+            for m in split(&mut*slice, slice::Ascii2(b'\r', b'\n')) {
+                for byte in m {
+                    *byte = b'-';
+                }
+            }
+        }
+
+        assert_eq!(slice, b"-\r\n-\n-");
+        assert!(string::Ascii2::new(b'x', b'y').unwrap().is_prefix_of("yx"));
+        assert!(!string::Ascii3::new(b'x', b'y', b'z').unwrap().is_prefix_of(""));
+
+        // Bytes of multi-byte chars could split them, so they are refused
+        assert!(string::Ascii2::new(0xA9, 0xA9).is_none());
+        assert!(string::Ascii2::new(b',', 0xC3).is_none());
+        assert!(string::Ascii3::new(b',', b';', 0xA9).is_none());
+        let separators = string::Ascii3::new(b',', b';', b' ').unwrap();
+        assert_eq!(split("é,ü;ö ß", separators), vec!["é", "ü", "ö", "ß"]);
+        assert_eq!(rsplit("é,ü", separators), vec!["ü", "é"]);
+        let a_or_b = string::Ascii2::new(b'a', b'b').unwrap();
+        assert_eq!(match_indices("éaé", a_or_b), vec![(2, "a")]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_str_pattern() {
+        assert_eq!(split("a,b,,c", ","), vec!["a", "b", "", "c"]);
+        assert_eq!(split("a::b", "::"), vec!["a", "b"]);
+        assert_eq!(split("abc", "x"), vec!["abc"]);
+
+        assert_eq!(match_indices("aaa", "aa"), vec![(0, "aa")]);
+        assert_eq!(match_indices("aaaa", "aa"), vec![(0, "aa"), (2, "aa")]);
+        assert_eq!(match_indices("a", "aa"), vec![]);
+
+        assert!("ab".is_prefix_of("abc"));
+        assert!(!"bc".is_prefix_of("abc"));
+    }
+
+    // The needle only lives inside this function
+    #[cfg(all(test, feature = "alloc"))]
+    fn words_searcher(haystack: &str, n: usize) -> string::StrSearcher<'_, String> {
+        let needle = "word".repeat(n);
+        needle.into_searcher(haystack)
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_string_pattern() {
+        let needle = String::from("ab");
+
+        assert_eq!(match_indices("abcab", &needle), vec![(0, "ab"), (3, "ab")]);
+        assert_eq!(split("xabyab", &needle), vec!["x", "y", ""]);
+        assert!((&needle).is_prefix_of("abc"));
+        assert_eq!(match_indices("abcab", needle), vec![(0, "ab"), (3, "ab")]);
+
+        let mut searcher = words_searcher("word wordword", 2);
+        let hs = searcher.haystack();
+        let (begin, end) = searcher.next_match().unwrap();
+        unsafe {
+            assert_eq!(<&str>::offset_from_start(hs, begin), 5);
+            assert_eq!(<&str>::range_to_self(hs, begin, end), "wordword");
+        }
+        assert!(searcher.next_match().is_none());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_str_pattern_empty_needle() {
+        assert_eq!(split("abc", ""), vec!["", "a", "b", "c", ""]);
+        assert_eq!(split("abc", ""), "abc".split("").collect::<Vec<_>>());
+        assert_eq!(split("aé", ""), "aé".split("").collect::<Vec<_>>());
+        assert_eq!(split("", ""), vec!["", ""]);
+        assert_eq!(match_indices("aé", ""),
+                   "aé".match_indices("").collect::<Vec<_>>());
+
+        let mut searcher = "".into_searcher("aé");
+        assert_eq!(searcher.next_reject().map(|(b, e)| e as usize - b as usize),
+                   Some(1));
+        assert_eq!(searcher.next_reject().map(|(b, e)| e as usize - b as usize),
+                   Some(2));
+        assert_eq!(searcher.next_reject(), None);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_no_case_ascii() {
+        use self::string::NoCaseAscii;
+
+        let headers = "Content-Length: 1\r\nCONTENT-length: 2\r\nContent-Type: x";
+        assert_eq!(match_indices(headers, NoCaseAscii("content-length")),
+                   vec![(0, "Content-Length"), (19, "CONTENT-length")]);
+        assert_eq!(split("aÉbéc", NoCaseAscii("é")), vec!["aÉb", "c"]);
+        assert_eq!(split("xAbyab", NoCaseAscii("aB")), vec!["x", "y", ""]);
+
+        assert!(NoCaseAscii("content").is_prefix_of(headers));
+        assert!(!NoCaseAscii("content-type").is_prefix_of(headers));
+        assert!(!NoCaseAscii("longer than the haystack").is_prefix_of("x"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_long_needles() {
+        for seed in 0..100 {
+            let haystack = random_bytes(seed, 300, b"ab");
+            let haystack = ::std::str::from_utf8(&haystack).unwrap();
+            let needle = random_bytes(seed + 1000, 8 + seed as usize % 5, b"ab");
+            let needle = ::std::str::from_utf8(&needle).unwrap();
+
+            assert_eq!(match_indices(haystack, needle),
+                       haystack.match_indices(needle).collect::<Vec<_>>());
+            assert_eq!(split(haystack, needle),
+                       haystack.split(needle).collect::<Vec<_>>());
+
+            let mut bytes = haystack.as_bytes().to_vec();
+            let found = match_indices(&mut bytes[..], needle.as_bytes())
+                .into_iter().map(|m| m.0).collect::<Vec<_>>();
+            let expected = haystack.match_indices(needle)
+                .map(|m| m.0).collect::<Vec<_>>();
+            assert_eq!(found, expected);
+        }
+
+        // Interpreting megabytes takes too long under Miri
+        let len = if cfg!(miri) { 1 << 12 } else { 1 << 20 };
+        let haystack = "a".repeat(len);
+        assert_eq!(match_indices(&haystack[..], "aaaaaaaaaaaaaaab"), vec![]);
+        let mut bytes = haystack.into_bytes();
+        assert_eq!(match_indices(&mut bytes[..], &b"baaaaaaaaaaaaaaa"[..]).len(), 0);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_str_pattern_two_way() {
+        let needles = ["aabaa", "abab", "aaaa", "abcabc", "baaab", "cabca",
+                       "aabaabaab", "ba", "é", "aé"];
+
+        for seed in 0..50 {
+            let haystack = random_bytes(seed, 200, b"abc");
+            let mut haystack = String::from_utf8(haystack).unwrap();
+            if seed % 5 == 0 {
+                haystack = haystack.replace("c", "é");
+            }
+
+            for &needle in &needles {
+                assert_eq!(match_indices(&haystack[..], needle),
+                           haystack.match_indices(needle).collect::<Vec<_>>());
+                assert_eq!(split(&haystack[..], needle),
+                           haystack.split(needle).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_overlapping() {
+        use self::generic::Overlapping;
+
+        assert_eq!(match_indices("aaaa", Overlapping("aa")),
+                   vec![(0, "aa"), (1, "aa"), (2, "aa")]);
+        assert_eq!(match_indices("aaaa", "aa"), vec![(0, "aa"), (2, "aa")]);
+        assert_eq!(match_indices("éaéaé", Overlapping("éaé")),
+                   vec![(0, "éaé"), (3, "éaé")]);
+        assert_eq!(match_indices("ab", Overlapping("")).len(), 3);
+
+        // The pieces would alias, so only the ranges are collected
+        let slice = &mut {*b"abababa"}[..];
+        assert_eq!(match_ranges(&mut*slice, Overlapping(&b"aba"[..])),
+                   vec![0..3, 2..5, 4..7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "overlapping matches have no rejects")]
+    fn test_overlapping_reject() {
+        use self::generic::Overlapping;
+
+        Overlapping("aa").into_searcher("aaa").next_reject();
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_glob() {
+        use self::string::Glob;
+
+        let haystack = "ab1cd22ef";
+        assert_eq!(match_indices(haystack, Glob("c*e")), vec![(3, "cd22e")]);
+        assert_eq!(match_indices(haystack, Glob("*d")), vec![(0, "ab1cd")]);
+        assert_eq!(match_indices(haystack, Glob("c*")), vec![(3, "c")]);
+        assert_eq!(match_indices(haystack, Glob("?2")), vec![(4, "d2")]);
+        assert_eq!(match_indices(haystack, Glob("2*f")), vec![(5, "22ef")]);
+        assert_eq!(match_indices(haystack, Glob("x*")), vec![]);
+        assert_eq!(match_indices("cdeee", Glob("c*e")), vec![(0, "cde")]);
+        assert_eq!(match_indices("aéb", Glob("a?b")), vec![(0, "aéb")]);
+
+        assert_eq!(match_indices("ab", Glob("*")),
+                   vec![(0, ""), (1, ""), (2, "")]);
+        assert_eq!(split("ab", Glob("*")), "ab".split("").collect::<Vec<_>>());
+        assert_eq!(split("a-b", Glob("?")), vec!["", "", "", ""]);
+        assert_eq!(split("a1b22c", Glob("*2")), vec!["", "", "c"]);
+
+        let mut searcher = Glob("b?").into_searcher("abcbbd");
+        let mut rejects = vec![];
+        while let Some((begin, end)) = searcher.next_reject() {
+            unsafe {
+                rejects.push(<&str>::range_to_self(searcher.haystack(),
+                                                   begin, end));
+            }
+        }
+        assert_eq!(rejects, vec!["a", "d"]);
+
+        assert!(Glob("a*c").is_prefix_of("abc"));
+        assert!(!Glob("b*").is_prefix_of("abc"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_bytes_in_str() {
+        // 'é' is 0xC3 0xA9
+        assert_eq!(match_indices("éaé", &b"\xA9"[..]), vec![]);
+        assert_eq!(match_indices("éé", &b"\xA9\xC3"[..]), vec![]);
+        assert_eq!(match_indices("éaé", &b"\xC3\xA9"[..]),
+                   vec![(0, "é"), (3, "é")]);
+        assert_eq!(match_indices("aéa", &b"a"[..]), vec![(0, "a"), (3, "a")]);
+        assert_eq!(match_indices("a\u{FF}", &b"\xFF"[..]), vec![]);
+        assert_eq!(split("a,b", &b","[..]), vec!["a", "b"]);
+
+        assert!((&b"\xC3\xA9"[..]).is_prefix_of("éa"));
+        assert!(!(&b"\xC3"[..]).is_prefix_of("éa"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_mut_str() {
+        let mut owned = String::from("Hello,WORLD,ÄÖ");
+
+        {
+            let haystack: &mut str = &mut owned;
+            for piece in split(haystack, string::Ascii::new(b',').unwrap()) {
+                piece.make_ascii_lowercase();
+            }
+        }
+        assert_eq!(owned, "hello,world,ÄÖ");
+
+        {
+            let haystack: &mut str = &mut owned;
+            let pieces = split(haystack, 'Ä');
+            assert_eq!(pieces.len(), 2);
+            for piece in pieces {
+                piece.make_ascii_uppercase();
+            }
+        }
+        assert_eq!(owned, "HELLO,WORLD,ÄÖ");
+
+        let haystack: &mut str = &mut owned;
+        assert_eq!(match_indices(&mut *haystack, 'Ö').len(), 1);
+        assert!('H'.is_prefix_of(&mut *haystack));
+        assert!(string::Ascii::new(b'H').unwrap().is_prefix_of(haystack));
+    }
+
+    #[test]
+    fn test_str_pattern_reject() {
+        let haystack = "xaaxxa";
+        let mut searcher = "a".into_searcher(haystack);
+        let mut rejects = vec![];
+        while let Some((begin, end)) = searcher.next_reject() {
+            unsafe {
+                rejects.push(<&str>::range_to_self(searcher.haystack(),
+                                                   begin, end));
+            }
+        }
+        assert_eq!(rejects, vec!["x", "xx"]);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_char_slice_pattern() {
+        let haystack = "aébxçy€z";
+        let set = &['é', 'x', '€'][..];
+
+        assert_eq!(split(haystack, set),
+                   haystack.split(set).collect::<Vec<_>>());
+        assert_eq!(match_indices(haystack, set),
+                   vec![(1, "é"), (4, "x"), (8, "€")]);
+
+        let mut searcher = set.into_searcher(haystack);
+        let mut rejects = vec![];
+        while let Some((begin, end)) = searcher.next_reject() {
+            unsafe {
+                rejects.push(<&str>::range_to_self(searcher.haystack(),
+                                                   begin, end));
+            }
+        }
+        assert_eq!(rejects, vec!["a", "b", "ç", "y", "z"]);
+
+        assert!(set.is_prefix_of("éa"));
+        assert!(!set.is_prefix_of("aé"));
+        assert!(!set.is_prefix_of(""));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_char_predicate_pattern() {
+        for &haystack in &["a1b22c3", "1abc", "", "x٣y", "no digits"] {
+            assert_eq!(split(haystack, |c: char| c.is_numeric()),
+                       haystack.split(|c: char| c.is_numeric())
+                               .collect::<Vec<_>>());
+        }
+
+        assert_eq!(split("a b\u{a0}c", char::is_whitespace),
+                   vec!["a", "b", "c"]);
+
+        assert!((|c: char| c.is_numeric()).is_prefix_of("1a"));
+        assert!((|c: char| c.is_numeric()).is_suffix_of("a1"));
+        assert!(!(|c: char| c.is_numeric()).is_suffix_of("1a"));
+        assert!(!(|c: char| c.is_numeric()).is_suffix_of(""));
+
+        let haystack = "1aé2";
+        let mut searcher = (|c: char| c.is_alphabetic()).into_searcher(haystack);
+        let hs = searcher.haystack();
+        unsafe {
+            let m = searcher.next_match_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "é");
+            let m = searcher.next_reject_back().unwrap();
+            assert_eq!(<&str>::range_to_self(hs, m.0, m.1), "1");
+        }
+        assert_eq!(searcher.next_match(), None);
+    }
+
+    // Predicates built at runtime, eg. from a configuration
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_dyn_char_predicate() {
+        use self::string::CharPredicateSearcher;
+
+        fn delimiter(config: &str) -> Box<dyn Fn(char) -> bool + Send> {
+            match config {
+                "whitespace" => Box::new(char::is_whitespace),
+                "digits" => Box::new(|c: char| c.is_ascii_digit()),
+                chars => {
+                    let chars = chars.to_owned();
+                    Box::new(move |c| chars.contains(c))
+                }
+            }
+        }
+
+        let haystack = "a1b c;d";
+        assert_eq!(split(haystack, delimiter("whitespace")), vec!["a1b", "c;d"]);
+        assert_eq!(split(haystack, delimiter("digits")), vec!["a", "b c;d"]);
+        assert_eq!(split(haystack, delimiter(";1")), vec!["a", "b c", "d"]);
+        assert_eq!(rsplit(haystack, delimiter("digits")), vec!["b c;d", "a"]);
+
+        let mut calls = 0;
+        let mut counting = |c: char| {
+            calls += 1;
+            c == ' '
+        };
+        {
+            let predicate: &mut dyn FnMut(char) -> bool = &mut counting;
+            assert_eq!(split(haystack, predicate), vec!["a1b", "c;d"]);
+        }
+        assert_eq!(calls, haystack.len());
+
+        // The searcher holds the box it was given and calls through it
+        let predicate: Box<dyn FnMut(char) -> bool> = Box::new(|c| c == 'c');
+        let mut searcher: CharPredicateSearcher<Box<dyn FnMut(char) -> bool>> =
+            predicate.into_searcher(haystack);
+        assert!(searcher.next_match().is_some());
     }
 
 }